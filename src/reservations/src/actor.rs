@@ -0,0 +1,140 @@
+///////////////////////////////////////////////////////////////////////
+////////////////////////// Facility Actors ////////////////////////////
+///////////////////////////////////////////////////////////////////////
+
+// System:  An optional mode in which each facility runs in its own thread, owning its
+//          bookings. Users don't lock the facility but send book and cancel requests
+//          to it over a channel and receive the result on a reply channel.
+
+// Implementation:  The facility thread handles one request after the other, so the
+//                  requests never contend for the facility. The facility is still kept
+//                  behind an Arc and RwLock, as bookings reference their facility, but
+//                  only the facility thread writes to it; others may read it for reports.
+
+//                  Bookings are handled by the booking function of the basic system: they are
+//                  confirmed as long as the capacity allows and there are no vip displacements.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{AuditAction, AuditEntry, AuditReason, Booking, BookingError, BookingResult, BookingSkeleton, Facility, SharedClock, User, CANCELLED, CONFIRMED};
+use crate::basic::book_locked;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::sync::mpsc;
+
+/////////////////////// Messages /////////////////////
+
+// A request to book the facility from start to end for the user.
+pub struct BookRequest {
+    pub start: u32,
+    pub end: u32,
+    pub user: Arc<User>,
+    pub reply: mpsc::Sender<BookingResult>,
+}
+
+// A request to cancel the booking with the given id, the reply tells whether it was cancelled.
+pub struct CancelRequest {
+    pub booking_id: u64,
+    pub reply: mpsc::Sender<bool>,
+}
+
+pub enum FacilityMessage {
+    Book(BookRequest),
+    Cancel(CancelRequest),
+}
+
+// The handle to a facility thread. It can be cloned and shared, the facility thread
+// ends once all handles are dropped.
+#[derive(Clone)]
+pub struct FacilityHandle {
+    pub facility: Arc<RwLock<Facility>>,
+    inbox: mpsc::Sender<FacilityMessage>,
+}
+
+/////////////////////// Facility thread /////////////////////
+
+// This function moves the facility into its own thread and returns the handle to it.
+pub fn spawn_facility(fac: Facility, program_time: SharedClock) -> FacilityHandle {
+    let facility = Arc::new(RwLock::new(fac));
+    let (tx, rx) = mpsc::channel();
+
+    let facility_clone = facility.clone();
+    thread::spawn(move || {
+        for msg in rx {
+            match msg {
+                FacilityMessage::Book(req) => {
+                    let result = handle_book(&facility_clone, &req, &program_time);
+                    // the requester may have stopped waiting for the reply
+                    let _ = req.reply.send(result);
+                }
+                FacilityMessage::Cancel(req) => {
+                    let result = handle_cancel(&facility_clone, req.booking_id, &program_time);
+                    let _ = req.reply.send(result);
+                }
+            }
+        }
+    });
+
+    FacilityHandle { facility, inbox: tx }
+}
+
+// This function books the facility for a request, with the same checks as the basic system:
+// the permission of the user, the duration, the lead time, the horizon and the capacity.
+fn handle_book(facility_arc: &Arc<RwLock<Facility>>, req: &BookRequest, program_time: &SharedClock) -> BookingResult {
+    let booking = Arc::new(RwLock::new(Booking::new(req.start, req.end, facility_arc.clone(), req.user.clone())));
+    let mut booking_mut = booking.write().unwrap();
+    let mut facility = facility_arc.write().unwrap();
+    let result = book_locked(&booking, &mut booking_mut, &mut facility, program_time);
+    drop(booking_mut);
+    if matches!(result, BookingResult::Confirmed { .. } | BookingResult::Waitlisted { .. }) {
+        facility.publish();
+    }
+    result
+}
+
+// This function cancels the confirmed booking with the given id, if there is one,
+// and notifies its users once the facility is released again.
+fn handle_cancel(facility_arc: &Arc<RwLock<Facility>>, booking_id: u64, program_time: &SharedClock) -> bool {
+    let mut facility = facility_arc.write().unwrap();
+    let now = program_time.get_current_time();
+    let mut cancelled: Option<(Arc<RwLock<Booking>>, AuditEntry)> = None;
+    for b in facility.bookings() {
+        let mut bmut = b.write().unwrap();
+        if bmut.id == booking_id && bmut.status == CONFIRMED {
+            bmut.status = CANCELLED;
+            facility.cancelled(&bmut);
+            cancelled = Some((b.clone(), AuditEntry { time: now, action: AuditAction::Cancelled, user_id: bmut.user().id, start: bmut.start, end: bmut.end, reason: AuditReason::UserRequest }));
+            break;
+        }
+    }
+    match cancelled {
+        Some((booking, entry)) => {
+            facility.audit_log.push(entry);
+            facility.publish();
+            drop(facility);
+            booking.read().unwrap().notify_users(&booking);
+            true
+        }
+        None => false,
+    }
+}
+
+/////////////////////// Handle /////////////////////
+
+impl FacilityHandle {
+    // Requests the booking of the skeleton for the user, the result arrives on the returned receiver.
+    // The skeleton is expected to refer to the facility of this handle.
+    // Fails if the facility thread is gone, e.g. because it panicked.
+    pub fn book(&self, skeleton: &BookingSkeleton, user: Arc<User>) -> Result<mpsc::Receiver<BookingResult>, BookingError> {
+        let (reply, rx) = mpsc::channel();
+        self.inbox.send(FacilityMessage::Book(BookRequest { start: skeleton.start, end: skeleton.end, user, reply })).map_err(|_| BookingError::ChannelClosed)?;
+        Ok(rx)
+    }
+
+    // Requests the cancellation of the booking with the given id.
+    pub fn cancel(&self, booking_id: u64) -> Result<mpsc::Receiver<bool>, BookingError> {
+        let (reply, rx) = mpsc::channel();
+        self.inbox.send(FacilityMessage::Cancel(CancelRequest { booking_id, reply })).map_err(|_| BookingError::ChannelClosed)?;
+        Ok(rx)
+    }
+}
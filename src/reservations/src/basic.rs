@@ -0,0 +1,320 @@
+///////////////////////////////////////////////////////////////////////
+//////////////// Simple Reservations System (Task 1) //////////////////
+///////////////////////////////////////////////////////////////////////
+
+// System:  This program implements a simple reservations system.
+//          There are facilities (e.g. rooms or projectors) which
+//          can be booked by users. The facilities have a capacity
+//          and can only be booked if the capacity is not exceeded.
+
+// Implementation: Each facility is managed as a struct with a list
+//                 of bookings. When a user tries to book a certain facility
+//                 e.g. a room, the facility is locked and the list of bookings
+//                 is checked for overlaps. If the capacity is not exceeded,
+//                 the booking is added to the list of bookings and the success
+//                 is returned to the user.
+
+//                 Each booking has a start and end time and also references
+//                 the user and the facility, so the correct facility can easily
+//                 be accessed and user information be used.
+
+//                 Users run on the threads of a worker pool and try to book facilities, ressource
+//                 management is done based on Arcs, RwLocks and Rusts ownership system.
+
+//                 There are no vip users in this system, so no booking is ever cancelled
+//                 and bookings are confirmed as soon as they are added to the facility.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{check_request, conflicts, AuditAction, AuditEntry, AuditReason, Booking, BookingEvent, BookingResult, BookingSkeleton, Facility, OnConfirm, RejectReason, SharedClock, SharedSink, Stats, StatsSink, StdoutSink, User, WorkerPool, CONFIRMED, WAITLISTED};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::sync::mpsc;
+use std::thread;
+
+/////////////////////// User server /////////////////////
+
+// The number of worker threads the users are run on, unless given otherwise.
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+// This function starts the users on a pool of DEFAULT_POOL_SIZE worker threads. Each user is given a list of bookings
+// to try to book. The booking events are printed to stdout.
+// The returned stats are complete, as all users are done before returning.
+pub fn start_users(user_ids: Vec<u32>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock) -> Arc<RwLock<Stats>> {
+    start_users_with_sink(user_ids, bookings, program_time, Arc::new(StdoutSink))
+}
+
+// Same as start_users, but the booking events are handed to the given sink.
+pub fn start_users_with_sink(user_ids: Vec<u32>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock, sink: SharedSink) -> Arc<RwLock<Stats>> {
+    start_users_with_pool(user_ids, bookings, program_time, sink, DEFAULT_POOL_SIZE)
+}
+
+// Same as start_users_with_sink, but the users are run on a pool of the given number of worker threads.
+pub fn start_users_with_pool(user_ids: Vec<u32>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock, sink: SharedSink, pool_size: usize) -> Arc<RwLock<Stats>> {
+    start_users_on_pool(user_ids, bookings, program_time, sink, pool_size, None)
+}
+
+// Same as start_users_with_sink, but the hook is run for every booking that is confirmed.
+pub fn start_users_with_hook(user_ids: Vec<u32>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock, sink: SharedSink, on_confirm: OnConfirm) -> Arc<RwLock<Stats>> {
+    start_users_on_pool(user_ids, bookings, program_time, sink, DEFAULT_POOL_SIZE, Some(on_confirm))
+}
+
+fn start_users_on_pool(user_ids: Vec<u32>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock, sink: SharedSink, pool_size: usize, on_confirm: Option<OnConfirm>) -> Arc<RwLock<Stats>> {
+    // count all events of the users on the way to the sink
+    let stats = Arc::new(RwLock::new(Stats::default()));
+    let sink: SharedSink = Arc::new(StatsSink { stats: stats.clone(), inner: sink });
+
+    // hand the users to the pool
+    let pool = WorkerPool::new(pool_size);
+    for (i, user_id) in (1..=user_ids.len()).enumerate() {
+        // create the user, nobody ever sends cancellations here so the receiving end is dropped
+        let (tx, _) = mpsc::channel();
+        let user = Arc::new(User { id: user_id as u32, vip: false, adress: tx, allowed_facility_types: None });
+
+        // create list of bookings of the user from the booking skeletons
+        let mut user_bookings: Vec<Arc<RwLock<Booking>>> = Vec::new();
+        for booking in &bookings[i] {
+            let user = Arc::clone(&user);
+            let booking = Booking::from_skeleton(booking, user);
+            user_bookings.push(Arc::new(RwLock::new(booking)));
+        }
+
+        // get the user a reference to the program time, the sink and the hook
+        let program_time = Arc::clone(&program_time);
+        let sink = Arc::clone(&sink);
+        let on_confirm = on_confirm.clone();
+
+        // queue the user
+        pool.execute(move || {
+            run_user(Arc::new(user_bookings), program_time, sink, on_confirm);
+        });
+    }
+    // wait for all users to finish the respective task
+    pool.join();
+    stats
+}
+
+// This function runs a user. It tries to book the facilities in the list of bookings,
+// running the hook (if any) for each booking that is confirmed.
+pub fn run_user(to_book: Arc<Vec<Arc<RwLock<Booking>>>>, program_time: SharedClock, sink: SharedSink, on_confirm: Option<OnConfirm>) {
+    for b in to_book.iter() {
+        book_facility_with_hook(b.clone(), program_time.clone(), &sink, on_confirm.as_ref());
+        // now the user might react to the success of the booking
+    }
+}
+
+/////////////////////// FIFO mode /////////////////////
+
+// In the FIFO mode the users don't book the facilities themselves, but submit their booking requests
+// to a single arbiter thread. Each request carries a sequence number and the arbiter handles the requests
+// strictly in the order of these, so of two requests racing for the same slot the earlier one always wins.
+// The sequence numbers are handed out in rounds: the first booking of every user, then the second one, ...
+struct FifoRequest {
+    seq: u64,
+    booking: Arc<RwLock<Booking>>,
+    reply: mpsc::Sender<bool>,
+}
+
+// This function starts the users in the FIFO mode, each on its own thread. The booking events are printed to stdout.
+pub fn start_users_fifo(user_ids: Vec<u32>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock) -> Arc<RwLock<Stats>> {
+    start_users_fifo_with_sink(user_ids, bookings, program_time, Arc::new(StdoutSink))
+}
+
+// Same as start_users_fifo, but the booking events are handed to the given sink.
+// The returned stats are complete, as all users and the arbiter are done before returning.
+pub fn start_users_fifo_with_sink(user_ids: Vec<u32>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock, sink: SharedSink) -> Arc<RwLock<Stats>> {
+    // count all events of the users on the way to the sink
+    let stats = Arc::new(RwLock::new(Stats::default()));
+    let sink: SharedSink = Arc::new(StatsSink { stats: stats.clone(), inner: sink });
+
+    // hand out the sequence numbers round by round
+    let rounds = bookings.iter().map(|b| b.len()).max().unwrap_or(0);
+    let mut seqs: Vec<Vec<u64>> = vec![Vec::new(); bookings.len()];
+    let mut seq: u64 = 0;
+    for round in 0..rounds {
+        for (i, user_bookings) in bookings.iter().enumerate() {
+            if round < user_bookings.len() {
+                seqs[i].push(seq);
+                seq += 1;
+            }
+        }
+    }
+
+    // start the arbiter, it holds back the requests until all earlier ones are handled
+    let (tx, rx) = mpsc::channel::<FifoRequest>();
+    let arbiter_time = Arc::clone(&program_time);
+    let arbiter = thread::spawn(move || {
+        let mut pending: BTreeMap<u64, FifoRequest> = BTreeMap::new();
+        let mut next: u64 = 0;
+        for req in rx {
+            pending.insert(req.seq, req);
+            while let Some(req) = pending.remove(&next) {
+                let result = book_facility(req.booking, arbiter_time.clone(), &sink);
+                // the user may have stopped waiting for the reply
+                let _ = req.reply.send(result);
+                next += 1;
+            }
+        }
+    });
+
+    // start the users, each one waits for the result of a request before submitting the next
+    // (they can't share a worker pool, as a user waiting for its turn would block a worker)
+    let mut threads = Vec::new();
+    for (i, user_id) in user_ids.iter().enumerate() {
+        let (cancel_tx, _) = mpsc::channel();
+        let user = Arc::new(User { id: *user_id, vip: false, adress: cancel_tx, allowed_facility_types: None });
+        let user_bookings: Vec<(u64, Arc<RwLock<Booking>>)> = bookings[i].iter().zip(&seqs[i]).map(|(b, seq)| (*seq, Arc::new(RwLock::new(Booking::from_skeleton(b, user.clone()))))).collect();
+        let tx = tx.clone();
+        threads.push(thread::spawn(move || {
+            for (seq, booking) in user_bookings {
+                let (reply, result) = mpsc::channel();
+                if tx.send(FifoRequest { seq, booking, reply }).is_err() {
+                    break;
+                }
+                // now the user might react to the success of the booking
+                let _ = result.recv();
+            }
+        }));
+    }
+    drop(tx);
+
+    // wait for all users and then the arbiter to finish
+    for t in threads {
+        t.join().unwrap();
+    }
+    arbiter.join().unwrap();
+    stats
+}
+
+/////////////////////// Booking function /////////////////////
+
+// This function books a facility for a user at a given time, if available.
+// It locks the facility and alters the bookings list of the facility,
+// if possible. It returns true if the booking was successful and false otherwise.
+// It receives the respective RwLocks as arguments and reports what happened to the sink.
+pub fn book_facility(booking: Arc<RwLock<Booking>>, program_time: SharedClock, sink: &SharedSink) -> bool {
+    book_facility_with_hook(booking, program_time, sink, None)
+}
+
+// Same as book_facility, but if the booking is confirmed the hook is run on it afterwards.
+// The hook only gets a read lock on the booking, the facility is not locked anymore.
+pub fn book_facility_with_hook(booking: Arc<RwLock<Booking>>, program_time: SharedClock, sink: &SharedSink, on_confirm: Option<&OnConfirm>) -> bool {
+
+    // lock the booking
+    let mut booking_mut = booking.write().unwrap();
+
+    // lock the facility
+    let facility_arc = booking_mut.facility.clone();
+    let mut facility = facility_arc.write().unwrap();
+
+    let result = book_locked(&booking, &mut booking_mut, &mut facility, &program_time);
+
+    // report what happened while the facility is still locked
+    match &result {
+        // report User X couldn't book facility Y from time Z to time W - reason.
+        BookingResult::Rejected { reason } => sink.emit(&BookingEvent::Rejected { id: booking_mut.id, user_id: booking_mut.user().id, vip: booking_mut.user().vip, fac_type: facility.fac_type, start: booking_mut.start, end: booking_mut.end, reason: reason.clone() }),
+        BookingResult::RejectedEqualPriority => sink.emit(&BookingEvent::Rejected { id: booking_mut.id, user_id: booking_mut.user().id, vip: booking_mut.user().vip, fac_type: facility.fac_type, start: booking_mut.start, end: booking_mut.end, reason: RejectReason::EqualPriority }),
+        BookingResult::WaitlistFull => sink.emit(&BookingEvent::Rejected { id: booking_mut.id, user_id: booking_mut.user().id, vip: booking_mut.user().vip, fac_type: facility.fac_type, start: booking_mut.start, end: booking_mut.end, reason: RejectReason::WaitlistFull }),
+        BookingResult::NotPermitted => sink.emit(&BookingEvent::Rejected { id: booking_mut.id, user_id: booking_mut.user().id, vip: booking_mut.user().vip, fac_type: facility.fac_type, start: booking_mut.start, end: booking_mut.end, reason: RejectReason::NotPermitted }),
+        BookingResult::RejectedTooLong => sink.emit(&BookingEvent::Rejected { id: booking_mut.id, user_id: booking_mut.user().id, vip: booking_mut.user().vip, fac_type: facility.fac_type, start: booking_mut.start, end: booking_mut.end, reason: RejectReason::TooLong { max_duration: facility.max_duration.unwrap_or(0) } }),
+        BookingResult::RejectedTooSoon => sink.emit(&BookingEvent::Rejected { id: booking_mut.id, user_id: booking_mut.user().id, vip: booking_mut.user().vip, fac_type: facility.fac_type, start: booking_mut.start, end: booking_mut.end, reason: RejectReason::TooSoon { min_lead: facility.min_lead } }),
+        BookingResult::Waitlisted { .. } => sink.emit(&BookingEvent::Waitlisted { id: booking_mut.id, user_id: booking_mut.user().id, vip: booking_mut.user().vip, fac_type: facility.fac_type, start: booking_mut.start, end: booking_mut.end }),
+        BookingResult::Confirmed { .. } => sink.emit(&BookingEvent::Confirmed { id: booking_mut.id, user_id: booking_mut.user().id, vip: booking_mut.user().vip, fac_type: facility.fac_type, start: booking_mut.start, end: booking_mut.end }),
+    }
+    let confirmed = matches!(result, BookingResult::Confirmed { .. });
+    let changed = confirmed || matches!(result, BookingResult::Waitlisted { .. });
+    drop(booking_mut);
+    if changed {
+        facility.publish();
+    }
+    drop(facility);
+
+    // now the hook can look at the confirmed booking
+    if let (true, Some(on_confirm)) = (confirmed, on_confirm) {
+        on_confirm(&booking.read().unwrap());
+    }
+    confirmed
+}
+
+// Same as book_facility, but it never waits on a lock: if the booking or its facility is locked by someone
+// else (or poisoned), None is returned right away, so the caller can do other work and try again later.
+// Nothing is reported, the result tells what happened.
+pub fn try_book(booking: Arc<RwLock<Booking>>, program_time: SharedClock) -> Option<BookingResult> {
+    // lock the booking
+    let mut booking_mut = booking.try_write().ok()?;
+
+    // lock the facility
+    let facility_arc = booking_mut.facility.clone();
+    let mut facility = facility_arc.try_write().ok()?;
+
+    let result = book_locked(&booking, &mut booking_mut, &mut facility, &program_time);
+    drop(booking_mut);
+    if matches!(result, BookingResult::Confirmed { .. } | BookingResult::Waitlisted { .. }) {
+        facility.publish();
+    }
+    Some(result)
+}
+
+// This function does the actual booking, with both the booking and its facility locked by the caller.
+// It doesn't report anything, the result tells what happened.
+pub fn book_locked(booking: &Arc<RwLock<Booking>>, booking_mut: &mut Booking, facility: &mut Facility, program_time: &SharedClock) -> BookingResult {
+
+    // check if the user may book the facility at all, and if the booking is short enough for it,
+    // in the future, made far enough in advance and ends within the booking horizon
+    if let Err(reason) = check_request(facility, booking_mut.user(), booking_mut.start, booking_mut.end, program_time) {
+        return reason.into();
+    }
+    let current_time = program_time.get_current_time();
+
+    // check for possible overlaps of the booking with the confirmed bookings, cancelled ones don't take up room anymore,
+    // each of them takes up its units of the capacity
+    let mut overlaps = 0;
+    for b in facility.conflicting(booking_mut.start, booking_mut.end) {
+        let b = b.read().unwrap();
+        if b.status == CONFIRMED && conflicts(&b, booking_mut.start, booking_mut.end, facility.buffer) {
+            overlaps += b.units;
+        }
+    }
+    // if the capacity is exceeded, queue the booking on the waitlist if there is room on it, otherwise decline it
+    if facility.is_full_for(overlaps, booking_mut.units, facility.capacity_between(booking_mut.start, booking_mut.end)) {
+        if !facility.waitlist_enabled {
+            return BookingResult::Rejected { reason: RejectReason::CapacityExceeded };
+        }
+        if facility.max_waitlist.is_some_and(|max| facility.waitlist.len() >= max) {
+            return BookingResult::WaitlistFull;
+        }
+        booking_mut.status = WAITLISTED;
+        facility.waitlist.push(booking.clone());
+        return BookingResult::Waitlisted { id: booking_mut.id };
+    }
+
+    // here the booking can be done
+    booking_mut.created_at = current_time;
+    facility.add_booking(booking.clone(), booking_mut.start, booking_mut.end);
+    facility.audit_log.push(AuditEntry { time: current_time, action: AuditAction::Booked, user_id: booking_mut.user().id, start: booking_mut.start, end: booking_mut.end, reason: AuditReason::UserRequest });
+    facility.audit_log.push(AuditEntry { time: current_time, action: AuditAction::Confirmed, user_id: booking_mut.user().id, start: booking_mut.start, end: booking_mut.end, reason: AuditReason::UserRequest });
+
+    // nothing can cancel the booking, so it is confirmed right away
+    booking_mut.status = CONFIRMED;
+    BookingResult::Confirmed { id: booking_mut.id }
+}
+
+/////////////////////// Batch booking /////////////////////
+
+// This function tries to book all the skeletons for the user, one after the other, without reporting anything.
+// It returns the outcome of each request together with the index of its skeleton, in the order of the skeletons.
+pub fn book_batch(user: Arc<User>, skeletons: &[BookingSkeleton], program_time: SharedClock) -> Vec<(usize, BookingResult)> {
+    let mut results: Vec<(usize, BookingResult)> = Vec::new();
+    for (i, skeleton) in skeletons.iter().enumerate() {
+        let booking = Arc::new(RwLock::new(Booking::from_skeleton(skeleton, user.clone())));
+        let mut booking_mut = booking.write().unwrap();
+        let mut facility = skeleton.facility.write().unwrap();
+        let result = book_locked(&booking, &mut booking_mut, &mut facility, &program_time);
+        drop(booking_mut);
+        if matches!(result, BookingResult::Confirmed { .. } | BookingResult::Waitlisted { .. }) {
+            facility.publish();
+        }
+        results.push((i, result));
+    }
+    results
+}
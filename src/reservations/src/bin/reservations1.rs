@@ -0,0 +1,41 @@
+///////////////////////////////////////////////////////////////////////
+//////////////// Simple Reservations System (Task 1) //////////////////
+///////////////////////////////////////////////////////////////////////
+
+// The system is implemented in the basic module of the reservations library,
+// this binary runs its example scenario.
+
+use reservations::basic::start_users;
+use reservations::{load_scenario, start_program_time, BookingSkeleton, Facility, PROJECTOR, ROOM};
+use std::sync::{Arc, RwLock};
+
+/////////////////////// Main | initial tests /////////////////////
+
+fn main() {
+
+    // start program time
+    let program_time = start_program_time();
+    println!("=========== Program started ===========");
+
+    // run the scenario of the json file given as argument, or the example scenario
+    if let Some(path) = std::env::args().nth(1) {
+        let (_facilities, bookings, user_ids, _user_stati) = load_scenario(&path);
+        start_users(user_ids, bookings, program_time.clone());
+    } else {
+        // create the facilities with respective references on RwLocks
+        let rooms = Facility::new(ROOM, 2);
+        let projectors = Facility::new(PROJECTOR, 2);
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+    
+        // some example bookings
+        let usr1_bookings = vec![BookingSkeleton::new(1, 2, rooms_arc.clone()), BookingSkeleton::new(2, 4, rooms_arc.clone()), BookingSkeleton::new(1, 2, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(1, 2, projectors_arc.clone()), BookingSkeleton::new(1, 3, projectors_arc.clone())];
+        let usr3_bookings = vec![BookingSkeleton::new(1, 2, rooms_arc.clone()), BookingSkeleton::new(1, 5, projectors_arc.clone())];
+    
+        // start the users
+        start_users(vec![1, 2, 3], vec![usr1_bookings, usr2_bookings, usr3_bookings], program_time.clone());
+    }
+
+    println!("=========== Program ended ===========");
+}
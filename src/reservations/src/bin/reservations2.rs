@@ -0,0 +1,80 @@
+///////////////////////////////////////////////////////////////////////
+//////////////// Simple Reservations System (Task 2) //////////////////
+///////////////////////////////////////////////////////////////////////
+
+// The system is implemented in the vip module of the reservations library,
+// this binary runs its example scenario and shows the reports on it.
+// With --users the scenario is built from the command line instead, e.g.
+// reservations2 --room-capacity 1 --runtime-secs 3 --users 2:vip --users 3:nonvip
+
+use reservations::vip::{example_scenario, start_users_with_sink};
+use reservations::{book_n_of, cli_scenario, export_audit_ndjson, free_windows, parse_args, print_user_summary, start_program_time, utilization, Facility, SharedSink, StyledStdoutSink, User, VictimPolicy, PROJECTOR, ROOM};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+use std::sync::mpsc;
+
+/////////////////////// Main | initial tests /////////////////////
+
+fn main() {
+    // read the configuration of the run
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match parse_args(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("usage: reservations2 [--room-capacity N] [--projector-capacity N] [--runtime-secs N] [--users N:vip|nonvip]... [--log-style emoji|plain|json]");
+            std::process::exit(2);
+        }
+    };
+
+    // start program time
+    let program_time = start_program_time();
+    println!("=========== Program started ===========");
+
+    // create facilities and bookings, from the command line if users are given,
+    // otherwise the example bookings
+    let (rooms_arc, projectors_arc, user_ids, user_stati, bookings) = if config.users.is_empty() {
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, config.room_capacity)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, config.projector_capacity)));
+        let (user_ids, user_stati, bookings) = example_scenario(&rooms_arc, &projectors_arc);
+        (rooms_arc, projectors_arc, user_ids, user_stati, bookings)
+    } else {
+        let (facilities, bookings, user_ids, user_stati) = cli_scenario(&config);
+        (facilities[0].clone(), facilities[1].clone(), user_ids, user_stati, bookings)
+    };
+    projectors_arc.write().unwrap().victim_policy = VictimPolicy::LeastRemainingTime;
+
+    // start the users, the events are printed in the configured style
+    let sink: SharedSink = Arc::new(StyledStdoutSink { style: config.log_style });
+    let users = start_users_with_sink(user_ids.clone(), user_stati, bookings, program_time.clone(), sink.clone()).expect("the scenario gives each user a status and bookings");
+    let stats = users.stats.clone();
+
+    // give the users the runtime to book and receive their cancellations,
+    // then shut them down and wait for all of them to be done
+    thread::sleep(Duration::from_secs(config.runtime_secs));
+    users.join_all();
+
+    // a workshop needs any one of the facilities later on
+    let (tx, _rx) = mpsc::channel();
+    let workshop = Arc::new(User { id: 6, vip: false, adress: tx, allowed_facility_types: None });
+    if let Err(e) = book_n_of(&[rooms_arc.clone(), projectors_arc.clone()], 1, 200, 210, workshop, program_time.clone(), &sink) {
+        println!("❌: Workshop booking failed: {:?}", e);
+    }
+
+    // print the statistics of the run, overall and per user
+    println!("Stats: {:?}", *stats.read().unwrap());
+    print_user_summary(&[rooms_arc.clone(), projectors_arc.clone()], &user_ids);
+
+    // print how busy the facilities were over the example window
+    println!("Room utilization: {:.2}", utilization(&rooms_arc, 1, 2));
+    println!("Projector utilization: {:.2}", utilization(&projectors_arc, 1, 2));
+
+    // print when the rooms are still free later on
+    println!("Free room windows: {:?}", free_windows(&rooms_arc, 0, 300, 10));
+
+    // export the audit log of the rooms as newline-delimited json
+    print!("{}", export_audit_ndjson(rooms_arc.read().unwrap().audit_log()));
+
+    println!("=========== Program ended ===========");
+}
\ No newline at end of file
@@ -0,0 +1,47 @@
+///////////////////////////////////////////////////////////////////////
+//////////////// Simple Reservations System (Task 3) //////////////////
+///////////////////////////////////////////////////////////////////////
+
+// The system is implemented in the compound module of the reservations library,
+// this binary runs its example scenario.
+
+use reservations::compound::start_users;
+use reservations::{load_scenario, start_program_time, start_reaper, BookingSkeleton, Facility, StdoutSink, PROJECTOR, ROOM};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+/////////////////////// Main | initial tests /////////////////////
+
+fn main() {
+
+    // start program time
+    let program_time = start_program_time();
+    println!("=========== Program started ===========");
+
+    // run the scenario of the json file given as argument, or the example scenario
+    if let Some(path) = std::env::args().nth(1) {
+        let (_facilities, bookings, user_ids, user_stati) = load_scenario(&path);
+        start_users(user_ids, user_stati, bookings, program_time.clone());
+    } else {
+        // create facilities
+        let rooms = Facility::new(ROOM, 2);
+        let projectors = Facility::new(PROJECTOR, 2);
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+
+        // cancel parts of compounds that stay pending for more than 50 ticks
+        start_reaper(vec![rooms_arc.clone(), projectors_arc.clone()], 50, program_time.clone(), Arc::new(StdoutSink));
+
+        // create example bookings
+        let usr1_bookings = vec![BookingSkeleton::new(1, 2, rooms_arc.clone()), BookingSkeleton::new(1, 2, projectors_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(1, 2, rooms_arc.clone()), BookingSkeleton::new(1, 2, projectors_arc.clone())];
+        let usr3_bookings = vec![BookingSkeleton::new(1, 2, rooms_arc.clone())];
+        start_users(vec![1, 2, 3], vec![false, false, true], vec![usr1_bookings, usr2_bookings, usr3_bookings], program_time.clone());
+    }
+
+    // wait for 10 seconds; in the real world this system would just run forever
+    thread::sleep(Duration::from_secs(10));
+
+    println!("=========== Program ended ===========");
+}
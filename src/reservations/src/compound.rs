@@ -0,0 +1,553 @@
+///////////////////////////////////////////////////////////////////////
+//////////////// Simple Reservations System (Task 3) //////////////////
+///////////////////////////////////////////////////////////////////////
+
+// System:  Bookings can compared to the previous version be made in
+//          compounds consisting of different resources (e.g. a room and a projector).
+//          A compound is only booked if all required parts of it are possible,
+//          optional parts that aren't possible are skipped.
+//          If a part of a compound has to be cancelled due to a VIP request
+//          the whole compound is cancelled and the user notified on all
+//          necessary cancellations.
+
+// Implementation:  Compounds can only be booked sequentially (see comment in the code).
+//                  For all parts of the compound it is checked whether they are possible
+//                  and if necessary what bookings have to be cancelled, with the facilities
+//                  of the compound locked up front in the order of their ids.
+//                  If a cancellation is necessary, the booking is added to
+//                  a list of bookings to be cancelled. The check and the actions after it
+//                  are bundled in a compound transaction that holds the parts until it is
+//                  committed or aborted.
+
+//                  Each booking now also references its compound, allowing for
+//                  the cancellation of all bookings in the compound if one of
+//                  them has to be cancelled.
+
+//                  The respective actions after the check are done in the user
+//                  thread; note that a server-client architecture as in a message
+//                  passing system where only the server makes changes is not necessary
+//                  here (the actor module provides such a mode for single facilities).
+
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{conflicts, overlapping_confirmed, select_victim_excluding, wait_for_cancellations, AuditAction, AuditEntry, AuditReason, Booking, BookingEvent, BookingSkeleton, Facility, RejectReason, SharedClock, SharedSink, Stats, StatsSink, StdoutSink, User, Users, VipPolicy, CANCELLED, CONFIRMED, UNCONFIRMED};
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+use std::sync::mpsc;
+
+/////////////////////// User server /////////////////////
+
+// A compound is the list of bookings a user wants to make together.
+pub type Compound = Vec<BookingSkeleton>;
+
+
+// Notice that as of cancellation and the vip system a booking of any ressource (e.g. projector) can sideeffect any other ressource
+// as in the compound of the cancelled e.g. projector everything else can be. Now, compounds have to be booked as an atomic unit
+// because otherwise conflicts can arise, so bookings of compounds have to be done sequentially.
+
+// The booking events are printed to stdout.
+// The stats of the returned users are filled in by the user threads as they go.
+pub fn start_users(user_ids: Vec<u32>, user_stati: Vec<bool>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock) -> Users {
+    start_users_with_sink(user_ids, user_stati, bookings, program_time, Arc::new(StdoutSink))
+}
+
+// Same as start_users, but the booking events are handed to the given sink.
+pub fn start_users_with_sink(user_ids: Vec<u32>, user_stati: Vec<bool>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock, sink: SharedSink) -> Users {
+    let user_atomic = vec![true; user_ids.len()];
+    start_users_with_atomic(user_ids, user_stati, user_atomic, bookings, program_time, sink)
+}
+
+// Same as start_users_with_sink, but for each user it is given whether the compound is booked
+// all or nothing (atomic), or whether each part that is possible is booked on its own.
+pub fn start_users_with_atomic(user_ids: Vec<u32>, user_stati: Vec<bool>, user_atomic: Vec<bool>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock, sink: SharedSink) -> Users {
+    let user_required = bookings.iter().map(|b| vec![true; b.len()]).collect();
+    start_users_with_required(user_ids, user_stati, user_atomic, user_required, bookings, program_time, sink)
+}
+
+// Same as start_users_with_atomic, but for each part of each compound it is given whether it is required.
+// A compound whose optional parts aren't possible is still booked, without these parts.
+pub fn start_users_with_required(user_ids: Vec<u32>, user_stati: Vec<bool>, user_atomic: Vec<bool>, user_required: Vec<Vec<bool>>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock, sink: SharedSink) -> Users {
+    let user_required = user_required.into_iter().map(|r| vec![r]).collect();
+    let user_compounds = bookings.into_iter().map(|b| vec![b]).collect();
+    start_users_with_parts(user_ids, user_stati, user_atomic, user_required, user_compounds, program_time, sink)
+}
+
+// Same as start_users_with_sink, but each user books several compounds one after the other.
+// Each compound is booked all or nothing, independently of the other compounds of the user.
+pub fn start_users_with_compounds(user_ids: Vec<u32>, user_stati: Vec<bool>, user_compounds: Vec<Vec<Compound>>, program_time: SharedClock, sink: SharedSink) -> Users {
+    let user_atomic = vec![true; user_ids.len()];
+    let user_required = user_compounds.iter().map(|c| c.iter().map(|b| vec![true; b.len()]).collect()).collect();
+    start_users_with_parts(user_ids, user_stati, user_atomic, user_required, user_compounds, program_time, sink)
+}
+
+fn start_users_with_parts(user_ids: Vec<u32>, user_stati: Vec<bool>, user_atomic: Vec<bool>, user_required: Vec<Vec<Vec<bool>>>, user_compounds: Vec<Vec<Compound>>, program_time: SharedClock, sink: SharedSink) -> Users {
+    // count all events of the users on the way to the sink
+    let stats = Arc::new(RwLock::new(Stats::default()));
+    let sink: SharedSink = Arc::new(StatsSink { stats: stats.clone(), inner: sink });
+
+    // Following the note above, we make sure that only once compound is booked at a time
+    // using this Arc to a RwLock signaling if a compound is currently in process.
+    let compound_in_process = Arc::new(RwLock::new(false));
+
+    // tells the users to stop waiting for cancellation messages
+    let shutdown = Arc::new(RwLock::new(false));
+
+    let threads: Vec<_> = (1..=user_ids.len()).enumerate().map(|(i, user_id)| {
+
+        // create a channel for the reception and transmission of cancellation messages
+        let (tx, rx) = mpsc::channel();
+        let user = Arc::new(User { id: user_id as u32, vip: user_stati[i], adress: tx, allowed_facility_types: None });
+
+        // create the lists of bookings of the compounds of the user from the booking skeletons
+        let atomic = user_atomic[i];
+        let mut compounds: Vec<Arc<Vec<Arc<RwLock<Booking>>>>> = Vec::new();
+        for (c, compound) in user_compounds[i].iter().enumerate() {
+            let mut user_bookings: Vec<Arc<RwLock<Booking>>> = Vec::new();
+            for (j, booking) in compound.iter().enumerate() {
+                let user = Arc::clone(&user);
+                let mut booking = Booking::from_skeleton(booking, user);
+                booking.required = user_required[i][c][j];
+                user_bookings.push(Arc::new(RwLock::new(booking)));
+            }
+            let user_bookings = Arc::new(user_bookings);
+
+            // make each booking aware of the compound it is part of,
+            // unless the parts are booked independently of each other
+            if atomic {
+                for booking in user_bookings.iter() {
+                    let mut booking_mut = booking.write().unwrap();
+                    booking_mut.compound = Some(user_bookings.clone());
+                }
+            }
+            compounds.push(user_bookings);
+        }
+
+        // get the user a reference to the program time
+        let program_time = Arc::clone(&program_time);
+
+        let compound_in_process = Arc::clone(&compound_in_process);
+        let sink = Arc::clone(&sink);
+        let shutdown = Arc::clone(&shutdown);
+
+        // start the user
+        // the outcomes of the compounds are only reported to the sink
+        thread::spawn(move || {
+            run_user(compounds, atomic, program_time, rx, compound_in_process, shutdown, sink);
+            Vec::new()
+        })
+    }).collect();
+    Users::new(stats, threads, shutdown)
+}
+
+// This function runs a user booking its compounds one after the other, each all or nothing if atomic is set.
+pub fn run_user(compounds: Vec<Arc<Vec<Arc<RwLock<Booking>>>>>, atomic: bool, program_time: SharedClock, inbox: mpsc::Receiver<Arc<RwLock<Booking>>>, compound_in_process: Arc<RwLock<bool>>, shutdown: Arc<RwLock<bool>>, sink: SharedSink) {
+    for to_book in compounds.iter().filter(|c| !c.is_empty()) {
+        // a compound conflicting with itself can never be booked, it is rejected before any facility is touched
+        if reject_self_overlapping(to_book, &sink) {
+            continue;
+        }
+        book_compound(to_book, atomic, &program_time, &compound_in_process, &sink);
+    }
+
+    // wait for cancellation messages until the users are shut down
+    wait_for_cancellations(inbox, shutdown, &sink);
+}
+
+// This function rejects the compound if some of its parts overlap other parts for the same facility
+// beyond the capacity of the facility, reporting these parts, and tells whether it did so.
+// The facilities are only read.
+fn reject_self_overlapping(to_book: &Arc<Vec<Arc<RwLock<Booking>>>>, sink: &SharedSink) -> bool {
+    let parts: Vec<RwLockReadGuard<Booking>> = to_book.iter().map(|b| b.read().unwrap()).collect();
+    let overlapping: Vec<&RwLockReadGuard<Booking>> = parts.iter().filter(|b| {
+        let facility = b.facility.read().unwrap();
+        // the units of the part itself are counted as well
+        let same: u32 = parts.iter().filter(|o| Arc::ptr_eq(&o.facility, &b.facility) && conflicts(o, b.start, b.end, facility.buffer)).map(|o| o.units).sum();
+        facility.is_full(same - 1, facility.capacity_between(b.start, b.end))
+    }).collect();
+    if overlapping.is_empty() {
+        return false;
+    }
+
+    for b in overlapping {
+        sink.emit(&BookingEvent::Rejected { id: b.id, user_id: b.user().id, vip: b.user().vip, fac_type: b.facility.read().unwrap().fac_type, start: b.start, end: b.end, reason: RejectReason::SelfOverlap });
+    }
+    sink.emit(&BookingEvent::CompoundRejected { user_id: parts[0].user().id, vip: parts[0].user().vip });
+    true
+}
+
+// This function books a single compound of a user, all or nothing if atomic is set.
+fn book_compound(to_book: &Arc<Vec<Arc<RwLock<Booking>>>>, atomic: bool, program_time: &SharedClock, compound_in_process: &Arc<RwLock<bool>>, sink: &SharedSink) {
+    // only one compound is booked at a time, so we lock the compound_in_process here
+    let mut compound_in_process = compound_in_process.write().unwrap();
+    // and set it to true
+    *compound_in_process = true;
+
+    // hold all parts of the compound that are possible
+    let transaction = CompoundTransaction::begin(to_book.clone(), program_time.clone());
+    let possible = transaction.possible();
+
+    // if the compound is possible, book all of its parts and cancel the conflicting bookings;
+    // if the compound is not atomic, the same is done for the possible parts;
+    // otherwise the holds are released and nothing of the compound is booked
+    if possible || !atomic {
+        transaction.commit(sink);
+    }
+    else {
+        transaction.abort();
+    }
+
+    let user_id = to_book[0].read().unwrap().user().id;
+    let vip = to_book[0].read().unwrap().user().vip;
+    if possible {
+        // report the compound success
+        sink.emit(&BookingEvent::CompoundConfirmed { user_id, vip });
+    }
+    else {
+        // report the failure
+        sink.emit(&BookingEvent::CompoundRejected { user_id, vip });
+    }
+
+    // set compound_in_process to false
+    *compound_in_process = false;
+} // here the compound_in_process lock is released and the next compound can be booked
+
+/////////////////////// Compound transaction /////////////////////
+
+// A compound booking in two steps: begin holds every possible part in its facility, commit then
+// confirms the held parts and cancels the conflicting bookings, abort releases the holds again.
+// A transaction dropped without commit is aborted. Compounds are still booked one at a time,
+// so the caller has to hold the compound_in_process lock for as long as the transaction is open.
+pub struct CompoundTransaction {
+    parts: Arc<Vec<Arc<RwLock<Booking>>>>,
+    results: Vec<Result<(), RejectReason>>,
+    cancel_list: Vec<Arc<RwLock<Booking>>>,
+    program_time: SharedClock,
+    done: bool,
+}
+
+impl CompoundTransaction {
+    // Checks all parts of the compound and holds the possible ones, i.e. pushes them
+    // unconfirmed to their facilities, noting the bookings they would displace.
+    pub fn begin(parts: Arc<Vec<Arc<RwLock<Booking>>>>, program_time: SharedClock) -> CompoundTransaction {
+        let mut results: Vec<Result<(), RejectReason>> = Vec::new();
+        let mut cancel_list: Vec<Arc<RwLock<Booking>>> = Vec::new();
+
+        // The facilities of the compound are locked up front, each once and in the order of their ids,
+        // and all parts are checked in this thread while holding them. Taking the locks in one total
+        // order means compounds referencing the same facilities in a different order cannot deadlock.
+        let facilities = facilities_of(&parts);
+        let mut guards: Vec<RwLockWriteGuard<Facility>> = facilities.iter().map(|f| f.write().unwrap()).collect();
+
+        // we go over all bookings of the compound and check if they are bookable
+        // and what cancellations would have to be made
+        for b in parts.iter() {
+            let g = facilities.iter().position(|f| Arc::ptr_eq(f, &b.read().unwrap().facility)).unwrap();
+            let (result, to_cancel) = check_locked(b, &mut b.write().unwrap(), &mut guards[g], &program_time, &cancel_list);
+
+            // if a cancellation is necessary for a possible part, add it to the cancel list
+            if let (Ok(()), Some(b)) = (&result, to_cancel) {
+                cancel_list.push(b);
+            }
+            results.push(result);
+        }
+
+        CompoundTransaction { parts, results, cancel_list, program_time, done: false }
+    }
+
+    // The outcome of the check of each part, in the order of the parts.
+    pub fn results(&self) -> &[Result<(), RejectReason>] {
+        &self.results
+    }
+
+    // Tells whether the compound can be booked, only required parts decide on it.
+    pub fn possible(&self) -> bool {
+        self.parts.iter().zip(&self.results).all(|(b, result)| result.is_ok() || !b.read().unwrap().required)
+    }
+
+    // Confirms the held parts and cancels the bookings they displace, together with the
+    // compounds of these. If the compound is possible, the parts not held are done with,
+    // so a later cancellation of the compound passes them by. All facilities the commit changes
+    // are locked up front in the order of their ids, and the bookings after them.
+    pub fn commit(mut self, sink: &SharedSink) {
+        self.done = true;
+        let possible = self.possible();
+        let now = self.program_time.get_current_time();
+
+        // the bookings the commit changes: the parts, the bookings they displace and the other bookings
+        // of the compounds of these (the compound of a booking never changes, so it can be read upfront)
+        let mut changed: Vec<Arc<RwLock<Booking>>> = self.parts.to_vec();
+        let mut pending: Vec<Arc<RwLock<Booking>>> = self.cancel_list.clone();
+        while let Some(b) = pending.pop() {
+            if changed.iter().any(|c| Arc::ptr_eq(c, &b)) {
+                continue;
+            }
+            if let Some(compound) = &b.read().unwrap().compound {
+                pending.extend(compound.iter().cloned());
+            }
+            changed.push(b);
+        }
+
+        // lock their facilities
+        let facilities = facilities_of(&changed);
+        let mut guards: Vec<RwLockWriteGuard<Facility>> = facilities.iter().map(|f| f.write().unwrap()).collect();
+        let position = |b: &Booking| facilities.iter().position(|f| Arc::ptr_eq(f, &b.facility)).unwrap();
+
+        for (b, result) in self.parts.iter().zip(&self.results) {
+            let mut bmut = b.write().unwrap();
+            let facility = &mut guards[position(&bmut)];
+            if result.is_ok() {
+                bmut.status = CONFIRMED;
+                facility.audit_log.push(AuditEntry { time: bmut.created_at, action: AuditAction::Booked, user_id: bmut.user().id, start: bmut.start, end: bmut.end, reason: AuditReason::UserRequest });
+                facility.audit_log.push(AuditEntry { time: now, action: AuditAction::Confirmed, user_id: bmut.user().id, start: bmut.start, end: bmut.end, reason: AuditReason::UserRequest });
+            }
+            else if possible {
+                bmut.status = CANCELLED;
+                facility.cancelled(&bmut);
+            }
+        }
+
+        // cancel all bookings in the cancel list as of the vip, and the other bookings of their compounds
+        // as of the compound failing; the bookings are taken from a stack, the other parts of a compound
+        // right after the booking bringing it down. Each booking is processed once, however many compounds
+        // and cancel list entries lead to it, so the cascade ends after at most one step per booking.
+        let mut visited: HashSet<u64> = HashSet::new();
+        let mut notified: Vec<Arc<RwLock<Booking>>> = Vec::new();
+        let mut stack: Vec<(Arc<RwLock<Booking>>, AuditReason)> = self.cancel_list.drain(..).rev().map(|b| (b, AuditReason::VipBooking)).collect();
+        while let Some((b, reason)) = stack.pop() {
+            let mut bmut = b.write().unwrap();
+            if !visited.insert(bmut.id) {
+                continue;
+            }
+
+            // cancel the booking, unless it is cancelled already
+            if bmut.status != CANCELLED {
+                bmut.status = CANCELLED;
+                let facility = &mut guards[position(&bmut)];
+                let (user_id, vip, fac_type) = (bmut.user().id, bmut.user().vip, facility.fac_type);
+                sink.emit(&match reason {
+                    AuditReason::VipBooking => BookingEvent::CancelledByVip { id: bmut.id, user_id, vip, fac_type, start: bmut.start, end: bmut.end },
+                    _ => BookingEvent::CancelledCompoundFailure { id: bmut.id, user_id, vip, fac_type, start: bmut.start, end: bmut.end },
+                });
+                facility.audit_log.push(AuditEntry { time: now, action: AuditAction::Cancelled, user_id, start: bmut.start, end: bmut.end, reason });
+                facility.cancelled(&bmut);
+                notified.push(b.clone());
+            }
+
+            // the other bookings of its compound follow
+            if let Some(compound) = &bmut.compound {
+                for sibling in compound.iter().rev() {
+                    if !Arc::ptr_eq(sibling, &b) {
+                        stack.push((sibling.clone(), AuditReason::CompoundFailure));
+                    }
+                }
+            }
+        }
+
+        // report the success or failure of all bookings in the compound while the facilities are still locked
+        for (b, result) in self.parts.iter().zip(&self.results) {
+            let b = b.read().unwrap();
+            let fac_type = guards[position(&b)].fac_type;
+            match result {
+                Ok(()) => sink.emit(&BookingEvent::Confirmed { id: b.id, user_id: b.user().id, vip: b.user().vip, fac_type, start: b.start, end: b.end }),
+                Err(reason) => sink.emit(&BookingEvent::Rejected { id: b.id, user_id: b.user().id, vip: b.user().vip, fac_type, start: b.start, end: b.end, reason: reason.clone() }),
+            }
+        }
+
+        // publish the bookings of all facilities the compound changed, once no booking is locked anymore,
+        // and notify the owners of the cancelled bookings once the facilities are released
+        for facility in guards.iter_mut() {
+            facility.publish();
+        }
+        drop(guards);
+        for b in &notified {
+            b.read().unwrap().notify_users(b);
+        }
+    }
+
+    // Releases the holds, the facilities and the displaced bookings are left as they were.
+    pub fn abort(mut self) {
+        self.release();
+    }
+
+    // This function takes the held parts off their facilities again, they stay unconfirmed.
+    fn release(&mut self) {
+        self.done = true;
+        for (b, result) in self.parts.iter().zip(&self.results) {
+            if result.is_ok() {
+                let facility_arc = b.read().unwrap().facility.clone();
+                let mut facility = facility_arc.write().unwrap();
+                facility.remove_booking(b);
+                facility.publish();
+            }
+        }
+    }
+}
+
+impl Drop for CompoundTransaction {
+    fn drop(&mut self) {
+        if !self.done {
+            self.release();
+        }
+    }
+}
+
+/////////////////////// Compound cancellation /////////////////////
+
+// This function cancels the whole compound the given booking is part of on request of its user,
+// or only the booking itself if it isn't part of a compound. The cancellations are recorded in the audit logs,
+// the owners of the cancelled bookings are notified and the number of bookings cancelled is returned. The facilities of
+// the members are locked up front in the order of their ids and the members after them, one after the other;
+// the given booking must not be locked by the caller. The owners are only notified once the facilities are released.
+pub fn cancel_compound(any_member: &Arc<RwLock<Booking>>, program_time: SharedClock) -> u32 {
+    let members = match &any_member.read().unwrap().compound {
+        Some(compound) => compound.clone(),
+        None => Arc::new(vec![any_member.clone()]),
+    };
+
+    // lock the facilities
+    let facilities = facilities_of(&members);
+    let mut guards: Vec<RwLockWriteGuard<Facility>> = facilities.iter().map(|f| f.write().unwrap()).collect();
+
+    let now = program_time.get_current_time();
+    let mut cancelled: Vec<Arc<RwLock<Booking>>> = Vec::new();
+    for b in members.iter() {
+        let mut bmut = b.write().unwrap();
+        if bmut.status == CANCELLED {
+            continue;
+        }
+        bmut.status = CANCELLED;
+        let facility = &mut guards[facilities.iter().position(|f| Arc::ptr_eq(f, &bmut.facility)).unwrap()];
+        facility.audit_log.push(AuditEntry { time: now, action: AuditAction::Cancelled, user_id: bmut.user().id, start: bmut.start, end: bmut.end, reason: AuditReason::UserRequest });
+        facility.cancelled(&bmut);
+        cancelled.push(b.clone());
+    }
+
+    // publish the bookings and release the facilities before the owners are notified
+    for facility in guards.iter_mut() {
+        facility.publish();
+    }
+    drop(guards);
+    for b in &cancelled {
+        b.read().unwrap().notify_users(b);
+    }
+    cancelled.len() as u32
+}
+
+// This function returns the facilities of the bookings, each once and in the order of their ids,
+// which is the order the facilities of a compound are locked in.
+fn facilities_of(bookings: &[Arc<RwLock<Booking>>]) -> Vec<Arc<RwLock<Facility>>> {
+    let mut facilities: Vec<Arc<RwLock<Facility>>> = Vec::new();
+    for b in bookings {
+        let facility = b.read().unwrap().facility.clone();
+        if !facilities.iter().any(|f| Arc::ptr_eq(f, &facility)) {
+            facilities.push(facility);
+        }
+    }
+    facilities.sort_by_key(|f| f.read().unwrap().id);
+    facilities
+}
+
+/////////////////////// Booking checker /////////////////////
+
+// This function checks if a booking is possible, or why not, and if necessary what conflicting booking has to be cancelled.
+pub fn check_facility(booking: Arc<RwLock<Booking>>, program_time: SharedClock) -> (Result<(), RejectReason>, Option<Arc<RwLock<Booking>>>) {
+    // lock the booking, from now on it is pending until the compound is decided
+    let mut booking_mut = booking.write().unwrap();
+
+    // lock the facility
+    let facility_arc = booking_mut.facility.clone();
+    let mut facility = facility_arc.write().unwrap();
+
+    let (result, to_cancel) = check_locked(&booking, &mut booking_mut, &mut facility, &program_time, &[]);
+    if result.is_ok() {
+        facility.audit_log.push(AuditEntry { time: booking_mut.created_at, action: AuditAction::Booked, user_id: booking_mut.user().id, start: booking_mut.start, end: booking_mut.end, reason: AuditReason::UserRequest });
+    }
+    (result, to_cancel)
+}
+
+// The check of check_facility, for a booking and its facility both locked by the caller.
+// The pushed booking is not logged here, as a held part of a transaction is only logged on commit.
+// The chosen bookings are those earlier parts of the compound displace already, they no longer count
+// and can't be displaced a second time, so each part needs a victim of its own.
+fn check_locked(booking: &Arc<RwLock<Booking>>, booking_read: &mut Booking, facility: &mut Facility, program_time: &SharedClock, chosen: &[Arc<RwLock<Booking>>]) -> (Result<(), RejectReason>, Option<Arc<RwLock<Booking>>>) {
+    let mut to_cancel: Option<Arc<RwLock<Booking>>> = None;
+    booking_read.created_at = program_time.get_current_time();
+
+    // check if the user may book the facility at all
+    if !booking_read.user().may_book(facility.fac_type) {
+        return (Err(RejectReason::NotPermitted), to_cancel);
+    }
+
+    // check if the booking is short enough for the facility
+    if let Some(max_duration) = facility.exceeded_max_duration(booking_read.start, booking_read.end) {
+        return (Err(RejectReason::TooLong { max_duration }), to_cancel);
+    }
+
+    // check if the booking is in the future
+    let current_time = program_time.get_current_time();
+    if booking_read.start < current_time {
+        return (Err(RejectReason::TimeInPast { current_time }), to_cancel);
+    }
+
+    // check if the booking is made far enough in advance
+    if facility.too_soon(booking_read.start, current_time) {
+        return (Err(RejectReason::TooSoon { min_lead: facility.min_lead }), to_cancel);
+    }
+
+    // check if the booking ends within the booking horizon
+    let horizon_end = program_time.get_horizon_end();
+    if booking_read.end > horizon_end {
+        return (Err(RejectReason::BeyondHorizon { horizon_end }), to_cancel);
+    }
+
+    // count the overlaps and the premium overlaps, the other parts of the compound that are
+    // already pushed to the same facility count as well, as they are confirmed together
+    // (only one compound is in process at a time, so its pending parts are those of the user)
+    let mut overlaps = 0;
+    let mut premium_overlaps = 0;
+    for b in facility.conflicting(booking_read.start, booking_read.end) {
+        if chosen.iter().any(|c| Arc::ptr_eq(c, &b)) {
+            continue;
+        }
+        let b = b.read().unwrap();
+        let sibling = b.status == UNCONFIRMED && Arc::ptr_eq(b.user(), booking_read.user());
+        if conflicts(&b, booking_read.start, booking_read.end, facility.buffer) && (b.status == CONFIRMED || sibling) {
+            overlaps += b.units;
+            if b.user().vip {
+                premium_overlaps += b.units;
+            }
+        }
+    }
+
+    // the capacity may be lower during parts of the time span of the booking, which needs room for its units
+    let capacity = facility.capacity_between(booking_read.start, booking_read.end);
+    let units = booking_read.units;
+
+    // if the user is non-vip, or the facility allows no displacements, and the capacity is exceeded, decline the booking
+    if facility.is_full_for(overlaps, units, capacity) && (!booking_read.user().vip || !facility.allow_displacement) {
+        return (Err(RejectReason::CapacityExceeded), to_cancel);
+    }
+
+    // if the user is a vip and we are at the capacity limit, the booking the vip booking displaces in the vip system
+    // (never one starting within the protect window, and of equals the first by the order of bookings, whichever order
+    // they were added in), unless another part displaces it already, is the candidate for cancellation should the compound the booking
+    // is in be possible; if there is none, or cancelling it doesn't make room, the booking is declined
+    if facility.is_full_for(overlaps, units, capacity) {
+        to_cancel = select_victim_excluding(facility, &overlapping_confirmed(facility, booking_read), booking_read, current_time, chosen, &VipPolicy);
+        let freed = to_cancel.as_ref().map_or(0, |b| b.read().unwrap().units);
+        if to_cancel.is_none() || facility.is_full_for(overlaps - freed, units, capacity) {
+            // all bookings left are vip, or are not enough for the capacity
+            let reason = if facility.is_full_for(premium_overlaps, units, capacity) { RejectReason::EqualPriority } else { RejectReason::CapacityExceeded };
+            return (Err(reason), None);
+        }
+    }
+
+    // here the booking can be pushed to the facility
+    // note that the status is only changed to confirmed
+    // when the whole compound is possible
+    facility.add_booking(booking.clone(), booking_read.start, booking_read.end);
+
+    (Ok(()), to_cancel)
+}
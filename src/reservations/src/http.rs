@@ -0,0 +1,191 @@
+///////////////////////////////////////////////////////////////////////
+//////////////////////////// HTTP Server //////////////////////////////
+///////////////////////////////////////////////////////////////////////
+
+// System:  A minimal HTTP server to drive the vip system from outside the process.
+//          POST /book books a facility for a user and answers with the booking result,
+//          GET /availability tells how much of a facility is booked in a time span.
+
+// Implementation:  The server runs in its own thread and handles each connection in
+//                  a thread of its own, one request per connection. The facilities are
+//                  looked up by id in a shared facility store, the bookings go through the
+//                  booking function of the vip system like those of the users.
+
+//                  Users of the server are not listening for cancellations, each request
+//                  creates a user whose receiving end is dropped right away.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{confirmed_overlaps, Booking, BookingResult, Facility, FacilityStore, SharedClock, SharedSink, User};
+use crate::vip::book_facility;
+use serde::Deserialize;
+use serde_json::json;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+/////////////////////// Requests /////////////////////
+
+// The JSON body of a booking request.
+#[derive(Deserialize)]
+struct BookBody {
+    user_id: u32,
+    facility_id: u32,
+    start: u32,
+    end: u32,
+    #[serde(default)]
+    vip: bool,
+}
+
+// A parsed HTTP request, only what the server needs of it.
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    body: String,
+}
+
+// The largest body a request may have, larger ones are refused before reading them.
+const MAX_BODY_LEN: usize = 64 * 1024;
+
+// How long the server waits for a client to send more of its request before it gives up on the connection.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/////////////////////// Server /////////////////////
+
+// This function starts serving the facilities of the store on the listener, which is bound by the caller
+// (e.g. to port 0 to get a free port). The facilities are booked on the given clock, events go to the sink.
+pub fn start_server(listener: TcpListener, store: Arc<FacilityStore>, program_time: SharedClock, sink: SharedSink) {
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let store = store.clone();
+            let program_time = program_time.clone();
+            let sink = sink.clone();
+            thread::spawn(move || handle_connection(stream, &store, program_time, &sink));
+        }
+    });
+}
+
+// This function answers a single request on the connection. A connection that breaks off is dropped,
+// as is one whose client doesn't send anything for longer than the read timeout.
+fn handle_connection(mut stream: TcpStream, store: &FacilityStore, program_time: SharedClock, sink: &SharedSink) {
+    if stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+        return;
+    }
+    let (status, body) = match read_request(&stream) {
+        Ok(req) => route(&req, store, program_time, sink),
+        Err(error) => error,
+    };
+    let body = body.to_string();
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        413 => "Payload Too Large",
+        _ => "Not Found",
+    };
+    let response = format!("HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, reason, body.len(), body);
+    // the client may have gone already
+    let _ = stream.write_all(response.as_bytes());
+}
+
+// This function reads the request line, the headers and, if there is a content length, the body.
+// If the request can't be read, the status code and the JSON body to answer with are returned instead.
+fn read_request(stream: &TcpStream) -> Result<Request, (u16, serde_json::Value)> {
+    let malformed = || (400, json!({ "error": "malformed request" }));
+    let mut reader = BufReader::new(stream);
+
+    // e.g. "GET /availability?facility=1&start=10&end=20 HTTP/1.1"
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|_| malformed())?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or_else(malformed)?.to_string();
+    let target = parts.next().ok_or_else(malformed)?;
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), query),
+        None => (target.to_string(), ""),
+    };
+    let query = query.split('&').filter_map(|pair| pair.split_once('=')).map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+    // the headers end with an empty line
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|_| malformed())?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| malformed())?;
+            }
+        }
+    }
+
+    // the body is only read if it isn't too large
+    if content_length > MAX_BODY_LEN {
+        return Err((413, json!({ "error": "request body too large", "max_len": MAX_BODY_LEN })));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|_| malformed())?;
+    Ok(Request { method, path, query, body: String::from_utf8(body).map_err(|_| malformed())? })
+}
+
+// This function dispatches a request and returns the status code and the JSON body of the response.
+fn route(req: &Request, store: &FacilityStore, program_time: SharedClock, sink: &SharedSink) -> (u16, serde_json::Value) {
+    match (req.method.as_str(), req.path.as_str()) {
+        ("POST", "/book") => book(req, store, program_time, sink),
+        ("GET", "/availability") => availability(req, store),
+        _ => (404, json!({ "error": "unknown endpoint" })),
+    }
+}
+
+/////////////////////// Endpoints /////////////////////
+
+// POST /book, the booking result is answered as is, e.g. {"status":"confirmed","id":3}.
+fn book(req: &Request, store: &FacilityStore, program_time: SharedClock, sink: &SharedSink) -> (u16, serde_json::Value) {
+    let body: BookBody = match serde_json::from_str(&req.body) {
+        Ok(body) => body,
+        Err(_) => return (400, json!({ "error": "invalid booking request" })),
+    };
+    let facility = match find_facility(store, body.facility_id) {
+        Some(facility) => facility,
+        None => return (404, json!({ "error": "unknown facility" })),
+    };
+    if body.end <= body.start {
+        return (400, json!({ "error": "the booking has to end after it starts" }));
+    }
+
+    // nobody listens for cancellations of the users of the server
+    let (tx, _) = mpsc::channel();
+    let user = Arc::new(User { id: body.user_id, vip: body.vip, adress: tx, allowed_facility_types: None });
+    let booking = Arc::new(RwLock::new(Booking::new(body.start, body.end, facility, user)));
+    let (result, _): (BookingResult, _) = book_facility(booking, program_time, sink);
+    (200, serde_json::to_value(result).unwrap())
+}
+
+// GET /availability?facility=..&start=..&end=.., tells how many confirmed bookings overlap the time span
+// and whether another one would fit.
+fn availability(req: &Request, store: &FacilityStore) -> (u16, serde_json::Value) {
+    let param = |name: &str| req.query.iter().find(|(k, _)| k == name).and_then(|(_, v)| v.parse::<u32>().ok());
+    let (facility_id, start, end) = match (param("facility"), param("start"), param("end")) {
+        (Some(facility_id), Some(start), Some(end)) => (facility_id, start, end),
+        _ => return (400, json!({ "error": "facility, start and end are required" })),
+    };
+    let facility = match find_facility(store, facility_id) {
+        Some(facility) => facility,
+        None => return (404, json!({ "error": "unknown facility" })),
+    };
+
+    let facility = facility.read().unwrap();
+    let booked = confirmed_overlaps(&facility, start, end);
+    let capacity = facility.capacity_between(start, end);
+    (200, json!({ "facility_id": facility_id, "start": start, "end": end, "capacity": capacity, "booked": booked, "available": !facility.is_full(booked, capacity) }))
+}
+
+// This function looks up a facility of the store by its id.
+fn find_facility(store: &FacilityStore, id: u32) -> Option<Arc<RwLock<Facility>>> {
+    store.facilities.iter().find(|f| f.read().unwrap().id == id).cloned()
+}
@@ -0,0 +1,3088 @@
+///////////////////////////////////////////////////////////////////////
+/////////////////////// Reservations Library //////////////////////////
+///////////////////////////////////////////////////////////////////////
+
+//! A reservation system in Rust, implemented as three systems of increasing complexity
+//! which share the types and helpers of this library.
+//!
+//! - `basic` (Task 1, feature `basic`): users book facilities as long as the capacity allows.
+//! - `vip` (Task 2, feature `vip`): bookings of vip users cancel non-vip bookings if necessary.
+//! - `compound` (Task 3, feature `compound`): bookings are made in compounds which are
+//!   only booked, and cancelled, as a whole.
+//! - `actor` (feature `basic`): an optional mode in which each facility runs in its own thread
+//!   and is booked by messages instead of locks with the booking of the basic system.
+//! - `http` (feature `vip`): a minimal HTTP server to book facilities and query their
+//!   availability with the booking of the vip system.
+//!
+//! The library root holds what all systems build on: the facilities, bookings and users,
+//! the program time, the helpers and reports, and the booking events handed to an [`EventSink`].
+//! The binaries `reservations1`, `reservations2` and `reservations3` run the example
+//! scenario of the respective system; all features are enabled by default.
+
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "basic")]
+pub mod basic;
+#[cfg(feature = "vip")]
+pub mod vip;
+#[cfg(feature = "compound")]
+pub mod compound;
+#[cfg(feature = "basic")]
+pub mod actor;
+#[cfg(feature = "vip")]
+pub mod http;
+
+#[cfg(all(test, feature = "basic"))]
+mod reservation1_test;
+#[cfg(all(test, feature = "vip"))]
+mod reservation2_test;
+#[cfg(all(test, feature = "compound"))]
+mod reservation3_test;
+#[cfg(test)]
+mod test_util;
+
+use iota::iota;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockWriteGuard, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use std::sync::mpsc;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::json;
+
+//////////////////// Definition of useful Constants ////////////////////
+
+pub type FacilityType = u32;
+pub type BookingStatus = u32;
+
+iota! {
+    pub const ROOM: FacilityType = 1 << iota;
+        , PROJECTOR
+}
+
+iota! {
+    pub const UNCONFIRMED: BookingStatus = 1 << iota;
+        , CONFIRMED
+        , CANCELLED
+        , HELD
+        , WAITLISTED
+        , TENTATIVE
+}
+
+//////////////////// Definition of useful Structs ////////////////////
+
+// A facility has a unique id, a type, a capacity, a list of bookings and an audit log
+// of what happened to these bookings. The victim policy decides which booking
+// is cancelled when a vip needs the facility. Bookings are only added and removed through add_booking
+// and remove_booking, which keep the index of the bookings used for the overlap queries up to date.
+pub struct Facility {
+    pub id: u32,
+    pub fac_type: FacilityType,
+    pub capacity: u32,
+    // the capacity during some time spans, where it differs from the capacity above
+    pub capacity_schedule: Vec<(Range<u32>, u32)>,
+    bookings: Vec<Arc<RwLock<Booking>>>,
+    audit_log: Vec<AuditEntry>,
+    pub victim_policy: VictimPolicy,
+    // if set, the vip bookings displace the users that have been cancelled least so far first
+    pub fairness: Option<Arc<Fairness>>,
+    // the time needed between two bookings, e.g. to clean up a room
+    pub buffer: u32,
+    // if enabled, bookings exceeding the capacity are queued on the waitlist instead of being rejected,
+    // as long as the waitlist is shorter than max_waitlist (if there is a limit)
+    pub waitlist_enabled: bool,
+    pub max_waitlist: Option<usize>,
+    pub waitlist: Vec<Arc<RwLock<Booking>>>,
+    // what a booking of the facility costs per unit of time, for the chargeback
+    pub cost_per_unit_time: u32,
+    // the longest a single booking of the facility may last, if there is a limit
+    pub max_duration: Option<u32>,
+    // how many bookings beyond the capacity are admitted, in percent of the capacity (rounded down)
+    pub overbook_pct: u32,
+    // how many units of time ahead a booking of the facility has to be made at least
+    pub min_lead: u32,
+    // run when a booking fills the facility up to its capacity, while the facility is still locked
+    pub on_full: Option<OnFull>,
+    // run for each booking of the facility that is cancelled, while the booking and the facility are still locked
+    pub on_cancel: Option<OnCancel>,
+    // how much of the capacity a booking of the user takes up in the vip system, 1 if not set
+    pub weight: Option<WeightFn>,
+    // whether the facility is full once the bookings reach its capacity, or only once they exceed it
+    pub capacity_mode: CapacityMode,
+    // whether a vip booking of a full facility may cancel confirmed bookings, if not it is rejected like any other
+    pub allow_displacement: bool,
+    // bookings starting within this many units of time from now can't be displaced, not even by a vip
+    pub protect_window: Option<u32>,
+    // the copies of the bookings as of the last publish, for readers not to wait for the facility
+    published: PublishedBookings,
+    // the bookings the published copies were taken from, in the same order
+    published_from: Vec<Weak<RwLock<Booking>>>,
+    index: IntervalIndex,
+}
+
+// The copies of the bookings of a facility as of its last publish, e.g. for availability queries and
+// calendars. Publishing swaps in a complete new list, so a reader holding on to a list never sees it
+// change; the lock is only held to clone or swap the Arc, never while a list is built or read.
+#[derive(Clone, Default)]
+pub struct PublishedBookings {
+    current: Arc<PublishedList>,
+}
+
+// The slot the published list of a facility is swapped into.
+type PublishedList = RwLock<Arc<Vec<BookingView>>>;
+
+impl PublishedBookings {
+    // The bookings as of the last publish.
+    pub fn current(&self) -> Arc<Vec<BookingView>> {
+        self.current.read().unwrap().clone()
+    }
+}
+
+// The published bookings of the facilities by facility id, so they can be read without locking the facility.
+// A facility shows up here once it first publishes; if a restored facility took over the id of another one,
+// the one that published last under the id is found first.
+static PUBLISHED: RwLock<Vec<(u32, Weak<PublishedList>)>> = RwLock::new(Vec::new());
+
+// The bookings of the facility with the given id as of its last publish, without locking the facility.
+// The list is empty if the facility didn't publish yet or doesn't exist (anymore).
+pub fn snapshot_of(facility_id: u32) -> Arc<Vec<BookingView>> {
+    let published = PUBLISHED.read().unwrap();
+    match published.iter().rev().find(|(id, _)| *id == facility_id).and_then(|(_, current)| current.upgrade()) {
+        Some(current) => current.read().unwrap().clone(),
+        None => Arc::new(Vec::new()),
+    }
+}
+
+// The interval index holds the time span of each booking of a facility sorted by start, together
+// with the position the booking was added at. As no booking is longer than max_len, the bookings
+// overlapping a time span all start within max_len before its start and its end, which is found
+// by binary search instead of scanning all bookings.
+#[derive(Default)]
+struct IntervalIndex {
+    entries: Vec<(u32, u32, u64, Arc<RwLock<Booking>>)>,
+    max_len: u32,
+    next_seq: u64,
+}
+
+// The facility store is the registry of all facilities of the system.
+#[derive(Default)]
+pub struct FacilityStore {
+    pub facilities: Vec<Arc<RwLock<Facility>>>,
+}
+
+// A facility pool groups interchangeable facilities of one type, e.g. all projectors,
+// when any of them will do.
+pub struct FacilityPool {
+    pub fac_type: FacilityType,
+    pub members: Vec<Arc<RwLock<Facility>>>,
+}
+
+// Whether bookings are taken as half-open, i.e. the end is not part of the booking,
+// or as closed, so that bookings touching at their ends overlap.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum OverlapMode {
+    #[default]
+    HalfOpen,
+    Closed,
+}
+
+// The policies for choosing the non-vip booking that is cancelled for a vip booking:
+// the first one in the order of bookings (see Booking::order), the one closest to its end
+// (as cancelling it wastes the least), or the one created last or first (by created_at).
+// Bookings ranked the same by the policy are taken in the order of bookings as well.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VictimPolicy {
+    FirstFound,
+    LeastRemainingTime,
+    MostRecent,
+    Oldest,
+}
+
+impl VictimPolicy {
+    // The rank of a booking as a victim at the time now, the least ranked is cancelled first.
+    pub fn rank(&self, booking: &Booking, now: u32) -> u32 {
+        match self {
+            VictimPolicy::FirstFound => 0,
+            VictimPolicy::LeastRemainingTime => booking.end.saturating_sub(now),
+            VictimPolicy::MostRecent => u32::MAX - booking.created_at,
+            VictimPolicy::Oldest => booking.created_at,
+        }
+    }
+}
+
+// When a facility is full, given the bookings overlapping a new one: in strict mode (the default)
+// it is full once the overlaps reach the capacity, so a capacity of 2 admits 2 overlapping bookings
+// and rejects the 3rd; allowing equal it is only full once they exceed it, admitting one more.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CapacityMode {
+    Strict,
+    AllowEqual,
+}
+
+// The number of cancellations each user suffered for vip bookings, by user id.
+// Facilities can share it so the cancellations are spread over all of them.
+#[derive(Default, Debug)]
+pub struct Fairness {
+    cancellations: RwLock<HashMap<u32, u32>>,
+}
+
+impl Fairness {
+    pub fn new() -> Fairness {
+        Fairness::default()
+    }
+
+    // How often the bookings of the user have been cancelled so far.
+    pub fn cancellations(&self, user_id: u32) -> u32 {
+        *self.cancellations.read().unwrap().get(&user_id).unwrap_or(&0)
+    }
+
+    // Counts another cancellation of a booking of the user.
+    pub fn record_cancellation(&self, user_id: u32) {
+        *self.cancellations.write().unwrap().entry(user_id).or_insert(0) += 1;
+    }
+}
+
+// A booking has a unique id, a start and end time, a facility, a user and a status.
+// The status can be unconfirmed, confirmed or cancelled and is changed
+// as necessary. Bookings made as part of a compound also reference the compound.
+// The created_at is the program time the booking was placed in its facility, as a pending hold or right away.
+pub struct Booking {
+    pub id: u64,
+    pub start: u32,
+    pub end: u32,
+    pub created_at: u32,
+    pub facility: Arc<RwLock<Facility>>,
+    // the users the booking is for, the one who made it first, e.g. the members of a group
+    pub users: Vec<Arc<User>>,
+    pub status: BookingStatus,
+    pub compound: Option<Arc<Vec<Arc<RwLock<Booking>>>>>,
+    // whether the compound fails if this part of it can't be booked
+    pub required: bool,
+    // labels of the booking for reports and policies, e.g. "exam"
+    pub tags: Vec<String>,
+    // how many units of the capacity of the facility the booking takes up, e.g. 3 seats of a room
+    pub units: u32,
+}
+
+// The booking skeleton is used to create bookings that are handed to the user
+// when we start the user and which of this compared to the booking to not
+// have a user reference yet.
+#[derive(Debug)]
+pub struct BookingSkeleton {
+    pub start: u32,
+    pub end: u32,
+    pub facility: Arc<RwLock<Facility>>,
+    // the tags the booking is created with
+    pub tags: Vec<String>,
+    // how many units of the capacity of the facility the booking asks for, 1 unless built otherwise
+    pub units: u32,
+}
+
+// Two skeletons are equal if they span the same time on the very same facility with the same tags and units.
+impl PartialEq for BookingSkeleton {
+    fn eq(&self, other: &BookingSkeleton) -> bool {
+        self.start == other.start && self.end == other.end && Arc::ptr_eq(&self.facility, &other.facility) && self.tags == other.tags && self.units == other.units
+    }
+}
+
+// The error when a skeleton builder is missing a facility or its time span is empty.
+#[derive(Debug, PartialEq)]
+pub enum SkeletonError {
+    MissingFacility,
+    EmptySpan { start: u32, end: u32 },
+    // a booking has to take up at least one unit of the capacity
+    NoUnits,
+}
+
+// A builder for booking skeletons, e.g. BookingSkeleton::builder().start(10).end(20).facility(room).build(),
+// which checks the skeleton once instead of every literal having to get it right. Skeletons take up a single unit
+// unless told otherwise.
+pub struct BookingSkeletonBuilder {
+    start: u32,
+    end: u32,
+    facility: Option<Arc<RwLock<Facility>>>,
+    tags: Vec<String>,
+    units: u32,
+}
+
+impl BookingSkeleton {
+    pub fn builder() -> BookingSkeletonBuilder {
+        BookingSkeletonBuilder { start: 0, end: 0, facility: None, tags: Vec::new(), units: 1 }
+    }
+}
+
+impl BookingSkeletonBuilder {
+    pub fn start(mut self, start: u32) -> BookingSkeletonBuilder {
+        self.start = start;
+        self
+    }
+
+    pub fn end(mut self, end: u32) -> BookingSkeletonBuilder {
+        self.end = end;
+        self
+    }
+
+    pub fn facility(mut self, facility: Arc<RwLock<Facility>>) -> BookingSkeletonBuilder {
+        self.facility = Some(facility);
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> BookingSkeletonBuilder {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    pub fn units(mut self, units: u32) -> BookingSkeletonBuilder {
+        self.units = units;
+        self
+    }
+
+    // Builds the skeleton, which needs a facility, has to end after it starts and take up at least one unit.
+    pub fn build(self) -> Result<BookingSkeleton, SkeletonError> {
+        let facility = self.facility.ok_or(SkeletonError::MissingFacility)?;
+        if self.start >= self.end {
+            return Err(SkeletonError::EmptySpan { start: self.start, end: self.end });
+        }
+        if self.units == 0 {
+            return Err(SkeletonError::NoUnits);
+        }
+        Ok(BookingSkeleton { start: self.start, end: self.end, facility, tags: self.tags, units: self.units })
+    }
+}
+
+// A recurring booking skeleton repeats the booking from start to end count times,
+// each occurrence period time units after the previous one (e.g. a weekly seminar).
+pub struct RecurringSkeleton {
+    pub start: u32,
+    pub end: u32,
+    pub period: u32,
+    pub count: u32,
+    pub facility: Arc<RwLock<Facility>>,
+}
+
+// A user has an id, a vip status and an inbox (channel) for cancellation messages.
+// On which others can send. The channel for receiving is handed to the user function
+// as an argument.
+pub struct User {
+    pub id: u32,
+    pub vip: bool,
+    pub adress: mpsc::Sender<Arc<RwLock<Booking>>>,
+    // the facility types the user may book as a mask, e.g. ROOM | PROJECTOR, or None for all of them
+    pub allowed_facility_types: Option<FacilityType>,
+}
+
+// The errors that can occur when booking several facilities at once.
+// For bookings across pools, the pool is the index of the pool that had no facility left.
+// A lock is poisoned if a thread panicked while holding it, a channel is closed if the
+// receiving user or facility thread is gone; both are reported instead of panicking as well.
+#[derive(Debug, PartialEq)]
+pub enum BookingError {
+    TimeInPast { current_time: u32 },
+    BeyondHorizon { horizon_end: u32 },
+    NotEnoughFacilities { available: u32, requested: u32 },
+    PoolExhausted { pool: usize },
+    PoisonedLock,
+    ChannelClosed,
+}
+
+impl<T> From<PoisonError<T>> for BookingError {
+    fn from(_: PoisonError<T>) -> BookingError {
+        BookingError::PoisonedLock
+    }
+}
+
+impl User {
+    // Sends the cancelled booking to the user. The user may have stopped listening already,
+    // which is not a problem for the one cancelling, so it is only reported as an error.
+    pub fn notify(&self, booking: Arc<RwLock<Booking>>) -> Result<(), BookingError> {
+        self.adress.send(booking).map_err(|_| BookingError::ChannelClosed)
+    }
+
+    // Whether the user is permitted to book facilities of the given type.
+    pub fn may_book(&self, fac_type: FacilityType) -> bool {
+        self.allowed_facility_types.is_none_or(|allowed| allowed & fac_type != 0)
+    }
+}
+
+// The actions and reasons recorded in the audit log of a facility.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AuditAction {
+    Booked,
+    Confirmed,
+    Cancelled,
+    // the booking was moved, the entry holds its new time span
+    Rescheduled,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AuditReason {
+    UserRequest,
+    VipBooking,
+    HoldExpired,
+    AdminRequest,
+    CompoundFailure,
+    Maintenance,
+}
+
+// An audit entry records at which program time what happened to
+// which user's booking of a facility and why.
+pub struct AuditEntry {
+    pub time: u32,
+    pub action: AuditAction,
+    pub user_id: u32,
+    pub start: u32,
+    pub end: u32,
+    pub reason: AuditReason,
+}
+
+// ProgramTime struct, we use an Arc to share it between threads. The time is an atomic,
+// so reading it never waits for the ticking thread.
+// The max_horizon limits how far ahead of the current time a booking may end.
+// For display, tick 0 is epoch seconds into the day and each tick lasts seconds_per_tick.
+// While paused, the ticking thread leaves the time as it is.
+pub struct ProgramTime {
+    pub time: AtomicU32,
+    pub paused: AtomicBool,
+    pub max_horizon: u32,
+    pub epoch: u32,
+    pub seconds_per_tick: u32,
+}
+
+// A clock that only advances when it is ticked, there is no thread driving it.
+// It lets tests decide exactly when time passes instead of sleeping.
+pub struct ManualClock {
+    pub time: AtomicU32,
+    pub max_horizon: u32,
+}
+
+// The systems only need to know the current time and the horizon, so they run on
+// any clock, be it the ProgramTime or a ManualClock.
+pub trait Clock {
+    fn get_current_time(&self) -> u32;
+    // The latest time a booking may end at, given the current time and the horizon.
+    fn get_horizon_end(&self) -> u32;
+}
+
+// The clock shared between the threads, like the sinks.
+pub type SharedClock = Arc<dyn Clock + Send + Sync>;
+
+////////////////// Facility types ///////////////////
+
+// The names of the facility types created at runtime, the built in ones are named by facility_type_to_string.
+static FACILITY_TYPE_NAMES: RwLock<Vec<(FacilityType, String)>> = RwLock::new(Vec::new());
+
+// The error when no more facility types can be created, as each of them takes one of the 32 bits.
+#[derive(Debug, PartialEq)]
+pub enum FacilityTypeError {
+    TooManyTypes,
+}
+
+// The next bit to hand out as a facility type, the built in types take the bits up to the one of the last of them.
+// It is shared by all builders, so the types of different builders never collide either.
+static NEXT_FACILITY_TYPE_BIT: AtomicU32 = AtomicU32::new(PROJECTOR.trailing_zeros() + 1);
+
+// A builder handing out facility types at runtime, in addition to the ones of the iota block.
+// The bits handed out so far are counted for the whole process, starting after the built in types,
+// so no two types created at runtime collide with each other or with the built in ones. The names are
+// registered globally, so facility_type_to_string and facility_type_from_string know them as well.
+pub struct FacilityTypeBuilder;
+
+impl Default for FacilityTypeBuilder {
+    fn default() -> FacilityTypeBuilder {
+        FacilityTypeBuilder::new()
+    }
+}
+
+impl FacilityTypeBuilder {
+    pub fn new() -> FacilityTypeBuilder {
+        FacilityTypeBuilder
+    }
+
+    // Creates a facility type of the given name, e.g. "Whiteboard", unless all bits are taken.
+    pub fn add(&mut self, name: &str) -> Result<FacilityType, FacilityTypeError> {
+        let bit = NEXT_FACILITY_TYPE_BIT.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bit| (bit < FacilityType::BITS).then_some(bit + 1))
+            .map_err(|_| FacilityTypeError::TooManyTypes)?;
+        let fac_type: FacilityType = 1 << bit;
+        FACILITY_TYPE_NAMES.write().unwrap().push((fac_type, name.to_string()));
+        Ok(fac_type)
+    }
+}
+
+////////////////// Facility constructor ///////////////////
+
+// Facility ids are handed out from a global counter, like the booking ids.
+static NEXT_FACILITY_ID: AtomicU32 = AtomicU32::new(1);
+
+impl Facility {
+    pub fn new(fac_type: FacilityType, capacity: u32) -> Facility {
+        let id = NEXT_FACILITY_ID.fetch_add(1, Ordering::Relaxed);
+        Facility { id, fac_type, capacity, capacity_schedule: Vec::new(), bookings: Vec::new(), audit_log: Vec::new(), victim_policy: VictimPolicy::FirstFound, fairness: None, buffer: 0, waitlist_enabled: false, max_waitlist: None, waitlist: Vec::new(), cost_per_unit_time: 0, max_duration: None, overbook_pct: 0, min_lead: 0, on_full: None, on_cancel: None, weight: None, capacity_mode: CapacityMode::Strict, allow_displacement: true, protect_window: None, published: PublishedBookings::default(), published_from: Vec::new(), index: IntervalIndex::default() }
+    }
+
+    // Adds a booking from start to end to the facility. The times are handed in separately
+    // as the caller usually holds the lock on the booking.
+    pub fn add_booking(&mut self, booking: Arc<RwLock<Booking>>, start: u32, end: u32) {
+        self.index.insert(start, end, booking.clone());
+        self.bookings.push(booking);
+    }
+
+    // The bookings of the facility, in the order they were added. They are only changed through
+    // add_booking and remove_booking, which keep the index of the facility up to date.
+    pub fn bookings(&self) -> &[Arc<RwLock<Booking>>] {
+        &self.bookings
+    }
+
+    // Removes a booking from the facility.
+    pub fn remove_booking(&mut self, booking: &Arc<RwLock<Booking>>) {
+        self.index.remove(booking);
+        self.bookings.retain(|b| !Arc::ptr_eq(b, booking));
+    }
+
+    // The chronological audit log of the facility, the facility has to be locked to read it.
+    // Bookings are logged as booked when they are added and as confirmed once they are final.
+    pub fn audit_log(&self) -> &[AuditEntry] {
+        &self.audit_log
+    }
+
+    // Tells the facility that one of its bookings now runs from start to end.
+    pub fn move_booking(&mut self, booking: &Arc<RwLock<Booking>>, start: u32, end: u32) {
+        self.index.move_to(booking, start, end);
+    }
+
+    // Returns the bookings that may overlap the time span from start to end, in the order they were added.
+    pub fn overlapping(&self, start: u32, end: u32) -> Vec<Arc<RwLock<Booking>>> {
+        self.index.overlapping(start, end)
+    }
+
+    // Returns the bookings that may conflict with a booking from start to end, which are
+    // the bookings overlapping it once they are widened by the buffer of the facility.
+    pub fn conflicting(&self, start: u32, end: u32) -> Vec<Arc<RwLock<Booking>>> {
+        self.overlapping(start.saturating_sub(self.buffer), end.saturating_add(self.buffer))
+    }
+
+    // The capacity of the facility at the given time, taken from the first time span of the
+    // capacity schedule containing it, or the constant capacity if there is none.
+    pub fn capacity_at(&self, time: u32) -> u32 {
+        match self.capacity_schedule.iter().find(|(range, _)| range.contains(&time)) {
+            Some((_, capacity)) => *capacity,
+            None => self.capacity,
+        }
+    }
+
+    // The capacity available to a booking from start to end, which is the least capacity during its time span,
+    // inflated by the overbooking allowance. The capacity only changes where a time span of the schedule
+    // starts or ends, so only these times are checked.
+    pub fn capacity_between(&self, start: u32, end: u32) -> u32 {
+        let mut capacity = self.capacity_at(start);
+        for (range, _) in &self.capacity_schedule {
+            for time in [range.start, range.end] {
+                if start < time && time < end {
+                    capacity = capacity.min(self.capacity_at(time));
+                }
+            }
+        }
+        capacity + capacity * self.overbook_pct / 100
+    }
+
+    // The number of confirmed bookings of the facility, at any time.
+    pub fn confirmed_count(&self) -> usize {
+        self.bookings.iter().filter(|b| b.read().unwrap().status == CONFIRMED).count()
+    }
+
+    // The number of cancelled bookings of the facility, at any time.
+    pub fn cancelled_count(&self) -> usize {
+        self.bookings.iter().filter(|b| b.read().unwrap().status == CANCELLED).count()
+    }
+
+    // The maximum duration of the facility, if a booking from start to end lasts longer than that.
+    pub fn exceeded_max_duration(&self, start: u32, end: u32) -> Option<u32> {
+        self.max_duration.filter(|&max| end.saturating_sub(start) > max)
+    }
+
+    // Whether a booking starting at start, made at current_time, is made less than min_lead in advance.
+    pub fn too_soon(&self, start: u32, current_time: u32) -> bool {
+        start < current_time.saturating_add(self.min_lead)
+    }
+
+    // Runs the cancellation hook of the facility, if there is one, for a booking that has just been cancelled.
+    pub fn cancelled(&self, booking: &Booking) {
+        if let Some(on_cancel) = &self.on_cancel {
+            on_cancel(booking);
+        }
+    }
+
+    // Whether the booking starts too soon after current_time to be displaced, bookings that have started already included.
+    pub fn protected(&self, booking: &Booking, current_time: u32) -> bool {
+        self.protect_window.is_some_and(|window| booking.start.saturating_sub(current_time) <= window)
+    }
+
+    // Publishes copies of the bookings as they are now, followed by the waitlist, the caller must keep the facility locked so they
+    // don't change meanwhile. Every function changing the bookings of a facility publishes once it is done,
+    // after letting go of the bookings it locked. A booking another thread holds locked right now keeps its
+    // last published copy, as that thread publishes again when it is done with it.
+    pub fn publish(&mut self) {
+        let previous = self.published.current();
+        let mut views: Vec<BookingView> = Vec::new();
+        let mut published_from: Vec<Weak<RwLock<Booking>>> = Vec::new();
+        for b in self.bookings.iter().chain(&self.waitlist) {
+            let view = match b.try_read() {
+                Ok(b) => BookingView { id: b.id, facility_id: self.id, fac_type: self.fac_type, user_id: b.user().id, start: b.start, end: b.end, status: b.status },
+                Err(_) => match self.published_from.iter().position(|p| p.as_ptr() == Arc::as_ptr(b)) {
+                    Some(i) => previous[i],
+                    None => continue,
+                },
+            };
+            views.push(view);
+            published_from.push(Arc::downgrade(b));
+        }
+        *self.published.current.write().unwrap() = Arc::new(views);
+        self.published_from = published_from;
+
+        // make the published bookings findable by the id of the facility
+        let mut published = PUBLISHED.write().unwrap();
+        published.retain(|(_, current)| current.strong_count() > 0);
+        let current = Arc::downgrade(&self.published.current);
+        if !published.iter().any(|(id, c)| *id == self.id && c.ptr_eq(&current)) {
+            published.push((self.id, current));
+        }
+    }
+
+    // A handle on the published bookings, to take snapshots later on without locking the facility.
+    pub fn published(&self) -> PublishedBookings {
+        self.published.clone()
+    }
+
+    // Whether the facility is too full for another booking, given the overlaps of the booking and the capacity
+    // during its time span, as decided by the capacity mode.
+    pub fn is_full(&self, overlaps: u32, capacity: u32) -> bool {
+        match self.capacity_mode {
+            CapacityMode::Strict => overlaps >= capacity,
+            CapacityMode::AllowEqual => overlaps > capacity,
+        }
+    }
+
+    // Same as is_full, but for a booking taking up the given units of the capacity, which only fits if
+    // each of its units would fit after the ones before them; a single unit is what is_full checks.
+    pub fn is_full_for(&self, overlaps: u32, units: u32, capacity: u32) -> bool {
+        self.is_full(overlaps.saturating_add(units.saturating_sub(1)), capacity)
+    }
+
+    // How much of the capacity a booking of the user takes up, as told by the weight hook of the facility.
+    pub fn weight_for(&self, user: &User) -> u32 {
+        match &self.weight {
+            Some(weight) => weight(user),
+            None => 1,
+        }
+    }
+
+    // Whether the confirmed bookings take up all of the capacity at some time from start to end. The number
+    // of bookings only goes up where one starts, so it is enough to look at start and the starts within.
+    pub fn full_during(&self, start: u32, end: u32) -> bool {
+        let mut times = vec![start];
+        for b in self.conflicting(start, end) {
+            let b = b.read().unwrap();
+            if b.status == CONFIRMED && start < b.start && b.start < end {
+                times.push(b.start);
+            }
+        }
+        times.iter().any(|&t| self.is_full(confirmed_overlaps(self, t, t + 1), self.capacity_between(t, t + 1)))
+    }
+}
+
+////////////////// Interval index ///////////////////
+
+impl IntervalIndex {
+    fn insert(&mut self, start: u32, end: u32, booking: Arc<RwLock<Booking>>) {
+        let pos = self.entries.partition_point(|(s, _, _, _)| *s <= start);
+        self.entries.insert(pos, (start, end, self.next_seq, booking));
+        self.next_seq += 1;
+        self.max_len = self.max_len.max(end.saturating_sub(start));
+    }
+
+    fn remove(&mut self, booking: &Arc<RwLock<Booking>>) {
+        self.entries.retain(|(_, _, _, b)| !Arc::ptr_eq(b, booking));
+    }
+
+    // Moves a booking to a new time span, it keeps its position in the order the bookings were added.
+    fn move_to(&mut self, booking: &Arc<RwLock<Booking>>, start: u32, end: u32) {
+        if let Some(i) = self.entries.iter().position(|(_, _, _, b)| Arc::ptr_eq(b, booking)) {
+            let (_, _, seq, b) = self.entries.remove(i);
+            let pos = self.entries.partition_point(|(s, _, _, _)| *s <= start);
+            self.entries.insert(pos, (start, end, seq, b));
+            self.max_len = self.max_len.max(end.saturating_sub(start));
+        }
+    }
+
+    fn overlapping(&self, start: u32, end: u32) -> Vec<Arc<RwLock<Booking>>> {
+        // only bookings starting in [start - max_len, end) can overlap
+        let from = self.entries.partition_point(|(s, _, _, _)| s.saturating_add(self.max_len) < start);
+        let to = self.entries.partition_point(|(s, _, _, _)| *s < end);
+        let mut found: Vec<&(u32, u32, u64, Arc<RwLock<Booking>>)> = self.entries[from..to.max(from)].iter().filter(|(s, e, _, _)| *s < end && start < *e).collect();
+        found.sort_by_key(|(_, _, seq, _)| *seq);
+        found.into_iter().map(|(_, _, _, b)| b.clone()).collect()
+    }
+}
+
+////////////////// Facility store ///////////////////
+
+impl FacilityStore {
+    pub fn new() -> FacilityStore {
+        FacilityStore { facilities: Vec::new() }
+    }
+
+    // Registers a facility in the store and returns the shared reference to it.
+    pub fn register(&mut self, facility: Facility) -> Arc<RwLock<Facility>> {
+        let facility = Arc::new(RwLock::new(facility));
+        self.facilities.push(facility.clone());
+        facility
+    }
+}
+
+////////////////// Facility pool ///////////////////
+
+impl FacilityPool {
+    pub fn new(fac_type: FacilityType) -> FacilityPool {
+        FacilityPool { fac_type, members: Vec::new() }
+    }
+
+    // Adds a facility to the pool, unless it is of another type; returns whether it was added.
+    pub fn add(&mut self, facility: Arc<RwLock<Facility>>) -> bool {
+        if facility.read().unwrap().fac_type != self.fac_type {
+            return false;
+        }
+        self.members.push(facility);
+        true
+    }
+}
+
+// This function returns all facilities of the given type in the store, sorted by their id
+// so that searching them for the first fit always goes through them in the same order.
+pub fn facilities_of_type(store: &FacilityStore, fac_type: FacilityType) -> Vec<Arc<RwLock<Facility>>> {
+    let mut matches: Vec<(u32, Arc<RwLock<Facility>>)> = Vec::new();
+    for f in &store.facilities {
+        let facility = f.read().unwrap();
+        if facility.fac_type == fac_type {
+            matches.push((facility.id, f.clone()));
+        }
+    }
+    matches.sort_by_key(|(id, _)| *id);
+    matches.into_iter().map(|(_, f)| f).collect()
+}
+
+////////////////// Booking constructor ///////////////////
+
+// Booking ids are handed out from a global counter, so they are unique across all threads.
+static NEXT_BOOKING_ID: AtomicU64 = AtomicU64::new(1);
+
+impl Booking {
+    pub fn new(start: u32, end: u32, facility: Arc<RwLock<Facility>>, user: Arc<User>) -> Booking {
+        let id = NEXT_BOOKING_ID.fetch_add(1, Ordering::Relaxed);
+        Booking { id, start, end, created_at: 0, facility, users: vec![user], status: UNCONFIRMED, compound: None, required: true, tags: Vec::new(), units: 1 }
+    }
+
+    // Same as new, but for a booking that is only checked and never made, e.g. by can_book, so it takes no id
+    // from the counter. It gets an id after all the ones handed out, so it orders like a new booking.
+    pub fn dry_run(start: u32, end: u32, facility: Arc<RwLock<Facility>>, user: Arc<User>) -> Booking {
+        Booking { id: u64::MAX, start, end, created_at: 0, facility, users: vec![user], status: UNCONFIRMED, compound: None, required: true, tags: Vec::new(), units: 1 }
+    }
+
+    // Same as new, but the booking is made by the user on behalf of a group, the participants.
+    pub fn for_group(start: u32, end: u32, facility: Arc<RwLock<Facility>>, user: Arc<User>, participants: Vec<Arc<User>>) -> Booking {
+        let mut booking = Booking::new(start, end, facility, user);
+        booking.users.extend(participants);
+        booking
+    }
+
+    // The order of bookings: by start, and bookings starting at the same time by id, i.e. the one created
+    // first comes first. It is a total order, so whichever order the bookings are found in (which depends
+    // on when their threads got to the facility), the same booking comes first.
+    pub fn order(&self, other: &Booking) -> std::cmp::Ordering {
+        (self.start, self.id).cmp(&(other.start, other.id))
+    }
+
+    // Creates the booking of the skeleton for the user, with the tags and units of the skeleton.
+    pub fn from_skeleton(skeleton: &BookingSkeleton, user: Arc<User>) -> Booking {
+        let mut booking = Booking::new(skeleton.start, skeleton.end, skeleton.facility.clone(), user);
+        booking.tags = skeleton.tags.clone();
+        booking.units = skeleton.units;
+        booking
+    }
+
+    // The user who made the booking, whose priority and permissions count for it.
+    pub fn user(&self) -> &Arc<User> {
+        &self.users[0]
+    }
+
+    // Sends the cancelled booking to all of its users, see User::notify.
+    pub fn notify_users(&self, booking: &Arc<RwLock<Booking>>) {
+        for user in &self.users {
+            // the user may have been shut down already
+            let _ = user.notify(booking.clone());
+        }
+    }
+}
+
+// Bookings are the same booking if they have the same id, and are ordered by their time span,
+// the id breaking ties, so sorting them gives a stable order by start time.
+impl PartialEq for Booking {
+    fn eq(&self, other: &Booking) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Booking {}
+
+impl PartialOrd for Booking {
+    fn partial_cmp(&self, other: &Booking) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Booking {
+    fn cmp(&self, other: &Booking) -> std::cmp::Ordering {
+        (self.start, self.end, self.id).cmp(&(other.start, other.end, other.id))
+    }
+}
+
+impl BookingSkeleton {
+    // A skeleton of the facility from start to end without tags, taking up a single unit. It isn't checked,
+    // the builder is there for that.
+    pub fn new(start: u32, end: u32, facility: Arc<RwLock<Facility>>) -> BookingSkeleton {
+        BookingSkeleton { start, end, facility, tags: Vec::new(), units: 1 }
+    }
+
+    // A skeleton of the given duration from start on, or None if its end does not fit into the time range.
+    pub fn from_duration(start: u32, duration: u32, facility: Arc<RwLock<Facility>>) -> Option<BookingSkeleton> {
+        let end = start.checked_add(duration)?;
+        Some(BookingSkeleton::new(start, end, facility))
+    }
+}
+
+////////////////// Formatting ///////////////////
+
+// Bookings and facilities reference each other, so formatting never follows these references
+// further than one level and only peeks at a facility if it is not locked right now,
+// as the facility may well be locked by the thread doing the formatting.
+
+// e.g. "User 3 VIP booked Room [10,20] CONFIRMED"
+impl fmt::Display for Booking {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fac_type = match self.facility.try_read() {
+            Ok(facility) => facility_type_to_string(facility.fac_type),
+            Err(_) => "a locked facility".to_string(),
+        };
+        write!(f, "User {} {} booked {} [{},{}] {}", self.user().id, vip_bool_to_string(self.user().vip), fac_type, self.start, self.end, booking_status_to_string(self.status).to_uppercase())
+    }
+}
+
+impl fmt::Debug for Booking {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Booking")
+            .field("id", &self.id)
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("created_at", &self.created_at)
+            .field("facility_id", &self.facility.try_read().ok().map(|facility| facility.id))
+            .field("user", &self.user())
+            .field("status", &booking_status_to_string(self.status))
+            .field("compound_size", &self.compound.as_ref().map(|c| c.len()))
+            .finish()
+    }
+}
+
+impl fmt::Debug for Facility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Facility")
+            .field("id", &self.id)
+            .field("fac_type", &facility_type_to_string(self.fac_type))
+            .field("capacity", &self.capacity)
+            .field("overbook_pct", &self.overbook_pct)
+            .field("capacity_mode", &self.capacity_mode)
+            .field("allow_displacement", &self.allow_displacement)
+            .field("bookings", &self.bookings.len())
+            .field("audit_entries", &self.audit_log.len())
+            .field("victim_policy", &self.victim_policy)
+            .finish()
+    }
+}
+
+impl fmt::Debug for User {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("User")
+            .field("id", &self.id)
+            .field("vip", &self.vip)
+            .finish()
+    }
+}
+
+////////////////// Timer function ///////////////////
+
+impl ProgramTime {
+    // A program time at the given time, it only advances if a thread ticks it.
+    pub fn new(time: u32, max_horizon: u32) -> ProgramTime {
+        ProgramTime { time: AtomicU32::new(time), paused: AtomicBool::new(false), max_horizon, epoch: 0, seconds_per_tick: 1 }
+    }
+
+    // Formats a tick as the time of day it stands for, e.g. 01:00:00 for tick 3600 with one second
+    // per tick and epoch 0. The hours keep counting past a day instead of wrapping around.
+    pub fn format_time(&self, tick: u32) -> String {
+        let seconds = self.epoch as u64 + tick as u64 * self.seconds_per_tick as u64;
+        format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    }
+
+    // Stops the time from advancing until it is resumed, e.g. to step through a schedule.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    // Lets the time advance again, from where it was paused.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ProgramTime {
+    fn get_current_time(&self) -> u32 {
+        self.time.load(Ordering::Relaxed)
+    }
+
+    fn get_horizon_end(&self) -> u32 {
+        self.get_current_time().saturating_add(self.max_horizon)
+    }
+}
+
+impl ManualClock {
+    // A manual clock at time 0 without a booking horizon.
+    pub fn new() -> ManualClock {
+        ManualClock { time: AtomicU32::new(0), max_horizon: u32::MAX }
+    }
+
+    // Advances the clock by n time units.
+    pub fn tick(&self, n: u32) {
+        let _ = self.time.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |t| Some(t.saturating_add(n)));
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> ManualClock {
+        ManualClock::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn get_current_time(&self) -> u32 {
+        self.time.load(Ordering::Relaxed)
+    }
+
+    fn get_horizon_end(&self) -> u32 {
+        self.get_current_time().saturating_add(self.max_horizon)
+    }
+}
+
+// Our program time is started and the Arc to the ProgramTime is returned,
+// it advances by one every 100ms
+pub fn start_program_time() -> Arc<ProgramTime> {
+    start_program_time_with_tick(Duration::from_millis(100))
+}
+
+// Like start_program_time, but the program time advances by one every tick,
+// e.g. faster in tests that wait for the time to pass
+pub fn start_program_time_with_tick(tick: Duration) -> Arc<ProgramTime> {
+    // Create a shared state for ProgramTime using Arc
+    let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+    // Clone Arc for the closure
+    let program_time_clone = program_time.clone();
+
+    // Create a thread to increment program time, sleeping until the next tick in between
+    thread::spawn(move || {
+        loop {
+            thread::sleep(tick);
+            // the ticks passing while paused are skipped
+            if !program_time_clone.paused.load(Ordering::Relaxed) {
+                program_time_clone.time.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    });
+
+    program_time
+}
+
+
+////////////////// Hold expiry ///////////////////
+
+// This function cancels all unconfirmed bookings of the facilities that have been pending for longer
+// than the ttl, e.g. the parts of a compound whose user thread stalled, and returns how many it cancelled.
+// Tentative bookings the user didn't confirm in time expire the same way.
+// Bookings that are locked right now are being worked on and therefore skipped. The expiries are reported
+// to the sink and the users of the expired bookings are notified, once the facility is released again.
+pub fn reap_unconfirmed(facilities: &[Arc<RwLock<Facility>>], ttl: u32, program_time: &SharedClock, sink: &SharedSink) -> u32 {
+    let now = program_time.get_current_time();
+    let mut reaped = 0;
+    for f in facilities {
+        let mut facility = f.write().unwrap();
+        let mut expired: Vec<Arc<RwLock<Booking>>> = Vec::new();
+        let mut entries: Vec<AuditEntry> = Vec::new();
+        for b in facility.bookings() {
+            if let Ok(mut bmut) = b.try_write() {
+                if bmut.status & (UNCONFIRMED | TENTATIVE) != 0 && now.saturating_sub(bmut.created_at) > ttl {
+                    bmut.status = CANCELLED;
+                    facility.cancelled(&bmut);
+                    entries.push(AuditEntry { time: now, action: AuditAction::Cancelled, user_id: bmut.user().id, start: bmut.start, end: bmut.end, reason: AuditReason::HoldExpired });
+                    sink.emit(&BookingEvent::Expired { id: bmut.id, user_id: bmut.user().id, vip: bmut.user().vip, fac_type: facility.fac_type, start: bmut.start, end: bmut.end });
+                    expired.push(b.clone());
+                }
+            }
+        }
+        reaped += expired.len() as u32;
+        facility.audit_log.extend(entries);
+        facility.publish();
+        drop(facility);
+
+        // notify the users of the expired bookings
+        for b in &expired {
+            b.read().unwrap().notify_users(b);
+        }
+    }
+    reaped
+}
+
+// The reaper is started and cancels expired unconfirmed bookings of the facilities with every tick,
+// reporting them to the sink.
+pub fn start_reaper(facilities: Vec<Arc<RwLock<Facility>>>, ttl: u32, program_time: SharedClock, sink: SharedSink) {
+    thread::spawn(move || {
+        loop {
+            thread::sleep(Duration::from_millis(100));
+            reap_unconfirmed(&facilities, ttl, &program_time, &sink);
+        }
+    });
+}
+
+////////////////// Compaction ///////////////////
+
+// This function removes the cancelled bookings and the bookings that are over from the facilities,
+// so the lists of bookings of a long running system don't grow without bound, and returns how many
+// it removed. The audit logs keep what happened to them. Bookings that are locked right now are
+// being worked on and therefore kept until the next time.
+pub fn compact(facilities: &[Arc<RwLock<Facility>>], program_time: &SharedClock) -> u32 {
+    let now = program_time.get_current_time();
+    let mut removed = 0;
+    for f in facilities {
+        let mut facility = f.write().unwrap();
+        let stale: Vec<Arc<RwLock<Booking>>> = facility.bookings().iter().filter(|b| match b.try_read() {
+            Ok(b) => b.status == CANCELLED || b.end < now,
+            Err(_) => false,
+        }).cloned().collect();
+        for b in &stale {
+            facility.remove_booking(b);
+        }
+        facility.publish();
+        removed += stale.len() as u32;
+    }
+    removed
+}
+
+// The compactor compacts the facilities in the background until it is stopped.
+pub struct Compactor {
+    stopped: Arc<RwLock<bool>>,
+    thread: JoinHandle<()>,
+}
+
+impl Compactor {
+    // Stops the compactor and waits for it, which takes up to one interval.
+    pub fn stop(self) {
+        *self.stopped.write().unwrap() = true;
+        self.thread.join().unwrap();
+    }
+}
+
+// The compactor is started next to the program time and compacts the facilities every interval.
+pub fn start_compactor(facilities: Vec<Arc<RwLock<Facility>>>, interval: Duration, program_time: SharedClock) -> Compactor {
+    let stopped = Arc::new(RwLock::new(false));
+    let stop = stopped.clone();
+    let thread = thread::spawn(move || {
+        while !*stop.read().unwrap() {
+            compact(&facilities, &program_time);
+            thread::sleep(interval);
+        }
+    });
+    Compactor { stopped, thread }
+}
+
+////////////////// Tentative bookings ///////////////////
+
+// A user can grab a slot first and commit to it later: reserve places a tentative booking, which takes up
+// capacity like a hold, and confirm turns it into a confirmed booking. Tentative bookings which aren't confirmed
+// expire through reap_unconfirmed. Like the holds, they only count for the capacity checks taking holds into account.
+
+// This function places a tentative booking of the facility from start to end for the user, if the capacity allows.
+pub fn reserve(facility: &Arc<RwLock<Facility>>, start: u32, end: u32, user: Arc<User>, program_time: SharedClock) -> Result<Arc<RwLock<Booking>>, RejectReason> {
+    let mut fac = facility.write().unwrap();
+
+    // check if the booking is in the future
+    let current_time = program_time.get_current_time();
+    if start < current_time {
+        return Err(RejectReason::TimeInPast { current_time });
+    }
+
+    // check if the booking ends within the booking horizon
+    let horizon_end = program_time.get_horizon_end();
+    if end > horizon_end {
+        return Err(RejectReason::BeyondHorizon { horizon_end });
+    }
+
+    // if the capacity is exceeded, decline the booking
+    if fac.is_full(occupied_overlaps(&fac, start, end), fac.capacity_between(start, end)) {
+        return Err(RejectReason::CapacityExceeded);
+    }
+
+    // here the tentative booking can be placed, it expires counting from now
+    let mut booking = Booking::new(start, end, facility.clone(), user.clone());
+    booking.status = TENTATIVE;
+    booking.created_at = current_time;
+    let booking = Arc::new(RwLock::new(booking));
+    fac.add_booking(booking.clone(), start, end);
+    fac.audit_log.push(AuditEntry { time: current_time, action: AuditAction::Booked, user_id: user.id, start, end, reason: AuditReason::UserRequest });
+    fac.publish();
+    Ok(booking)
+}
+
+// This function confirms a tentative booking. It returns false if the booking isn't tentative (anymore),
+// e.g. because it expired in the meantime. The booking is in the facility already, so the facility is locked first.
+pub fn confirm(booking: &Arc<RwLock<Booking>>, program_time: SharedClock) -> bool {
+    let facility_arc = booking.read().unwrap().facility.clone();
+    let mut facility = facility_arc.write().unwrap();
+    let mut booking_mut = booking.write().unwrap();
+    if booking_mut.status != TENTATIVE {
+        return false;
+    }
+    facility.audit_log.push(AuditEntry { time: program_time.get_current_time(), action: AuditAction::Confirmed, user_id: booking_mut.user().id, start: booking_mut.start, end: booking_mut.end, reason: AuditReason::UserRequest });
+    booking_mut.status = CONFIRMED;
+    drop(booking_mut);
+    facility.publish();
+    true
+}
+
+
+////////////////// Worker pool ///////////////////
+
+// A job for the worker pool, e.g. running a user.
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// A fixed number of worker threads taking jobs from a shared queue, so that running many users
+// does not need one thread per user. The receiving end of the queue can only be shared behind a Mutex.
+pub struct WorkerPool {
+    workers: Vec<JoinHandle<()>>,
+    queue: Option<mpsc::Sender<Job>>,
+}
+
+impl WorkerPool {
+    // Starts a pool with the given number of workers (at least one).
+    pub fn new(size: usize) -> WorkerPool {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        let workers = (0..size.max(1)).map(|_| {
+            let rx = Arc::clone(&rx);
+            thread::spawn(move || {
+                loop {
+                    // the lock is only held while taking the next job off the queue
+                    let job = rx.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                }
+            })
+        }).collect();
+        WorkerPool { workers, queue: Some(tx) }
+    }
+
+    // Queues a job, it is run by the next free worker.
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        self.queue.as_ref().unwrap().send(Box::new(job)).unwrap();
+    }
+
+    // Waits until all queued jobs are done and stops the workers.
+    pub fn join(mut self) {
+        drop(self.queue.take());
+        for worker in self.workers.drain(..) {
+            worker.join().unwrap();
+        }
+    }
+}
+
+////////////////// User threads ///////////////////
+
+// The users started by the vip and compound systems. After booking, each user thread waits
+// for cancellation messages until the users are shut down.
+pub struct Users {
+    pub stats: Arc<RwLock<Stats>>,
+    threads: Vec<JoinHandle<Vec<BookingResult>>>,
+    shutdown: Arc<RwLock<bool>>,
+}
+
+impl Users {
+    pub fn new(stats: Arc<RwLock<Stats>>, threads: Vec<JoinHandle<Vec<BookingResult>>>, shutdown: Arc<RwLock<bool>>) -> Users {
+        Users { stats, threads, shutdown }
+    }
+
+    // Tells the users to stop waiting for cancellation messages once their inbox is empty.
+    // Cancellations for a user that has stopped are no longer delivered.
+    pub fn shutdown(&self) {
+        *self.shutdown.write().unwrap() = true;
+    }
+
+    // Shuts the users down and returns once every user thread is done, with the results of the bookings
+    // of each user in the order the users were started (none for the users of the compound system).
+    pub fn join_all(self) -> Vec<Vec<BookingResult>> {
+        self.shutdown();
+        self.threads.into_iter().map(|t| t.join().unwrap()).collect()
+    }
+}
+
+// This function reports the cancellation messages arriving on the inbox of a user
+// until the users are shut down and the inbox is empty.
+pub fn wait_for_cancellations(inbox: mpsc::Receiver<Arc<RwLock<Booking>>>, shutdown: Arc<RwLock<bool>>, sink: &SharedSink) {
+    loop {
+        match inbox.recv_timeout(Duration::from_millis(100)) {
+            Ok(msg) => {
+                let msg = msg.read().unwrap();
+                // report user X received cancel message
+                sink.emit(&BookingEvent::Notified { id: msg.id, user_id: msg.user().id, vip: msg.user().vip });
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if *shutdown.read().unwrap() {
+                    break;
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+/////////////////////// Helpers /////////////////////
+
+// This functions checks if two bookings overlap.
+// It returns true if they overlap and false otherwise.
+// The bookings are taken as half-open, so a booking ending when the other one starts doesn't overlap it.
+pub fn overlap(b1: &Booking, b2: &Booking) -> bool {
+    overlap_in_mode(b1, b2, OverlapMode::HalfOpen)
+}
+
+// This function checks if an existing booking conflicts with the time span from start to end,
+// i.e. if they overlap once the existing booking is widened by the buffer on both sides.
+pub fn conflicts(existing: &Booking, start: u32, end: u32, buffer: u32) -> bool {
+    existing.start.saturating_sub(buffer) < end && start < existing.end.saturating_add(buffer)
+}
+
+// Same as overlap, but in the given mode. In the closed mode bookings that only touch,
+// e.g. from 1 to 2 and from 2 to 4, overlap as well.
+pub fn overlap_in_mode(b1: &Booking, b2: &Booking, mode: OverlapMode) -> bool {
+    let (first, second) = if b1.order(b2).is_lt() { (b1, b2) } else { (b2, b1) };
+    match mode {
+        OverlapMode::HalfOpen => first.end > second.start,
+        OverlapMode::Closed => first.end >= second.start,
+    }
+}
+
+// This function counts the units taken up by the confirmed bookings of a facility overlapping the given time span.
+pub fn confirmed_overlaps(facility: &Facility, start: u32, end: u32) -> u32 {
+    let mut overlaps = 0;
+    for b in facility.conflicting(start, end) {
+        let b = b.read().unwrap();
+        if b.status == CONFIRMED && conflicts(&b, start, end, facility.buffer) {
+            overlaps += b.units;
+        }
+    }
+    overlaps
+}
+
+// This function returns the confirmed bookings of a facility overlapping the booking, taking the buffer
+// of the facility into account. The booking itself is only among them if it is confirmed already.
+pub fn overlapping_confirmed(facility: &Facility, booking: &Booking) -> Vec<Arc<RwLock<Booking>>> {
+    facility.conflicting(booking.start, booking.end).into_iter().filter(|b| {
+        let b = b.read().unwrap();
+        b.status == CONFIRMED && conflicts(&b, booking.start, booking.end, facility.buffer)
+    }).collect()
+}
+
+// This function counts the units taken up by the bookings of a facility overlapping the given time span which take up
+// capacity, these are the confirmed ones, the holds of two-phase bookings, the pending ones and the tentative ones.
+pub fn occupied_overlaps(facility: &Facility, start: u32, end: u32) -> u32 {
+    let mut overlaps = 0;
+    for b in facility.conflicting(start, end) {
+        let b = b.read().unwrap();
+        if b.status & (CONFIRMED | HELD | UNCONFIRMED | TENTATIVE) != 0 && conflicts(&b, start, end, facility.buffer) {
+            overlaps += b.units;
+        }
+    }
+    overlaps
+}
+
+// This function checks a request of the user to book the facility from start to end against the rules of the
+// facility and the program time, but not against the room left in it: the user has to be permitted to book the
+// facility, the booking short enough, in the future, made far enough in advance and ending within the horizon.
+// The first rule broken is told, in this order.
+pub fn check_request(facility: &Facility, user: &User, start: u32, end: u32, program_time: &SharedClock) -> Result<(), RejectReason> {
+    if !user.may_book(facility.fac_type) {
+        return Err(RejectReason::NotPermitted);
+    }
+    if let Some(max_duration) = facility.exceeded_max_duration(start, end) {
+        return Err(RejectReason::TooLong { max_duration });
+    }
+    let current_time = program_time.get_current_time();
+    if start < current_time {
+        return Err(RejectReason::TimeInPast { current_time });
+    }
+    if facility.too_soon(start, current_time) {
+        return Err(RejectReason::TooSoon { min_lead: facility.min_lead });
+    }
+    let horizon_end = program_time.get_horizon_end();
+    if end > horizon_end {
+        return Err(RejectReason::BeyondHorizon { horizon_end });
+    }
+    Ok(())
+}
+
+// This function expands a recurring booking skeleton into the skeletons of its occurrences.
+// They are booked like any other bookings, so some occurrences may succeed while others fail.
+// Occurrences that would lie beyond the largest time are left out instead of wrapping around.
+pub fn expand_recurring(r: &RecurringSkeleton) -> Vec<BookingSkeleton> {
+    (0..r.count).map_while(|i| {
+        let offset = i.checked_mul(r.period)?;
+        Some(BookingSkeleton::new(r.start.checked_add(offset)?, r.end.checked_add(offset)?, r.facility.clone()))
+    }).collect()
+}
+
+// This function converts a facility type to a string.
+pub fn facility_type_to_string(fac_type: FacilityType) -> String {
+    match fac_type {
+        ROOM => "Room".to_string(),
+        PROJECTOR => "Projector".to_string(),
+        _ => FACILITY_TYPE_NAMES.read().unwrap().iter().find(|(t, _)| *t == fac_type).map_or("Unknown".to_string(), |(_, name)| name.clone()),
+    }
+}
+
+// This function converts the name of a facility type back to the type, if it is known.
+pub fn facility_type_from_string(name: &str) -> Option<FacilityType> {
+    match name {
+        "Room" => Some(ROOM),
+        "Projector" => Some(PROJECTOR),
+        _ => FACILITY_TYPE_NAMES.read().unwrap().iter().find(|(_, n)| n == name).map(|(t, _)| *t),
+    }
+}
+
+// This function converts a vip bool to a string.
+pub fn vip_bool_to_string(vip: bool) -> String {
+    match vip {
+        true => "VIP".to_string(),
+        false => "Non-VIP".to_string(),
+    }
+}
+
+// This function converts a booking status to a string.
+pub fn booking_status_to_string(status: BookingStatus) -> String {
+    match status {
+        UNCONFIRMED => "unconfirmed".to_string(),
+        CONFIRMED => "confirmed".to_string(),
+        CANCELLED => "cancelled".to_string(),
+        HELD => "held".to_string(),
+        WAITLISTED => "waitlisted".to_string(),
+        TENTATIVE => "tentative".to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+// This function converts an audit action to a string.
+pub fn audit_action_to_string(action: AuditAction) -> String {
+    match action {
+        AuditAction::Booked => "booked".to_string(),
+        AuditAction::Confirmed => "confirmed".to_string(),
+        AuditAction::Cancelled => "cancelled".to_string(),
+        AuditAction::Rescheduled => "rescheduled".to_string(),
+    }
+}
+
+// This function converts an audit reason to a string.
+pub fn audit_reason_to_string(reason: AuditReason) -> String {
+    match reason {
+        AuditReason::UserRequest => "user request".to_string(),
+        AuditReason::VipBooking => "vip booking".to_string(),
+        AuditReason::HoldExpired => "hold expired".to_string(),
+        AuditReason::AdminRequest => "admin request".to_string(),
+        AuditReason::CompoundFailure => "compound failure".to_string(),
+        AuditReason::Maintenance => "maintenance".to_string(),
+    }
+}
+
+/////////////////////// Displacement /////////////////////
+
+// A displacement policy decides which existing bookings an incoming booking may displace
+// once the facility is at its capacity limit, e.g. vips displacing non-vips.
+pub trait DisplacementPolicy {
+    fn can_displace(&self, incoming: &Booking, existing: &Booking) -> bool;
+}
+
+pub type SharedPolicy = Arc<dyn DisplacementPolicy + Send + Sync>;
+
+// The default policy: bookings of vips displace those of non-vips, nobody else displaces anyone.
+pub struct VipPolicy;
+
+impl DisplacementPolicy for VipPolicy {
+    fn can_displace(&self, incoming: &Booking, existing: &Booking) -> bool {
+        incoming.user().vip && !existing.user().vip
+    }
+}
+
+// This function selects which of the given overlapping bookings the booking displaces, for the vip and the compound
+// system alike. The excluded ones are no candidates, e.g. as they are displaced already, and neither are those
+// starting within the protect window; the candidates are the bookings the policy lets the booking displace.
+// If the facility is fair the users cancelled least so far are preferred, among them the victim policy of
+// the facility decides.
+pub fn select_victim_excluding(facility: &Facility, overlapping: &[Arc<RwLock<Booking>>], booking: &Booking, now: u32, excluded: &[Arc<RwLock<Booking>>], policy: &dyn DisplacementPolicy) -> Option<Arc<RwLock<Booking>>> {
+    let mut victim: Option<Arc<RwLock<Booking>>> = None;
+    // the cancellations and the rank by the victim policy of the victim so far, the least is chosen,
+    // ties go by the order of bookings
+    let mut least: (u32, u32, u32, u64) = (0, 0, 0, 0);
+    for b in overlapping {
+        if excluded.iter().any(|e| Arc::ptr_eq(e, b)) {
+            continue;
+        }
+        let candidate = b.read().unwrap();
+        if policy.can_displace(booking, &candidate) && !facility.protected(&candidate, now) {
+            let cancellations = facility.fairness.as_ref().map_or(0, |f| f.cancellations(candidate.user().id));
+            let rank = facility.victim_policy.rank(&candidate, now);
+            if victim.is_none() || (cancellations, rank, candidate.start, candidate.id) < least {
+                victim = Some(b.clone());
+                least = (cancellations, rank, candidate.start, candidate.id);
+            }
+        }
+    }
+    victim
+}
+
+/////////////////////// Rescheduling /////////////////////
+
+// This function moves a confirmed booking to the time span from new_start to new_end, if the facility has room
+// for it then. The booking itself doesn't count against the new time span, and the facility stays locked
+// from the check to the update, so no other booking can take the slot in between. As the booking is in the
+// facility already, the facility is locked before the booking, like everywhere else the bookings of a facility
+// are changed. The move is recorded in the audit log of the facility.
+// If the new time span is empty, breaks a rule of the facility or the program time a new booking would be
+// checked against (see check_request) or is full, or the booking isn't confirmed, the booking is left as it was.
+pub fn reschedule(booking: &Arc<RwLock<Booking>>, new_start: u32, new_end: u32, program_time: SharedClock) -> BookingResult {
+    // check if the new time span is a time span at all
+    if new_end <= new_start {
+        return BookingResult::Rejected { reason: RejectReason::EmptySpan };
+    }
+
+    // lock the facility
+    let facility_arc = booking.read().unwrap().facility.clone();
+    let mut facility = facility_arc.write().unwrap();
+
+    // lock the booking, only confirmed bookings are moved
+    let mut booking_mut = booking.write().unwrap();
+    if booking_mut.status != CONFIRMED {
+        return BookingResult::Rejected { reason: RejectReason::NotConfirmed };
+    }
+
+    // check the new time span like that of a new booking
+    if let Err(reason) = check_request(&facility, booking_mut.user(), new_start, new_end, &program_time) {
+        return reason.into();
+    }
+
+    // count the units of the confirmed bookings overlapping the new time span, except for the booking itself
+    let mut overlaps = 0;
+    for b in facility.conflicting(new_start, new_end) {
+        if Arc::ptr_eq(&b, booking) {
+            continue;
+        }
+        let b = b.read().unwrap();
+        if b.status == CONFIRMED && conflicts(&b, new_start, new_end, facility.buffer) {
+            overlaps += b.units;
+        }
+    }
+    if facility.is_full_for(overlaps, booking_mut.units, facility.capacity_between(new_start, new_end)) {
+        return BookingResult::Rejected { reason: RejectReason::CapacityExceeded };
+    }
+
+    // here the booking can be moved
+    facility.move_booking(booking, new_start, new_end);
+    booking_mut.start = new_start;
+    booking_mut.end = new_end;
+    facility.audit_log.push(AuditEntry { time: program_time.get_current_time(), action: AuditAction::Rescheduled, user_id: booking_mut.user().id, start: new_start, end: new_end, reason: AuditReason::UserRequest });
+    let id = booking_mut.id;
+    drop(booking_mut);
+    facility.publish();
+    BookingResult::Confirmed { id }
+}
+
+// This function exchanges the time spans of two confirmed bookings, of the same or of different facilities,
+// if both facilities have room for them afterwards; otherwise both are left as they were and false is returned.
+// The facilities are locked first and then the bookings, like everywhere else the bookings of a facility are
+// changed, each in the order of their ids, and stay locked from the check to the update, so concurrent swaps
+// cannot deadlock and no other booking can interfere.
+pub fn swap_bookings(a: &Arc<RwLock<Booking>>, b: &Arc<RwLock<Booking>>) -> bool {
+    if Arc::ptr_eq(a, b) {
+        return false;
+    }
+
+    // lock the facilities, and then the bookings
+    let a_facility_arc = a.read().unwrap().facility.clone();
+    let b_facility_arc = b.read().unwrap().facility.clone();
+    if Arc::ptr_eq(&a_facility_arc, &b_facility_arc) {
+        let mut facility = a_facility_arc.write().unwrap();
+        let (mut a_mut, mut b_mut) = write_both(a, b);
+        if a_mut.status != CONFIRMED || b_mut.status != CONFIRMED {
+            return false;
+        }
+        let (a_span, b_span) = ((a_mut.start, a_mut.end), (b_mut.start, b_mut.end));
+        if !fits_after_swap(&facility, a, b, b_span, a_mut.units, Some((a_span, b_mut.units))) || !fits_after_swap(&facility, a, b, a_span, b_mut.units, Some((b_span, a_mut.units))) {
+            return false;
+        }
+        facility.move_booking(a, b_span.0, b_span.1);
+        facility.move_booking(b, a_span.0, a_span.1);
+        (a_mut.start, a_mut.end) = b_span;
+        (b_mut.start, b_mut.end) = a_span;
+        drop((a_mut, b_mut));
+        facility.publish();
+    }
+    else {
+        let a_facility_first = a_facility_arc.read().unwrap().id < b_facility_arc.read().unwrap().id;
+        let (first, second) = if a_facility_first { (&a_facility_arc, &b_facility_arc) } else { (&b_facility_arc, &a_facility_arc) };
+        let first_guard = first.write().unwrap();
+        let second_guard = second.write().unwrap();
+        let (mut a_facility, mut b_facility) = if a_facility_first { (first_guard, second_guard) } else { (second_guard, first_guard) };
+        let (mut a_mut, mut b_mut) = write_both(a, b);
+        if a_mut.status != CONFIRMED || b_mut.status != CONFIRMED {
+            return false;
+        }
+        let (a_span, b_span) = ((a_mut.start, a_mut.end), (b_mut.start, b_mut.end));
+        if !fits_after_swap(&a_facility, a, b, b_span, a_mut.units, None) || !fits_after_swap(&b_facility, a, b, a_span, b_mut.units, None) {
+            return false;
+        }
+        a_facility.move_booking(a, b_span.0, b_span.1);
+        b_facility.move_booking(b, a_span.0, a_span.1);
+        (a_mut.start, a_mut.end) = b_span;
+        (b_mut.start, b_mut.end) = a_span;
+        drop((a_mut, b_mut));
+        a_facility.publish();
+        b_facility.publish();
+    }
+    true
+}
+
+// This function write locks the two bookings in the order of their ids and returns the guards in the order of the bookings.
+fn write_both<'a>(a: &'a Arc<RwLock<Booking>>, b: &'a Arc<RwLock<Booking>>) -> (RwLockWriteGuard<'a, Booking>, RwLockWriteGuard<'a, Booking>) {
+    let a_first = a.read().unwrap().id < b.read().unwrap().id;
+    let (first, second) = if a_first { (a, b) } else { (b, a) };
+    let first_mut = first.write().unwrap();
+    let second_mut = second.write().unwrap();
+    if a_first { (first_mut, second_mut) } else { (second_mut, first_mut) }
+}
+
+// This function tells whether the facility has room for one of the swapped bookings, taking up units, in the time span once
+// the swap is done. The swapped bookings are passed by, except for the other one when it moves to its new time span (given
+// with its units) in the same facility.
+fn fits_after_swap(facility: &Facility, a: &Arc<RwLock<Booking>>, b: &Arc<RwLock<Booking>>, span: (u32, u32), units: u32, other: Option<((u32, u32), u32)>) -> bool {
+    let (start, end) = span;
+    if facility.exceeded_max_duration(start, end).is_some() {
+        return false;
+    }
+
+    // count the confirmed bookings overlapping the time span, the swapped ones are locked by the caller
+    let mut overlaps = 0;
+    for c in facility.conflicting(start, end) {
+        if Arc::ptr_eq(&c, a) || Arc::ptr_eq(&c, b) {
+            continue;
+        }
+        let c = c.read().unwrap();
+        if c.status == CONFIRMED && conflicts(&c, start, end, facility.buffer) {
+            overlaps += c.units;
+        }
+    }
+    if let Some(((other_start, other_end), other_units)) = other {
+        if other_start.saturating_sub(facility.buffer) < end && start < other_end.saturating_add(facility.buffer) {
+            overlaps += other_units;
+        }
+    }
+    !facility.is_full_for(overlaps, units, facility.capacity_between(start, end))
+}
+
+/////////////////////// Audit export /////////////////////
+
+// This function exports an audit log as newline-delimited JSON, one object per entry.
+// Timestamps and reasons are written as strings so the lines are readable as is.
+pub fn export_audit_ndjson(log: &[AuditEntry]) -> String {
+    let mut out = String::new();
+    for entry in log {
+        let line = json!({
+            "timestamp": entry.time.to_string(),
+            "action": audit_action_to_string(entry.action),
+            "user_id": entry.user_id,
+            "start": entry.start,
+            "end": entry.end,
+            "reason": audit_reason_to_string(entry.reason),
+        });
+        out.push_str(&line.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+/////////////////////// Metrics export /////////////////////
+
+// This function renders the stats of a run and the state of the facilities in the Prometheus text format,
+// e.g. to be served over HTTP. The active bookings of a facility are its confirmed ones.
+pub fn render_metrics(facilities: &[Arc<RwLock<Facility>>], stats: &Stats) -> String {
+    let mut out = String::new();
+    out.push_str("# TYPE reservations_confirmed_total counter\n");
+    out.push_str(&format!("reservations_confirmed_total {}\n", stats.confirmed));
+    out.push_str("# TYPE reservations_cancelled_total counter\n");
+    out.push_str(&format!("reservations_cancelled_total {}\n", stats.cancelled));
+
+    // the facility metrics are gauges, one line per facility
+    let mut capacity = String::from("# TYPE facility_capacity gauge\n");
+    let mut active = String::from("# TYPE facility_active_bookings gauge\n");
+    for f in facilities {
+        let facility = f.read().unwrap();
+        let labels = format!("{{facility_id=\"{}\",facility_type=\"{}\"}}", facility.id, facility_type_to_string(facility.fac_type).to_lowercase());
+        let confirmed = facility.bookings().iter().filter(|b| b.read().unwrap().status == CONFIRMED).count();
+        capacity.push_str(&format!("facility_capacity{} {}\n", labels, facility.capacity));
+        active.push_str(&format!("facility_active_bookings{} {}\n", labels, confirmed));
+    }
+    out.push_str(&capacity);
+    out.push_str(&active);
+    out
+}
+
+/////////////////////// State export /////////////////////
+
+// The facilities, bookings and users hold locks, channels and references to each other,
+// so they are serialized as these flat records instead. A booking refers to its facility
+// and its user by id, and the facility lists its bookings.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct FacilityState {
+    pub id: u32,
+    pub fac_type: FacilityType,
+    pub capacity: u32,
+    pub bookings: Vec<BookingState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct BookingState {
+    pub id: u64,
+    pub start: u32,
+    pub end: u32,
+    pub facility_id: u32,
+    pub user_id: u32,
+    pub status: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct UserState {
+    pub id: u32,
+    pub vip: bool,
+}
+
+// This function creates the record of a booking of the facility with the given id.
+pub fn booking_state(booking: &Booking, facility_id: u32) -> BookingState {
+    BookingState { id: booking.id, start: booking.start, end: booking.end, facility_id, user_id: booking.user().id, status: booking_status_to_string(booking.status) }
+}
+
+// This function creates the record of a facility together with its bookings.
+pub fn facility_state(facility: &Facility) -> FacilityState {
+    let bookings = facility.bookings().iter().map(|b| booking_state(&b.read().unwrap(), facility.id)).collect();
+    FacilityState { id: facility.id, fac_type: facility.fac_type, capacity: facility.capacity, bookings }
+}
+
+impl Serialize for Facility {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        facility_state(self).serialize(serializer)
+    }
+}
+
+// Serializing a booking on its own reads the id of its facility, so the facility must not be write locked.
+impl Serialize for Booking {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        booking_state(self, self.facility.read().unwrap().id).serialize(serializer)
+    }
+}
+
+impl Serialize for User {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        UserState { id: self.id, vip: self.vip }.serialize(serializer)
+    }
+}
+
+// This function exports the state of the given facilities and their bookings as a JSON array,
+// which can be read back as a list of facility states.
+pub fn export_state(facilities: &[Arc<RwLock<Facility>>]) -> String {
+    let states: Vec<FacilityState> = facilities.iter().map(|f| facility_state(&f.read().unwrap())).collect();
+    serde_json::to_string(&states).unwrap()
+}
+
+/////////////////////// Snapshots /////////////////////
+
+// A snapshot records the facilities with all their bookings, so the system can be rebuilt from it,
+// e.g. after a crash. Unlike the state export it keeps what is needed to rebuild the bookings as they
+// were: the status, when a booking was placed, whether it is a required part of its compound, and the
+// compounds as lists of booking ids. Users are recorded by id and vip status only.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Snapshot {
+    pub facilities: Vec<FacilitySnapshot>,
+    pub users: Vec<UserState>,
+    pub compounds: Vec<Vec<u64>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct FacilitySnapshot {
+    pub id: u32,
+    pub fac_type: FacilityType,
+    pub capacity: u32,
+    pub buffer: u32,
+    pub cost_per_unit_time: u32,
+    pub waitlist_enabled: bool,
+    pub max_waitlist: Option<usize>,
+    pub max_duration: Option<u32>,
+    pub overbook_pct: u32,
+    pub min_lead: u32,
+    pub bookings: Vec<BookingSnapshot>,
+    pub waitlist: Vec<BookingSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct BookingSnapshot {
+    pub id: u64,
+    pub start: u32,
+    pub end: u32,
+    pub created_at: u32,
+    pub user_id: u32,
+    // the other users of a group booking
+    pub participant_ids: Vec<u32>,
+    pub status: BookingStatus,
+    pub required: bool,
+    pub tags: Vec<String>,
+    pub units: u32,
+}
+
+// This function takes a snapshot of the given facilities. Each facility is only read locked while its
+// lists of bookings are copied, the bookings are read afterwards, so it is best taken while nobody books.
+pub fn snapshot(facilities: &[Arc<RwLock<Facility>>]) -> Snapshot {
+    let mut snapshot = Snapshot { facilities: Vec::new(), users: Vec::new(), compounds: Vec::new() };
+    let mut compounds: Vec<Arc<Vec<Arc<RwLock<Booking>>>>> = Vec::new();
+
+    for f in facilities {
+        let (mut state, bookings, waitlist) = {
+            let facility = f.read().unwrap();
+            let state = FacilitySnapshot { id: facility.id, fac_type: facility.fac_type, capacity: facility.capacity, buffer: facility.buffer, cost_per_unit_time: facility.cost_per_unit_time, waitlist_enabled: facility.waitlist_enabled, max_waitlist: facility.max_waitlist, max_duration: facility.max_duration, overbook_pct: facility.overbook_pct, min_lead: facility.min_lead, bookings: Vec::new(), waitlist: Vec::new() };
+            (state, facility.bookings().to_vec(), facility.waitlist.clone())
+        };
+        for (list, records) in [(&bookings, &mut state.bookings), (&waitlist, &mut state.waitlist)] {
+            for b in list {
+                let b = b.read().unwrap();
+                let participant_ids = b.users[1..].iter().map(|u| u.id).collect();
+                records.push(BookingSnapshot { id: b.id, start: b.start, end: b.end, created_at: b.created_at, user_id: b.user().id, participant_ids, status: b.status, required: b.required, tags: b.tags.clone(), units: b.units });
+                for user in &b.users {
+                    if !snapshot.users.iter().any(|u| u.id == user.id) {
+                        snapshot.users.push(UserState { id: user.id, vip: user.vip });
+                    }
+                }
+                // each compound is recorded once, however many of its parts there are
+                if let Some(compound) = &b.compound {
+                    if !compounds.iter().any(|c| Arc::ptr_eq(c, compound)) {
+                        compounds.push(compound.clone());
+                    }
+                }
+            }
+        }
+        snapshot.facilities.push(state);
+    }
+
+    // the parts of a compound are read after the part referencing it is released
+    for compound in compounds {
+        snapshot.compounds.push(compound.iter().map(|b| b.read().unwrap().id).collect());
+    }
+    snapshot
+}
+
+// This function rebuilds the facilities of a snapshot with their bookings, under their old ids.
+// The bookings of a user, including group bookings, share one user again and reference their new facility, and the parts of each
+// compound reference the same new compound; parts that were in none of the facilities are left out.
+// Nobody listens for cancellations of the users, their receiving ends are dropped.
+// The ids handed out from now on are beyond those of the snapshot, so they stay unique.
+pub fn restore(snapshot: &Snapshot) -> Vec<Arc<RwLock<Facility>>> {
+    // rebuild the users
+    let mut users: HashMap<u32, Arc<User>> = HashMap::new();
+    for u in &snapshot.users {
+        let (tx, _) = mpsc::channel();
+        users.insert(u.id, Arc::new(User { id: u.id, vip: u.vip, adress: tx, allowed_facility_types: None }));
+    }
+
+    // rebuild the facilities and their bookings
+    let mut facilities: Vec<Arc<RwLock<Facility>>> = Vec::new();
+    let mut bookings: HashMap<u64, Arc<RwLock<Booking>>> = HashMap::new();
+    for state in &snapshot.facilities {
+        let mut facility = Facility::new(state.fac_type, state.capacity);
+        facility.id = state.id;
+        facility.buffer = state.buffer;
+        facility.cost_per_unit_time = state.cost_per_unit_time;
+        facility.waitlist_enabled = state.waitlist_enabled;
+        facility.max_waitlist = state.max_waitlist;
+        facility.max_duration = state.max_duration;
+        facility.overbook_pct = state.overbook_pct;
+        facility.min_lead = state.min_lead;
+        let facility_arc = Arc::new(RwLock::new(facility));
+
+        let mut facility = facility_arc.write().unwrap();
+        for (records, waitlisted) in [(&state.bookings, false), (&state.waitlist, true)] {
+            for record in records {
+                let mut booking_users: Vec<Arc<User>> = Vec::new();
+                for id in std::iter::once(&record.user_id).chain(&record.participant_ids) {
+                    let user = users.entry(*id).or_insert_with(|| {
+                        let (tx, _) = mpsc::channel();
+                        Arc::new(User { id: *id, vip: false, adress: tx, allowed_facility_types: None })
+                    });
+                    booking_users.push(user.clone());
+                }
+                let user = booking_users.remove(0);
+                let mut booking = Booking::for_group(record.start, record.end, facility_arc.clone(), user, booking_users);
+                booking.id = record.id;
+                booking.created_at = record.created_at;
+                booking.status = record.status;
+                booking.required = record.required;
+                booking.tags = record.tags.clone();
+                booking.units = record.units;
+                let booking = Arc::new(RwLock::new(booking));
+                if waitlisted {
+                    facility.waitlist.push(booking.clone());
+                } else {
+                    facility.add_booking(booking.clone(), record.start, record.end);
+                }
+                bookings.insert(record.id, booking);
+            }
+        }
+        facility.publish();
+        drop(facility);
+        facilities.push(facility_arc);
+    }
+
+    // link the parts of each compound to the compound again
+    for ids in &snapshot.compounds {
+        let compound: Arc<Vec<Arc<RwLock<Booking>>>> = Arc::new(ids.iter().filter_map(|id| bookings.get(id).cloned()).collect());
+        for b in compound.iter() {
+            b.write().unwrap().compound = Some(compound.clone());
+        }
+    }
+
+    // hand out fresh ids beyond the restored ones
+    if let Some(max) = snapshot.facilities.iter().map(|f| f.id).max() {
+        NEXT_FACILITY_ID.fetch_max(max + 1, Ordering::Relaxed);
+    }
+    if let Some(max) = bookings.keys().max() {
+        NEXT_BOOKING_ID.fetch_max(max + 1, Ordering::Relaxed);
+    }
+
+    facilities
+}
+
+/////////////////////// Scenario config /////////////////////
+
+// A scenario file lists the facilities and the users with their booking requests, e.g.
+// {"facilities": [{"fac_type": "Room", "capacity": 2}],
+//  "users": [{"id": 1, "vip": false, "bookings": [{"facility": 0, "start": 1, "end": 2}]}]}
+// where a booking refers to a facility by its position in the list of facilities.
+// A loaded scenario: the facilities, the bookings of each user, the user ids and their vip stati.
+pub type Scenario = (Vec<Arc<RwLock<Facility>>>, Vec<Vec<BookingSkeleton>>, Vec<u32>, Vec<bool>);
+
+#[derive(Deserialize)]
+struct ScenarioConfig {
+    facilities: Vec<FacilityConfig>,
+    users: Vec<UserConfig>,
+}
+
+#[derive(Deserialize)]
+struct FacilityConfig {
+    fac_type: String,
+    capacity: u32,
+}
+
+#[derive(Deserialize)]
+struct UserConfig {
+    id: u32,
+    vip: bool,
+    bookings: Vec<BookingConfig>,
+}
+
+#[derive(Deserialize)]
+struct BookingConfig {
+    facility: usize,
+    start: u32,
+    end: u32,
+}
+
+// This function loads a scenario from the json file at the given path and returns the facilities,
+// the bookings of each user, the user ids and their vip stati, ready to be handed to start_users.
+// It panics if the file cannot be read or does not describe a valid scenario.
+pub fn load_scenario(path: &str) -> Scenario {
+    let config: ScenarioConfig = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+
+    // create the facilities
+    let mut facilities: Vec<Arc<RwLock<Facility>>> = Vec::new();
+    for f in &config.facilities {
+        let fac_type = facility_type_from_string(&f.fac_type).unwrap_or_else(|| panic!("unknown facility type {}", f.fac_type));
+        facilities.push(Arc::new(RwLock::new(Facility::new(fac_type, f.capacity))));
+    }
+
+    // create the booking skeletons of the users
+    let mut bookings: Vec<Vec<BookingSkeleton>> = Vec::new();
+    let mut user_ids: Vec<u32> = Vec::new();
+    let mut user_stati: Vec<bool> = Vec::new();
+    for u in &config.users {
+        bookings.push(u.bookings.iter().map(|b| BookingSkeleton::new(b.start, b.end, facilities[b.facility].clone())).collect());
+        user_ids.push(u.id);
+        user_stati.push(u.vip);
+    }
+
+    (facilities, bookings, user_ids, user_stati)
+}
+
+// A small seeded random number generator (splitmix64), so generated scenarios can be reproduced.
+struct ScenarioRng {
+    state: u64,
+}
+
+impl ScenarioRng {
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // A number from 0 to n - 1.
+    fn below(&mut self, n: u32) -> u32 {
+        (self.next() % n as u64) as u32
+    }
+}
+
+// This function generates a random scenario from the seed, the same seed always gives the same scenario.
+// About a quarter of the users are vip, the facilities are rooms or projectors with a capacity of 1 to 3
+// and the bookings are short and close together, so many of them overlap.
+pub fn generate_scenario(seed: u64, users: usize, facilities: usize, bookings_per_user: usize) -> Scenario {
+    let mut rng = ScenarioRng { state: seed };
+
+    // create the facilities
+    let mut scenario_facilities: Vec<Arc<RwLock<Facility>>> = Vec::new();
+    for _ in 0..facilities {
+        let fac_type = if rng.below(2) == 0 { ROOM } else { PROJECTOR };
+        scenario_facilities.push(Arc::new(RwLock::new(Facility::new(fac_type, 1 + rng.below(3)))));
+    }
+
+    // create the booking skeletons of the users
+    let mut bookings: Vec<Vec<BookingSkeleton>> = Vec::new();
+    let mut user_ids: Vec<u32> = Vec::new();
+    let mut user_stati: Vec<bool> = Vec::new();
+    for i in 0..users {
+        let mut user_bookings: Vec<BookingSkeleton> = Vec::new();
+        for _ in 0..bookings_per_user {
+            let facility = scenario_facilities[rng.below(facilities as u32) as usize].clone();
+            let start = 10 + rng.below(50);
+            let end = start + 1 + rng.below(10);
+            user_bookings.push(BookingSkeleton::new(start, end, facility));
+        }
+        bookings.push(user_bookings);
+        user_ids.push(i as u32 + 1);
+        user_stati.push(rng.below(4) == 0);
+    }
+
+    (scenario_facilities, bookings, user_ids, user_stati)
+}
+
+/////////////////////// Command line /////////////////////
+
+// The configuration of a run given on the command line: the capacities of the room and
+// the projector, how long the users get to book, groups of users as (count, vip) and the style of the log.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CliConfig {
+    pub room_capacity: u32,
+    pub projector_capacity: u32,
+    pub runtime_secs: u64,
+    pub users: Vec<(u32, bool)>,
+    pub log_style: LogStyle,
+}
+
+impl Default for CliConfig {
+    fn default() -> CliConfig {
+        CliConfig { room_capacity: 2, projector_capacity: 2, runtime_secs: 2, users: Vec::new(), log_style: LogStyle::Emoji }
+    }
+}
+
+// This function parses the arguments of a binary (without the program name), e.g.
+// --room-capacity 1 --projector-capacity 3 --runtime-secs 5 --users 2:vip --users 3:nonvip --log-style plain
+// where --users can be repeated and adds the given number of vip or non-vip users, and --log-style
+// is emoji, plain or json.
+// Options that are not given keep their default, anything it doesn't understand is an error.
+pub fn parse_args(args: &[String]) -> Result<CliConfig, String> {
+    let mut config = CliConfig::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("missing value for {}", arg))?;
+        let number = |value: &str| value.parse::<u32>().map_err(|_| format!("invalid value for {}: {}", arg, value));
+        match arg.as_str() {
+            "--room-capacity" => config.room_capacity = number(value)?,
+            "--projector-capacity" => config.projector_capacity = number(value)?,
+            "--runtime-secs" => config.runtime_secs = number(value)? as u64,
+            "--users" => {
+                let (count, status) = value.split_once(':').ok_or_else(|| format!("invalid value for {}: {}", arg, value))?;
+                let vip = match status {
+                    "vip" => true,
+                    "nonvip" => false,
+                    _ => return Err(format!("invalid value for {}: {}", arg, value)),
+                };
+                config.users.push((number(count)?, vip));
+            }
+            "--log-style" => {
+                config.log_style = match value.as_str() {
+                    "emoji" => LogStyle::Emoji,
+                    "plain" => LogStyle::Plain,
+                    "json" => LogStyle::Json,
+                    _ => return Err(format!("invalid value for {}: {}", arg, value)),
+                };
+            }
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+    Ok(config)
+}
+
+// This function builds the scenario of a command line configuration: a room and a projector
+// with the configured capacities, and the users of all groups, numbered from 1, each booking
+// the room and the projector from 1 to 2 like in the example scenarios.
+pub fn cli_scenario(config: &CliConfig) -> Scenario {
+    let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, config.room_capacity)));
+    let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, config.projector_capacity)));
+
+    let mut bookings: Vec<Vec<BookingSkeleton>> = Vec::new();
+    let mut user_ids: Vec<u32> = Vec::new();
+    let mut user_stati: Vec<bool> = Vec::new();
+    for &(count, vip) in &config.users {
+        for _ in 0..count {
+            bookings.push(vec![BookingSkeleton::new(1, 2, rooms_arc.clone()), BookingSkeleton::new(1, 2, projectors_arc.clone())]);
+            user_ids.push(user_ids.len() as u32 + 1);
+            user_stati.push(vip);
+        }
+    }
+
+    (vec![rooms_arc, projectors_arc], bookings, user_ids, user_stati)
+}
+
+/////////////////////// Events /////////////////////
+
+// The reasons a booking can be rejected for.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    TimeInPast { current_time: u32 },
+    BeyondHorizon { horizon_end: u32 },
+    CapacityExceeded,
+    // a vip booking only found bookings of other vips it can't displace
+    EqualPriority,
+    // the capacity is exceeded and so is the waitlist of the facility
+    WaitlistFull,
+    // the user is not permitted to book facilities of this type
+    NotPermitted,
+    // the booking lasts longer than the facility may be booked at once
+    TooLong { max_duration: u32 },
+    // the booking is made less than the lead time of the facility in advance
+    TooSoon { min_lead: u32 },
+    // the compound holds more parts for the same facility at once than the facility has capacity for
+    SelfOverlap,
+    // the booking doesn't end after it starts
+    EmptySpan,
+    // only confirmed bookings can be changed
+    NotConfirmed,
+}
+
+// The result of a single booking request, with the id of the booking if it was confirmed.
+// A vip booking failing on the bookings of other vips is told apart from other rejections,
+// as are bookings put on the waitlist, bookings rejected as the waitlist is full and bookings
+// of facilities the user is not permitted to book, bookings lasting too long for their facility
+// and bookings made too short in advance.
+// Serialized with the variant as status, e.g. {"status":"confirmed","id":3}.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BookingResult {
+    Confirmed { id: u64 },
+    Rejected { reason: RejectReason },
+    RejectedEqualPriority,
+    Waitlisted { id: u64 },
+    WaitlistFull,
+    NotPermitted,
+    RejectedTooLong,
+    RejectedTooSoon,
+}
+
+// The result of a rejected request, with the dedicated variants for the reasons that have one.
+impl From<RejectReason> for BookingResult {
+    fn from(reason: RejectReason) -> BookingResult {
+        match reason {
+            RejectReason::EqualPriority => BookingResult::RejectedEqualPriority,
+            RejectReason::WaitlistFull => BookingResult::WaitlistFull,
+            RejectReason::NotPermitted => BookingResult::NotPermitted,
+            RejectReason::TooLong { .. } => BookingResult::RejectedTooLong,
+            RejectReason::TooSoon { .. } => BookingResult::RejectedTooSoon,
+            reason => BookingResult::Rejected { reason },
+        }
+    }
+}
+
+// The events that happen while booking. Instead of printing them directly,
+// they are handed to an event sink which decides what to do with them.
+// The compound events are only emitted by the compound system.
+// Serialized with the variant as event, e.g. {"event":"confirmed","id":3,"user_id":1,...}.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BookingEvent {
+    Confirmed { id: u64, user_id: u32, vip: bool, fac_type: FacilityType, start: u32, end: u32 },
+    Rejected { id: u64, user_id: u32, vip: bool, fac_type: FacilityType, start: u32, end: u32, reason: RejectReason },
+    CancelledByVip { id: u64, user_id: u32, vip: bool, fac_type: FacilityType, start: u32, end: u32 },
+    // the booking was cancelled with its compound, as another part of it was cancelled
+    CancelledCompoundFailure { id: u64, user_id: u32, vip: bool, fac_type: FacilityType, start: u32, end: u32 },
+    // the hold or tentative booking was cancelled as it wasn't confirmed in time
+    Expired { id: u64, user_id: u32, vip: bool, fac_type: FacilityType, start: u32, end: u32 },
+    Waitlisted { id: u64, user_id: u32, vip: bool, fac_type: FacilityType, start: u32, end: u32 },
+    Notified { id: u64, user_id: u32, vip: bool },
+    CompoundConfirmed { user_id: u32, vip: bool },
+    CompoundRejected { user_id: u32, vip: bool },
+}
+
+impl BookingEvent {
+    // The id of the booking the event is about, none for the events about a compound as a whole.
+    pub fn booking_id(&self) -> Option<u64> {
+        match self {
+            BookingEvent::Confirmed { id, .. }
+            | BookingEvent::Rejected { id, .. }
+            | BookingEvent::CancelledByVip { id, .. }
+            | BookingEvent::CancelledCompoundFailure { id, .. }
+            | BookingEvent::Expired { id, .. }
+            | BookingEvent::Waitlisted { id, .. }
+            | BookingEvent::Notified { id, .. } => Some(*id),
+            BookingEvent::CompoundConfirmed { .. } | BookingEvent::CompoundRejected { .. } => None,
+        }
+    }
+}
+
+// An event sink receives all booking events; it is shared between the user threads.
+pub trait EventSink {
+    fn emit(&self, e: &BookingEvent);
+}
+
+pub type SharedSink = Arc<dyn EventSink + Send + Sync>;
+
+// A hook run for each confirmed booking, e.g. to send the user an email or update a UI.
+pub type OnConfirm = Arc<dyn Fn(&Booking) + Send + Sync>;
+
+// A hook run when a facility is full, e.g. to tell an operator. It must not lock the facility again.
+pub type OnFull = Arc<dyn Fn(&Facility) + Send + Sync>;
+
+// A hook run for each cancelled booking, e.g. to refund the user. It must not lock the booking or its facility again.
+pub type OnCancel = Arc<dyn Fn(&Booking) + Send + Sync>;
+
+// A hook weighting the bookings of a user, e.g. to have vip bookings take up twice the capacity.
+pub type WeightFn = Arc<dyn Fn(&User) -> u32 + Send + Sync>;
+
+// How the events are written: as lines marked with emoji (the default), as lines marked with plain
+// words for terminals and log scrapers that can't take emoji, e.g. OK: and FAIL:, or as one JSON object
+// per event, e.g. {"event":"confirmed","id":3,...}, with the times as the ticks they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LogStyle {
+    #[default]
+    Emoji,
+    Plain,
+    Json,
+}
+
+// The default sink printing the events to stdout.
+pub struct StdoutSink;
+
+impl EventSink for StdoutSink {
+    fn emit(&self, e: &BookingEvent) {
+        println!("{}", format_event(e, LogStyle::Emoji, &|t| t.to_string()));
+    }
+}
+
+// A sink printing the events to stdout like the default sink, but in the given style.
+pub struct StyledStdoutSink {
+    pub style: LogStyle,
+}
+
+impl EventSink for StyledStdoutSink {
+    fn emit(&self, e: &BookingEvent) {
+        println!("{}", format_event(e, self.style, &|t| t.to_string()));
+    }
+}
+
+// A sink printing the events to stdout like the default sink, but with the times
+// formatted by the program time, e.g. from time 08:00:00 to time 09:30:00.
+pub struct TimedStdoutSink {
+    pub program_time: Arc<ProgramTime>,
+}
+
+impl EventSink for TimedStdoutSink {
+    fn emit(&self, e: &BookingEvent) {
+        println!("{}", format_event(e, LogStyle::Emoji, &|t| self.program_time.format_time(t)));
+    }
+}
+
+// This function describes an event in a line, showing the times as formatted by fmt_time.
+fn format_event(e: &BookingEvent, style: LogStyle, fmt_time: &dyn Fn(u32) -> String) -> String {
+    if style == LogStyle::Json {
+        return serde_json::to_string(e).unwrap();
+    }
+    let text = match e {
+        BookingEvent::Confirmed { user_id, vip, fac_type, start, end, .. } => {
+            format!("{} User {} booked {} from time {} to time {}.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::Rejected { user_id, vip, fac_type, start, end, reason: RejectReason::TimeInPast { current_time }, .. } => {
+            format!("{} User {} couldn't book {} from time {} to time {} - time in the past (current time is {}).", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end), fmt_time(*current_time))
+        }
+        BookingEvent::Rejected { user_id, vip, fac_type, start, end, reason: RejectReason::BeyondHorizon { horizon_end }, .. } => {
+            format!("{} User {} couldn't book {} from time {} to time {} - beyond the booking horizon (latest end is {}).", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end), fmt_time(*horizon_end))
+        }
+        BookingEvent::Rejected { user_id, vip, fac_type, start, end, reason: RejectReason::CapacityExceeded, .. } => {
+            format!("{} User {} couldn't book {} from time {} to time {} - capacity exceeded.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::Rejected { user_id, vip, fac_type, start, end, reason: RejectReason::EqualPriority, .. } => {
+            format!("{} User {} couldn't book {} from time {} to time {} - cannot displace equal-or-higher priority.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::Rejected { user_id, vip, fac_type, start, end, reason: RejectReason::WaitlistFull, .. } => {
+            format!("{} User {} couldn't book {} from time {} to time {} - capacity exceeded and waitlist full.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::Rejected { user_id, vip, fac_type, start, end, reason: RejectReason::NotPermitted, .. } => {
+            format!("{} User {} couldn't book {} from time {} to time {} - not permitted to book this facility.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::Rejected { user_id, vip, fac_type, start, end, reason: RejectReason::TooLong { max_duration }, .. } => {
+            format!("{} User {} couldn't book {} from time {} to time {} - longer than the facility may be booked (at most {}).", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end), max_duration)
+        }
+        BookingEvent::Rejected { user_id, vip, fac_type, start, end, reason: RejectReason::TooSoon { min_lead }, .. } => {
+            format!("{} User {} couldn't book {} from time {} to time {} - booked too short in advance (at least {} ahead).", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end), min_lead)
+        }
+        BookingEvent::Rejected { user_id, vip, fac_type, start, end, reason: RejectReason::SelfOverlap, .. } => {
+            format!("{} User {} couldn't book {} from time {} to time {} - overlaps other parts of the compound beyond the capacity.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::Rejected { user_id, vip, fac_type, start, end, reason: RejectReason::EmptySpan, .. } => {
+            format!("{} User {} couldn't book {} from time {} to time {} - the booking has to end after it starts.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::Rejected { user_id, vip, fac_type, start, end, reason: RejectReason::NotConfirmed, .. } => {
+            format!("{} User {} couldn't book {} from time {} to time {} - the booking isn't confirmed.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::Waitlisted { user_id, vip, fac_type, start, end, .. } => {
+            format!("{} User {} is on the waitlist for {} from time {} to time {}.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::CancelledByVip { user_id, vip, fac_type, start, end, .. } => {
+            format!("{} User {}'s booking of facility {} from time {} to time {} was cancelled as of a vip booking.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::CancelledCompoundFailure { user_id, vip, fac_type, start, end, .. } => {
+            format!("{} User {}'s booking of facility {} from time {} to time {} was cancelled as another part of its compound was.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::Expired { user_id, vip, fac_type, start, end, .. } => {
+            format!("{} User {}'s booking of facility {} from time {} to time {} was cancelled as it wasn't confirmed in time.", vip_bool_to_string(*vip), user_id, facility_type_to_string(*fac_type), fmt_time(*start), fmt_time(*end))
+        }
+        BookingEvent::Notified { user_id, vip, .. } => {
+            format!("{} User {} received cancellation message.", vip_bool_to_string(*vip), user_id)
+        }
+        BookingEvent::CompoundConfirmed { user_id, vip } => {
+            format!("{} User {} successfully booked all facilities.", vip_bool_to_string(*vip), user_id)
+        }
+        BookingEvent::CompoundRejected { user_id, vip } => {
+            format!("{} User {} couldn't book all facilities.", vip_bool_to_string(*vip), user_id)
+        }
+    };
+    let line = format!("{}: {}", event_mark(e, style), text);
+    // the lines about a booking start with its id, e.g. [#42], so they can be told apart
+    match e.booking_id() {
+        Some(id) => format!("[#{}] {}", id, line),
+        None => line,
+    }
+}
+
+// This function tells how a line about the event is marked: whether it went through, e.g. ✅ or OK,
+// failed, e.g. ❌ or FAIL, or is waiting, e.g. ⏳ or WAIT.
+fn event_mark(e: &BookingEvent, style: LogStyle) -> &'static str {
+    let plain = style == LogStyle::Plain;
+    match e {
+        BookingEvent::Confirmed { .. } | BookingEvent::CompoundConfirmed { .. } => if plain { "OK" } else { "✅" },
+        BookingEvent::Waitlisted { .. } => if plain { "WAIT" } else { "⏳" },
+        _ => if plain { "FAIL" } else { "❌" },
+    }
+}
+
+/////////////////////// Logging /////////////////////
+
+// The levels of the log messages, from the least to the most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+}
+
+// A logger receives the messages together with their level; it is shared between the user threads.
+pub trait Logger {
+    fn log(&self, level: LogLevel, message: &str);
+}
+
+pub type SharedLogger = Arc<dyn Logger + Send + Sync>;
+
+// A logger printing the messages of at least the minimum level to stdout, e.g. "[WARN] ...".
+pub struct StdoutLogger {
+    pub min_level: LogLevel,
+}
+
+impl Logger for StdoutLogger {
+    fn log(&self, level: LogLevel, message: &str) {
+        if level >= self.min_level {
+            println!("[{}] {}", level.as_str(), message);
+        }
+    }
+}
+
+// A logger collecting the messages, so tests can check what was logged.
+#[derive(Default)]
+pub struct VecLogger {
+    messages: RwLock<Vec<(LogLevel, String)>>,
+}
+
+impl VecLogger {
+    pub fn new() -> VecLogger {
+        VecLogger { messages: RwLock::new(Vec::new()) }
+    }
+
+    pub fn messages(&self) -> Vec<(LogLevel, String)> {
+        self.messages.read().unwrap().clone()
+    }
+}
+
+impl Logger for VecLogger {
+    fn log(&self, level: LogLevel, message: &str) {
+        self.messages.write().unwrap().push((level, message.to_string()));
+    }
+}
+
+// This function tells the level an event is logged at. Bookings that went through are info,
+// bookings lost to capacity or to a vip are warnings and bookings that could never be made,
+// e.g. in the past, are errors.
+pub fn event_level(e: &BookingEvent) -> LogLevel {
+    match e {
+        BookingEvent::Confirmed { .. } | BookingEvent::Waitlisted { .. } | BookingEvent::Notified { .. } | BookingEvent::CompoundConfirmed { .. } => LogLevel::Info,
+        BookingEvent::CancelledByVip { .. } | BookingEvent::CancelledCompoundFailure { .. } | BookingEvent::Expired { .. } | BookingEvent::CompoundRejected { .. } => LogLevel::Warn,
+        BookingEvent::Rejected { reason: RejectReason::CapacityExceeded | RejectReason::EqualPriority | RejectReason::WaitlistFull, .. } => LogLevel::Warn,
+        BookingEvent::Rejected { .. } => LogLevel::Error,
+    }
+}
+
+// A sink handing the events to a logger at their level, described as by the default sink in the given style.
+pub struct LogSink {
+    pub logger: SharedLogger,
+    pub style: LogStyle,
+}
+
+impl EventSink for LogSink {
+    fn emit(&self, e: &BookingEvent) {
+        self.logger.log(event_level(e), &format_event(e, self.style, &|t| t.to_string()));
+    }
+}
+
+// The statistics of a run, counting what happened to the bookings.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub confirmed: u32,
+    pub rejected_capacity: u32,
+    pub rejected_past: u32,
+    pub rejected_horizon: u32,
+    pub rejected_priority: u32,
+    pub rejected_permission: u32,
+    pub rejected_too_long: u32,
+    pub rejected_too_soon: u32,
+    pub cancelled: u32,
+}
+
+// A sink counting the events into the stats before handing them on to the inner sink.
+pub struct StatsSink {
+    pub stats: Arc<RwLock<Stats>>,
+    pub inner: SharedSink,
+}
+
+impl EventSink for StatsSink {
+    fn emit(&self, e: &BookingEvent) {
+        {
+            let mut stats = self.stats.write().unwrap();
+            match e {
+                BookingEvent::Confirmed { .. } => stats.confirmed += 1,
+                BookingEvent::Rejected { reason: RejectReason::CapacityExceeded | RejectReason::WaitlistFull | RejectReason::SelfOverlap, .. } => stats.rejected_capacity += 1,
+                BookingEvent::Rejected { reason: RejectReason::TimeInPast { .. }, .. } => stats.rejected_past += 1,
+                BookingEvent::Rejected { reason: RejectReason::BeyondHorizon { .. }, .. } => stats.rejected_horizon += 1,
+                BookingEvent::Rejected { reason: RejectReason::EqualPriority, .. } => stats.rejected_priority += 1,
+                BookingEvent::Rejected { reason: RejectReason::NotPermitted, .. } => stats.rejected_permission += 1,
+                BookingEvent::Rejected { reason: RejectReason::TooLong { .. }, .. } => stats.rejected_too_long += 1,
+                BookingEvent::Rejected { reason: RejectReason::TooSoon { .. }, .. } => stats.rejected_too_soon += 1,
+                // these only reject changes of existing bookings, which aren't counted
+                BookingEvent::Rejected { reason: RejectReason::EmptySpan | RejectReason::NotConfirmed, .. } => {}
+                BookingEvent::CancelledByVip { .. } | BookingEvent::CancelledCompoundFailure { .. } | BookingEvent::Expired { .. } => stats.cancelled += 1,
+                BookingEvent::Waitlisted { .. } | BookingEvent::Notified { .. } | BookingEvent::CompoundConfirmed { .. } | BookingEvent::CompoundRejected { .. } => {}
+            }
+        }
+        self.inner.emit(e);
+    }
+}
+
+// A sink collecting the events, so tests can check what happened.
+#[derive(Default)]
+pub struct VecSink {
+    events: RwLock<Vec<BookingEvent>>,
+}
+
+impl VecSink {
+    pub fn new() -> VecSink {
+        VecSink { events: RwLock::new(Vec::new()) }
+    }
+
+    pub fn events(&self) -> Vec<BookingEvent> {
+        self.events.read().unwrap().clone()
+    }
+}
+
+impl EventSink for VecSink {
+    fn emit(&self, e: &BookingEvent) {
+        self.events.write().unwrap().push(e.clone());
+    }
+}
+
+/////////////////////// Multi-facility booking /////////////////////
+
+// This function books n of the given facilities for a user at the same time, e.g. any 3 of 5 rooms.
+// Either exactly n facilities are booked and the ids of the bookings returned, or nothing is booked.
+// All facilities are locked up front (ordered by address, so concurrent calls cannot deadlock)
+// and only released after the bookings are done. The facilities are expected to be distinct.
+// A facility is free if a booking of the user for the time span passes its rules (see check_request)
+// and the bookings taking up its capacity, holds and pending ones among them, leave room for it.
+pub fn book_n_of(facilities: &[Arc<RwLock<Facility>>], n: u32, start: u32, end: u32, user: Arc<User>, program_time: SharedClock, sink: &SharedSink) -> Result<Vec<u64>, BookingError> {
+    // check if the booking is in the future
+    let current_time = program_time.get_current_time();
+    if start < current_time {
+        return Err(BookingError::TimeInPast { current_time });
+    }
+
+    // check if the booking ends within the booking horizon
+    let horizon_end = program_time.get_horizon_end();
+    if end > horizon_end {
+        return Err(BookingError::BeyondHorizon { horizon_end });
+    }
+
+    // lock all facilities in a stable order
+    let mut order: Vec<usize> = (0..facilities.len()).collect();
+    order.sort_by_key(|&i| Arc::as_ptr(&facilities[i]) as usize);
+    let mut guards: Vec<(usize, RwLockWriteGuard<Facility>)> = Vec::new();
+    for i in order {
+        guards.push((i, facilities[i].write()?));
+    }
+    guards.sort_by_key(|(i, _)| *i);
+
+    // find the facilities the user may book with spare capacity
+    let free: Vec<usize> = (0..guards.len()).filter(|&g| {
+        let facility = &guards[g].1;
+        check_request(facility, &user, start, end, &program_time).is_ok() && !facility.is_full(occupied_overlaps(facility, start, end), facility.capacity_between(start, end))
+    }).collect();
+    if (free.len() as u32) < n {
+        return Err(BookingError::NotEnoughFacilities { available: free.len() as u32, requested: n });
+    }
+
+    // book the first n free facilities
+    let mut ids = Vec::new();
+    for &g in free.iter().take(n as usize) {
+        let (i, facility) = &mut guards[g];
+        let mut booking = Booking::new(start, end, facilities[*i].clone(), user.clone());
+        booking.status = CONFIRMED;
+        let id = booking.id;
+        ids.push(id);
+        facility.add_booking(Arc::new(RwLock::new(booking)), start, end);
+        facility.audit_log.push(AuditEntry { time: current_time, action: AuditAction::Booked, user_id: user.id, start, end, reason: AuditReason::UserRequest });
+        facility.audit_log.push(AuditEntry { time: current_time, action: AuditAction::Confirmed, user_id: user.id, start, end, reason: AuditReason::UserRequest });
+        sink.emit(&BookingEvent::Confirmed { id, user_id: user.id, vip: user.vip, fac_type: facility.fac_type, start, end });
+        facility.publish();
+    }
+
+    Ok(ids)
+}
+
+// This function books one facility out of each of the given pools for a user at the same time,
+// e.g. a room out of the rooms and a projector out of the projectors, in two phases.
+// In the first phase a hold is placed in each pool, going through the pools and the facilities
+// within them in the order of their ids. The holds count against the capacity, so nobody can
+// take the slots while the other pools are probed. In the second phase all holds are confirmed
+// if every pool could be held, otherwise all of them are released again and nothing is booked.
+// A facility is only locked while a hold on it is placed, confirmed or released.
+pub fn book_across_pools(pools: &[Vec<Arc<RwLock<Facility>>>], start: u32, end: u32, user: Arc<User>, program_time: SharedClock, sink: &SharedSink) -> Result<Vec<u64>, BookingError> {
+    // check if the booking is in the future
+    let current_time = program_time.get_current_time();
+    if start < current_time {
+        return Err(BookingError::TimeInPast { current_time });
+    }
+
+    // check if the booking ends within the booking horizon
+    let horizon_end = program_time.get_horizon_end();
+    if end > horizon_end {
+        return Err(BookingError::BeyondHorizon { horizon_end });
+    }
+
+    // sort the facilities of each pool by id, and the pools by their lowest id
+    let mut sorted_pools: Vec<(usize, Vec<Arc<RwLock<Facility>>>)> = Vec::new();
+    for (p, pool) in pools.iter().enumerate() {
+        let mut sorted = pool.clone();
+        sorted.sort_by_key(|f| f.read().unwrap().id);
+        sorted_pools.push((p, sorted));
+    }
+    sorted_pools.sort_by_key(|(_, sorted)| sorted.first().map(|f| f.read().unwrap().id).unwrap_or(u32::MAX));
+
+    // phase 1: place a hold on the first facility of each pool with spare capacity
+    let mut holds: Vec<(usize, Arc<RwLock<Booking>>)> = Vec::new();
+    let mut exhausted: Option<usize> = None;
+    for (p, sorted) in &sorted_pools {
+        let mut hold: Option<Arc<RwLock<Booking>>> = None;
+        for f in sorted {
+            // a facility poisoned by a panicked thread is passed over
+            let mut facility = match f.write() {
+                Ok(facility) => facility,
+                Err(_) => continue,
+            };
+            if !facility.is_full(occupied_overlaps(&facility, start, end), facility.capacity_between(start, end)) {
+                let mut booking = Booking::new(start, end, f.clone(), user.clone());
+                booking.status = HELD;
+                let booking = Arc::new(RwLock::new(booking));
+                facility.add_booking(booking.clone(), start, end);
+                facility.publish();
+                hold = Some(booking);
+                break;
+            }
+        }
+        match hold {
+            Some(b) => holds.push((*p, b)),
+            None => {
+                exhausted = Some(*p);
+                break;
+            }
+        }
+    }
+
+    // phase 2: release all holds if a pool had nothing left
+    if let Some(pool) = exhausted {
+        for (_, b) in &holds {
+            let facility_arc = b.read().unwrap().facility.clone();
+            let mut facility = facility_arc.write().unwrap();
+            facility.remove_booking(b);
+            facility.publish();
+        }
+        return Err(BookingError::PoolExhausted { pool });
+    }
+
+    // phase 2: otherwise confirm all holds, reported in the order of the pools
+    holds.sort_by_key(|(p, _)| *p);
+    let mut ids = Vec::new();
+    for (_, b) in &holds {
+        let facility = b.read().unwrap().facility.clone();
+        let mut facility = facility.write().unwrap();
+        let mut bmut = b.write().unwrap();
+        bmut.status = CONFIRMED;
+        ids.push(bmut.id);
+        facility.audit_log.push(AuditEntry { time: current_time, action: AuditAction::Booked, user_id: user.id, start, end, reason: AuditReason::UserRequest });
+        facility.audit_log.push(AuditEntry { time: current_time, action: AuditAction::Confirmed, user_id: user.id, start, end, reason: AuditReason::UserRequest });
+        sink.emit(&BookingEvent::Confirmed { id: bmut.id, user_id: user.id, vip: user.vip, fac_type: facility.fac_type, start, end });
+        drop(bmut);
+        facility.publish();
+    }
+
+    Ok(ids)
+}
+
+// This function books any one facility of the pool for the time span of the skeleton, trying the members
+// in the order they were added and booking the first with spare capacity. The facility of the skeleton
+// is not used, any member will do. Members whose rules the booking breaks (see check_request) are passed over,
+// and the holds and pending bookings take up capacity as the confirmed ones do. Only one member is locked at a time.
+// If no member admits the booking at all, it is rejected for the rule the first member broke.
+pub fn book_in_pool(pool: &FacilityPool, skeleton: &BookingSkeleton, user: Arc<User>, program_time: SharedClock, sink: &SharedSink) -> BookingResult {
+    let (start, end) = (skeleton.start, skeleton.end);
+    // no booking is made before a member is found, so the rejections have no booking id
+    let reject = |reason: RejectReason| {
+        sink.emit(&BookingEvent::Rejected { id: 0, user_id: user.id, vip: user.vip, fac_type: pool.fac_type, start, end, reason: reason.clone() });
+        reason.into()
+    };
+
+    // check if the booking is in the future
+    let current_time = program_time.get_current_time();
+    if start < current_time {
+        return reject(RejectReason::TimeInPast { current_time });
+    }
+
+    // check if the booking ends within the booking horizon
+    let horizon_end = program_time.get_horizon_end();
+    if end > horizon_end {
+        return reject(RejectReason::BeyondHorizon { horizon_end });
+    }
+
+    // book the first member the user may book with spare capacity
+    let mut broken: Option<RejectReason> = None;
+    let mut full = false;
+    for f in &pool.members {
+        let mut facility = f.write().unwrap();
+        if let Err(reason) = check_request(&facility, &user, start, end, &program_time) {
+            broken.get_or_insert(reason);
+            continue;
+        }
+        if !facility.is_full_for(occupied_overlaps(&facility, start, end), skeleton.units, facility.capacity_between(start, end)) {
+            let mut booking = Booking::new(start, end, f.clone(), user.clone());
+            booking.tags = skeleton.tags.clone();
+            booking.units = skeleton.units;
+            booking.status = CONFIRMED;
+            let id = booking.id;
+            facility.add_booking(Arc::new(RwLock::new(booking)), start, end);
+            facility.audit_log.push(AuditEntry { time: current_time, action: AuditAction::Booked, user_id: user.id, start, end, reason: AuditReason::UserRequest });
+            facility.audit_log.push(AuditEntry { time: current_time, action: AuditAction::Confirmed, user_id: user.id, start, end, reason: AuditReason::UserRequest });
+            sink.emit(&BookingEvent::Confirmed { id, user_id: user.id, vip: user.vip, fac_type: facility.fac_type, start, end });
+            facility.publish();
+            return BookingResult::Confirmed { id };
+        }
+        full = true;
+    }
+
+    // the booking only breaks rules if no member was merely full
+    match broken {
+        Some(reason) if !full => reject(reason),
+        _ => reject(RejectReason::CapacityExceeded),
+    }
+}
+
+
+/////////////////////// Cancellation by user /////////////////////
+
+// This function cancels all confirmed bookings of a user in the given facilities, e.g. on request of an admin,
+// notifies the user of each of them and returns how many were cancelled. The facilities are write locked
+// one at a time and the user is only notified once the facility is released again.
+pub fn cancel_all_for_user(user_id: u32, facilities: &[Arc<RwLock<Facility>>], program_time: SharedClock) -> u32 {
+    let now = program_time.get_current_time();
+    let mut cancelled: Vec<Arc<RwLock<Booking>>> = Vec::new();
+    for f in facilities {
+        let mut facility = f.write().unwrap();
+        let mut entries: Vec<AuditEntry> = Vec::new();
+        for b in facility.bookings() {
+            let mut bmut = b.write().unwrap();
+            if bmut.user().id == user_id && bmut.status == CONFIRMED {
+                bmut.status = CANCELLED;
+                facility.cancelled(&bmut);
+                entries.push(AuditEntry { time: now, action: AuditAction::Cancelled, user_id, start: bmut.start, end: bmut.end, reason: AuditReason::AdminRequest });
+                cancelled.push(b.clone());
+            }
+        }
+        facility.audit_log.extend(entries);
+        facility.publish();
+    }
+
+    for b in &cancelled {
+        b.read().unwrap().notify_users(b);
+    }
+    cancelled.len() as u32
+}
+
+/////////////////////// Maintenance /////////////////////
+
+// This function closes the facility from start to end, e.g. for repairs, after bookings may have been made:
+// the time span gets a capacity of zero ahead of the rest of the capacity schedule, so nothing can be booked
+// in it anymore, and the confirmed bookings overlapping it are cancelled. Their users are notified once
+// the facility is released again, the number of bookings cancelled is returned.
+pub fn schedule_maintenance(facility: &Arc<RwLock<Facility>>, start: u32, end: u32, program_time: SharedClock) -> u32 {
+    let now = program_time.get_current_time();
+    let mut cancelled: Vec<Arc<RwLock<Booking>>> = Vec::new();
+    {
+        let mut facility = facility.write().unwrap();
+        facility.capacity_schedule.insert(0, (start..end, 0));
+
+        let mut entries: Vec<AuditEntry> = Vec::new();
+        for b in facility.overlapping(start, end) {
+            let mut bmut = b.write().unwrap();
+            if bmut.status == CONFIRMED && bmut.start < end && start < bmut.end {
+                bmut.status = CANCELLED;
+                facility.cancelled(&bmut);
+                entries.push(AuditEntry { time: now, action: AuditAction::Cancelled, user_id: bmut.user().id, start: bmut.start, end: bmut.end, reason: AuditReason::Maintenance });
+                drop(bmut);
+                cancelled.push(b);
+            }
+        }
+        facility.audit_log.extend(entries);
+        facility.publish();
+    }
+
+    for b in &cancelled {
+        b.read().unwrap().notify_users(b);
+    }
+    cancelled.len() as u32
+}
+
+// This function changes the capacity of the facility, e.g. when an operator takes some of it out of service.
+// If the confirmed bookings overlapping at some time exceed the new capacity there, bookings overlapping at
+// that time are cancelled until they fit: those of non-vips before those of vips, among them the victim policy
+// of the facility decides. Their users are notified once the facility is released, the number of bookings
+// cancelled is returned.
+pub fn set_capacity(facility: &Arc<RwLock<Facility>>, capacity: u32, program_time: SharedClock) -> u32 {
+    let now = program_time.get_current_time();
+    let mut cancelled: Vec<Arc<RwLock<Booking>>> = Vec::new();
+    {
+        let mut facility = facility.write().unwrap();
+        facility.capacity = capacity;
+
+        // the overlaps only grow where a booking starts, so it is enough to check the starts
+        let confirmed: Vec<Arc<RwLock<Booking>>> = facility.bookings().iter().filter(|b| b.read().unwrap().status == CONFIRMED).cloned().collect();
+        let mut starts: Vec<u32> = confirmed.iter().map(|b| b.read().unwrap().start).collect();
+        starts.sort();
+        starts.dedup();
+
+        let mut entries: Vec<AuditEntry> = Vec::new();
+        for time in starts {
+            let mut running: Vec<Arc<RwLock<Booking>>> = confirmed.iter().filter(|b| {
+                let b = b.read().unwrap();
+                b.status == CONFIRMED && b.start <= time && time < b.end
+            }).cloned().collect();
+
+            // they fit if each of them could have been booked next to the others, as in the consistency check
+            let capacity = facility.capacity_between(time, time + 1);
+            let mut units: u32 = running.iter().map(|b| b.read().unwrap().units).sum();
+            while units > 0 && facility.is_full(units - 1, capacity) {
+                // the least by vip status and victim policy is cancelled
+                let victim = (0..running.len()).min_by_key(|&i| {
+                    let b = running[i].read().unwrap();
+                    (b.user().vip, facility.victim_policy.rank(&b, now), b.start, b.id)
+                }).unwrap();
+                let b = running.remove(victim);
+                let mut bmut = b.write().unwrap();
+                units -= bmut.units;
+                bmut.status = CANCELLED;
+                facility.cancelled(&bmut);
+                entries.push(AuditEntry { time: now, action: AuditAction::Cancelled, user_id: bmut.user().id, start: bmut.start, end: bmut.end, reason: AuditReason::AdminRequest });
+                drop(bmut);
+                cancelled.push(b);
+            }
+        }
+        facility.audit_log.extend(entries);
+        facility.publish();
+    }
+
+    for b in &cancelled {
+        b.read().unwrap().notify_users(b);
+    }
+    cancelled.len() as u32
+}
+
+/////////////////////// Consistency check /////////////////////
+
+// This function checks the bookings of the facilities, e.g. after a long run with compounds:
+// each confirmed booking has to refer back to the facility it is booked in, the members of a compound
+// have to share the same compound, which contains them, and the confirmed bookings running at the same
+// time mustn't take up more units than the capacity admits (whatever the weight of their users).
+// All violations found are returned, described one by one. Each facility is only read locked while its
+// bookings are collected.
+pub fn verify_consistency(facilities: &[Arc<RwLock<Facility>>]) -> Result<(), Vec<String>> {
+    let mut errors: Vec<String> = Vec::new();
+    for f in facilities {
+        let (facility_id, bookings) = {
+            let facility = f.read().unwrap();
+            (facility.id, facility.bookings().to_vec())
+        };
+
+        // the back references and the compounds
+        for b in &bookings {
+            let (id, status, compound) = {
+                let b = b.read().unwrap();
+                if b.status == CONFIRMED && !Arc::ptr_eq(&b.facility, f) {
+                    let other = b.facility.read().unwrap().id;
+                    errors.push(format!("booking #{} is confirmed in facility {} but refers to facility {}", b.id, facility_id, other));
+                }
+                (b.id, b.status, b.compound.clone())
+            };
+            if status != CONFIRMED {
+                continue;
+            }
+            if let Some(compound) = compound {
+                if !compound.iter().any(|m| Arc::ptr_eq(m, b)) {
+                    errors.push(format!("booking #{} is not a member of its own compound", id));
+                }
+                for m in compound.iter() {
+                    let m = m.read().unwrap();
+                    if !m.compound.as_ref().is_some_and(|c| Arc::ptr_eq(c, &compound)) {
+                        errors.push(format!("booking #{} is in the compound of booking #{} but doesn't share it", m.id, id));
+                    }
+                }
+            }
+        }
+
+        // the capacity, the overlaps only grow where a booking starts
+        let facility = f.read().unwrap();
+        let confirmed: Vec<(u32, u32, u32)> = bookings.iter().map(|b| b.read().unwrap()).filter(|b| b.status == CONFIRMED).map(|b| (b.start, b.end, b.units)).collect();
+        let mut starts: Vec<u32> = confirmed.iter().map(|(start, _, _)| *start).collect();
+        starts.sort();
+        starts.dedup();
+        for time in starts {
+            let running: u32 = confirmed.iter().filter(|(start, end, _)| *start <= time && time < *end).map(|(_, _, units)| units).sum();
+            let capacity = facility.capacity_between(time, time + 1);
+            if running > 0 && facility.is_full(running - 1, capacity) {
+                errors.push(format!("facility {} has {} confirmed units booked at time {}, more than its capacity of {} admits", facility_id, running, time, capacity));
+            }
+        }
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+
+/////////////////////// Reports /////////////////////
+
+// This function returns the fraction of the capacity-time of a facility that is consumed
+// by confirmed bookings in the window from window_start to window_end. The capacity-time follows
+// the capacity schedule, so a window that is closed for a while offers less of it.
+// It only takes a read lock on the facility.
+pub fn utilization(facility: &Arc<RwLock<Facility>>, window_start: u32, window_end: u32) -> f64 {
+    let facility = facility.read().unwrap();
+
+    // split the window where the capacity schedule may change the capacity
+    let mut times = vec![window_start, window_end];
+    for (range, _) in &facility.capacity_schedule {
+        for time in [range.start, range.end] {
+            if window_start < time && time < window_end {
+                times.push(time);
+            }
+        }
+    }
+    times.sort_unstable();
+    times.dedup();
+
+    // sum up the capacity-time of each segment
+    let mut capacity_time: u64 = 0;
+    for segment in times.windows(2) {
+        capacity_time += facility.capacity_between(segment[0], segment[1]) as u64 * (segment[1] - segment[0]) as u64;
+    }
+
+    // an empty window or a facility without capacity cannot be utilized
+    if capacity_time == 0 {
+        return 0.0;
+    }
+
+    // sum up the time each confirmed booking spends inside the window
+    let mut booked: u64 = 0;
+    for b in facility.overlapping(window_start, window_end) {
+        let b = b.read().unwrap();
+        if b.status == CONFIRMED {
+            let start = b.start.max(window_start);
+            let end = b.end.min(window_end);
+            if end > start {
+                booked += (end - start) as u64;
+            }
+        }
+    }
+
+    booked as f64 / capacity_time as f64
+}
+
+// A lightweight copy of a booking, e.g. for an availability calendar.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BookingView {
+    pub id: u64,
+    pub facility_id: u32,
+    pub fac_type: FacilityType,
+    pub user_id: u32,
+    pub start: u32,
+    pub end: u32,
+    pub status: BookingStatus,
+}
+
+// This function returns copies of the bookings of the facility carrying the given tag, cancelled ones included,
+// in the order they were added. It only takes a read lock on the facility.
+pub fn bookings_with_tag(facility: &Arc<RwLock<Facility>>, tag: &str) -> Vec<BookingView> {
+    let facility = facility.read().unwrap();
+    let mut views = Vec::new();
+    for b in facility.bookings() {
+        let b = b.read().unwrap();
+        if b.tags.iter().any(|t| t == tag) {
+            views.push(BookingView { id: b.id, facility_id: facility.id, fac_type: facility.fac_type, user_id: b.user().id, start: b.start, end: b.end, status: b.status });
+        }
+    }
+    views
+}
+
+// This function returns copies of the bookings of the facility overlapping the time span from start to end,
+// in the order they were added, leaving out the cancelled ones unless include_cancelled is set.
+// It only takes a read lock on the facility.
+pub fn bookings_in_range(facility: &Arc<RwLock<Facility>>, start: u32, end: u32, include_cancelled: bool) -> Vec<BookingView> {
+    let facility = facility.read().unwrap();
+    let mut views = Vec::new();
+    for b in facility.overlapping(start, end) {
+        let b = b.read().unwrap();
+        if b.start < end && start < b.end && (include_cancelled || b.status != CANCELLED) {
+            views.push(BookingView { id: b.id, facility_id: facility.id, fac_type: facility.fac_type, user_id: b.user().id, start: b.start, end: b.end, status: b.status });
+        }
+    }
+    views
+}
+
+// This function iterates over copies of the bookings of all the facilities, cancelled ones included,
+// facility by facility in the order they were added. Each facility is only read locked while its list
+// of bookings is copied, each booking while it is copied in turn.
+pub fn all_bookings<'a>(facilities: &'a [Arc<RwLock<Facility>>]) -> impl Iterator<Item = BookingView> + 'a {
+    facilities.iter().flat_map(|f| {
+        let (facility_id, fac_type, bookings) = {
+            let facility = f.read().unwrap();
+            (facility.id, facility.fac_type, facility.bookings().to_vec())
+        };
+        bookings.into_iter().map(move |b| {
+            let b = b.read().unwrap();
+            BookingView { id: b.id, facility_id, fac_type, user_id: b.user().id, start: b.start, end: b.end, status: b.status }
+        })
+    })
+}
+
+// This function returns all bookings of the facility, cancelled ones included, sorted by their start,
+// then their end and id. It only takes a read lock on the facility.
+pub fn sorted_bookings(facility: &Arc<RwLock<Facility>>) -> Vec<Arc<RwLock<Booking>>> {
+    let mut bookings = facility.read().unwrap().bookings().to_vec();
+    bookings.sort_by(|a, b| a.read().unwrap().cmp(&b.read().unwrap()));
+    bookings
+}
+
+// This function returns what the confirmed bookings of a user cost over all the given facilities,
+// each booking costing its duration times the cost per unit of time of its facility times its units.
+// The total saturates instead of wrapping around. It only takes read locks on the facilities.
+pub fn user_total_cost(user_id: u32, facilities: &[Arc<RwLock<Facility>>]) -> u32 {
+    let mut total: u32 = 0;
+    for f in facilities {
+        let facility = f.read().unwrap();
+        for b in facility.bookings() {
+            let b = b.read().unwrap();
+            if b.user().id == user_id && b.status == CONFIRMED {
+                total = total.saturating_add((b.end - b.start).saturating_mul(facility.cost_per_unit_time).saturating_mul(b.units));
+            }
+        }
+    }
+    total
+}
+
+// How the bookings of a user turned out, over all facilities.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UserSummary {
+    pub user_id: u32,
+    pub confirmed: u32,
+    pub cancelled: u32,
+}
+
+// This function tallies the confirmed and the cancelled bookings of each of the given users over
+// all the given facilities, in the order of the user ids. It only takes read locks on the facilities.
+pub fn user_summary(facilities: &[Arc<RwLock<Facility>>], user_ids: &[u32]) -> Vec<UserSummary> {
+    let mut summaries: Vec<UserSummary> = user_ids.iter().map(|&user_id| UserSummary { user_id, ..UserSummary::default() }).collect();
+    for f in facilities {
+        let facility = f.read().unwrap();
+        for b in facility.bookings() {
+            let b = b.read().unwrap();
+            if let Some(summary) = summaries.iter_mut().find(|s| s.user_id == b.user().id) {
+                match b.status {
+                    CONFIRMED => summary.confirmed += 1,
+                    CANCELLED => summary.cancelled += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+    summaries
+}
+
+// This function prints a line per user telling how many of their bookings were confirmed and how many cancelled.
+pub fn print_user_summary(facilities: &[Arc<RwLock<Facility>>], user_ids: &[u32]) {
+    for summary in user_summary(facilities, user_ids) {
+        println!("User {}: {} confirmed, {} cancelled.", summary.user_id, summary.confirmed, summary.cancelled);
+    }
+}
+
+// This function returns all maximal windows between from and to in which the facility has
+// spare capacity, as long as they last at least min_duration.
+// It only takes a read lock on the facility.
+pub fn free_windows(facility: &Arc<RwLock<Facility>>, from: u32, to: u32, min_duration: u32) -> Vec<(u32, u32)> {
+    let facility = facility.read().unwrap();
+
+    // the boundaries of all confirmed bookings inside the range split it into segments,
+    // within each segment the number of overlapping bookings is constant
+    let mut boundaries = vec![from, to];
+    for b in facility.overlapping(from, to) {
+        let b = b.read().unwrap();
+        if b.status == CONFIRMED {
+            boundaries.extend([b.start, b.end].iter().filter(|&&t| t > from && t < to));
+        }
+    }
+    boundaries.sort();
+    boundaries.dedup();
+
+    // merge neighbouring segments with spare capacity into windows
+    let mut windows = Vec::new();
+    let mut window_start: Option<u32> = None;
+    for segment in boundaries.windows(2) {
+        let free = !facility.is_full(confirmed_overlaps(&facility, segment[0], segment[1]), facility.capacity_between(segment[0], segment[1]));
+        match (free, window_start) {
+            (true, None) => window_start = Some(segment[0]),
+            (false, Some(start)) => {
+                windows.push((start, segment[0]));
+                window_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = window_start {
+        windows.push((start, to));
+    }
+
+    windows.into_iter().filter(|(start, end)| end - start >= min_duration).collect()
+}
+
+// This function renders the window from from to to as a timeline of cols columns, each standing for an
+// equal share of the window: '#' if the confirmed bookings fill the capacity at some time within it, '+' if
+// some of it is taken and '.' if it is free, e.g. ".#.." for a room booked during the second quarter of the window.
+// Bookings of a column that follow each other don't add up, only those running at the same time do.
+// It only takes a read lock on the facility.
+pub fn render_calendar(facility: &Arc<RwLock<Facility>>, from: u32, to: u32, cols: usize) -> String {
+    let facility = facility.read().unwrap();
+    if to <= from {
+        return String::new();
+    }
+
+    let mut calendar = String::new();
+    for col in 0..cols as u64 {
+        // the columns split the window as evenly as the times allow
+        let start = from + ((to - from) as u64 * col / cols as u64) as u32;
+        let end = from + ((to - from) as u64 * (col + 1) / cols as u64) as u32;
+        let booked = confirmed_overlaps(&facility, start, end);
+        calendar.push(if end <= start || booked == 0 {
+            '.'
+        } else if facility.full_during(start, end) {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    calendar
+}
@@ -0,0 +1,1302 @@
+#[cfg(test)]
+use crate::ProgramTime;
+use crate::Clock;
+use crate::start_program_time;
+use crate::start_program_time_with_tick;
+use crate::BookingSkeleton;
+use crate::SkeletonError;
+use crate::ROOM;
+use crate::PROJECTOR;
+use crate::FacilityTypeBuilder;
+use crate::FacilityTypeError;
+use crate::CapacityMode;
+use crate::facility_type_to_string;
+use crate::facility_type_from_string;
+use crate::Facility;
+use crate::basic::start_users;
+use std::sync::{Arc, Barrier, RwLock};
+use crate::overlap;
+use crate::overlap_in_mode;
+use crate::OverlapMode;
+use crate::basic::start_users_with_sink;
+use crate::VecSink;
+use crate::BookingEvent;
+use crate::RejectReason;
+use crate::RecurringSkeleton;
+use crate::expand_recurring;
+use crate::basic::start_users_with_pool;
+use crate::actor::spawn_facility;
+use crate::BookingResult;
+use crate::reschedule;
+use crate::confirmed_overlaps;
+use crate::swap_bookings;
+use crate::reserve;
+use crate::confirm;
+use crate::cancel_all_for_user;
+use crate::basic::book_facility;
+use crate::basic::book_batch;
+use crate::basic::try_book;
+use crate::WAITLISTED;
+use crate::CONFIRMED;
+use crate::CANCELLED;
+use crate::HELD;
+use crate::UNCONFIRMED;
+use crate::basic::start_users_fifo;
+use crate::basic::start_users_with_hook;
+use crate::OnConfirm;
+use crate::sorted_bookings;
+use crate::FacilityPool;
+use crate::book_in_pool;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::SharedSink;
+use crate::User;
+use crate::Booking;
+use std::sync::mpsc;
+use crate::test_util::assert_capacity_invariant;
+use crate::test_util::booking_id;
+use crate::AuditAction;
+use crate::snapshot_of;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_current_time() {
+        let program_time = ProgramTime::new(0, u32::MAX);
+        assert_eq!(program_time.get_current_time(), 0);
+    }
+
+    #[test]
+    fn test_start_program_time() {
+        let program_time = start_program_time();
+        assert_eq!(program_time.get_current_time(), 0);
+    }
+
+    #[test]
+    fn test_1user_2bookings_1possible_overlap(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        start_users(vec![1], vec![usr1_bookings], program_time.clone());
+
+        // we expect this output because the only one room is available,
+        // and there is overlap between the two bookings
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+    }
+
+    #[test]
+    fn test_1user_2bookings_2possible_no_overlap(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(25, 30, rooms_arc.clone())];
+        start_users(vec![1], vec![usr1_bookings], program_time.clone());
+
+        // we expect this output because the only one room is available,
+        // but there is no overlap between the two bookings
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 2);
+        assert!(!overlap(&rooms_arc.read().unwrap().bookings()[0].read().unwrap(), &rooms_arc.read().unwrap().bookings()[1].read().unwrap()));
+
+    }
+
+    #[test]
+    fn test_1user_2bookings_2possible_different_facilities(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+        let projectors = Facility::new(PROJECTOR, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(25, 30, projectors_arc.clone())];
+        start_users(vec![1], vec![usr1_bookings], program_time.clone());
+
+        // we expect this output because the only one room is available,
+        // and one projector is available, but there is no overlap between the two bookings
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        assert_eq!(projectors_arc.read().unwrap().bookings().len(), 1);
+        assert!(!overlap(&rooms_arc.read().unwrap().bookings()[0].read().unwrap(), &projectors_arc.read().unwrap().bookings()[0].read().unwrap()));
+
+    }
+
+    #[test]
+    fn test_2users_2bookings_2possible_no_overlap(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(25, 30, rooms_arc.clone())];
+        start_users(vec![1, 2], vec![usr1_bookings, usr2_bookings], program_time.clone());
+
+
+
+        // we expect this output because the only one room is available,
+        // but there is no overlap between the two bookings of 2 users
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 2);
+        assert!(!overlap(&rooms_arc.read().unwrap().bookings()[0].read().unwrap(), &rooms_arc.read().unwrap().bookings()[1].read().unwrap()));
+
+        let bookings = rooms_arc.read().unwrap().bookings().to_vec();
+        let user_id_0 = bookings[0].read().unwrap().user().id;
+        let user_id_1 = bookings[1].read().unwrap().user().id;
+        assert!((user_id_0 == 1 && user_id_1 == 2) || (user_id_0 == 2 && user_id_1 == 1));
+        
+    }
+
+    #[test]
+    fn test_2users_2bookings_2possible_different_facilities(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+        let projectors = Facility::new(PROJECTOR, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users(vec![1, 2], vec![usr1_bookings, usr2_bookings], program_time.clone());
+
+        // we expect this output because the only one room is available,
+        // and one projector is available, but there is no overlap 
+        //between the two bookings of 2 users
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        assert_eq!(projectors_arc.read().unwrap().bookings().len(), 1);
+        assert!(overlap(&rooms_arc.read().unwrap().bookings()[0].read().unwrap(), &projectors_arc.read().unwrap().bookings()[0].read().unwrap()));   
+    }
+
+    #[test]
+    fn test_2users_2bookings_1possible_overlap(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+
+        start_users(vec![1, 2], vec![usr1_bookings, usr2_bookings], program_time.clone());
+
+        // we expect this output because the only one room is available,
+        // but there is overlap between the two bookings of 2 users
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        let bookings = rooms_arc.read().unwrap().bookings().to_vec();
+        let user_id_0 = bookings[0].read().unwrap().user().id;
+        assert!(user_id_0 == 1 || user_id_0 == 2);
+
+    }
+
+    #[test]
+    fn test_3users_8bookings_6possible(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 2);
+        let projectors = Facility::new(PROJECTOR, 2);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(25, 30, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, projectors_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone()), BookingSkeleton::new(25, 30, projectors_arc.clone()) ];
+        let usr3_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users(vec![1, 2, 3], vec![usr1_bookings, usr2_bookings, usr3_bookings], program_time.clone());
+
+        // we expect this output because 2 rooms and 2 projectors are available,
+        // and there is overlap on some bookings so in total 6 bookings are possible
+        // 3 bookings for rooms and 3 bookings for projectors
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 3); 
+        assert_eq!(projectors_arc.read().unwrap().bookings().len(), 3);
+    }
+
+    #[test]
+    fn test_1user_2bookings_1possible_horizon(){
+        // program time that does not advance and only allows bookings ending 50 units ahead
+        let program_time = Arc::new(ProgramTime::new(0, 50));
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(100, 110, rooms_arc.clone())];
+        start_users(vec![1], vec![usr1_bookings], program_time.clone());
+
+        // we expect this output because the second booking ends beyond the horizon
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        assert_eq!(rooms_arc.read().unwrap().bookings()[0].read().unwrap().start, 10);
+    }
+
+    #[test]
+    fn test_1user_2bookings_events(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+
+        // collect the events instead of printing them
+        let sink = Arc::new(VecSink::new());
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        start_users_with_sink(vec![1], vec![usr1_bookings], program_time.clone(), sink.clone());
+
+        // we expect these events because the second booking overlaps the first one,
+        // the rejected booking is only known by its event
+        let events = sink.events();
+        let confirmed_id = booking_id(&rooms_arc, 10, CONFIRMED);
+        let rejected_id = events[1].booking_id().unwrap();
+        assert_ne!(confirmed_id, rejected_id);
+        assert_eq!(events, vec![
+            BookingEvent::Confirmed { id: confirmed_id, user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20 },
+            BookingEvent::Rejected { id: rejected_id, user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20, reason: RejectReason::CapacityExceeded },
+        ]);
+    }
+
+    #[test]
+    fn test_1user_recurring_3weekly(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // a seminar every 7 time units, 3 times
+        let seminar = RecurringSkeleton { start: 10, end: 12, period: 7, count: 3, facility: rooms_arc.clone() };
+        start_users(vec![1], vec![expand_recurring(&seminar)], program_time.clone());
+
+        // we expect all three occurrences because the room is free
+        let times: Vec<(u32, u32)> = rooms_arc.read().unwrap().bookings().iter().map(|b| (b.read().unwrap().start, b.read().unwrap().end)).collect();
+        assert_eq!(times, vec![(10, 12), (17, 19), (24, 26)]);
+    }
+
+    #[test]
+    fn test_100users_pool_matches_single_worker(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // every user competes for one of 5 rooms at the same time and books a slot of its own later on
+        let run = |pool_size: usize| {
+            let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 5)));
+            let bookings: Vec<Vec<BookingSkeleton>> = (0..100).map(|i| vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(100 + i, 101 + i, rooms_arc.clone())]).collect();
+            let stats = start_users_with_pool((1..=100).collect(), bookings, program_time.clone(), Arc::new(VecSink::new()), pool_size);
+            let stats = *stats.read().unwrap();
+            stats
+        };
+
+        // we expect the same tallies from 4 workers as from one
+        let single = run(1);
+        let pooled = run(4);
+        assert_eq!(single.confirmed, 105);
+        assert_eq!(single.rejected_capacity, 95);
+        assert_eq!(pooled, single);
+    }
+
+    #[test]
+    fn test_facility_actor_10users_capacity3(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // the room runs in its own thread
+        let rooms = spawn_facility(Facility::new(ROOM, 3), program_time.clone());
+
+        // 10 users ask for the room at the same time
+        let threads: Vec<_> = (1..=10).map(|id| {
+            let rooms = rooms.clone();
+            thread::spawn(move || {
+                let (tx, _) = mpsc::channel();
+                let user = Arc::new(User { id, vip: false, adress: tx, allowed_facility_types: None });
+                let skeleton = BookingSkeleton::new(10, 20, rooms.facility.clone());
+                rooms.book(&skeleton, user).unwrap().recv().unwrap()
+            })
+        }).collect();
+        let results: Vec<BookingResult> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+
+        // we expect this output because the room has a capacity of 3
+        let confirmed: Vec<u64> = results.iter().filter_map(|r| match r { BookingResult::Confirmed { id } => Some(*id), _ => None }).collect();
+        assert_eq!(confirmed.len(), 3);
+        assert_eq!(results.iter().filter(|r| **r == BookingResult::Rejected { reason: RejectReason::CapacityExceeded }).count(), 7);
+
+        // after a cancellation the slot can be booked again
+        assert!(rooms.cancel(confirmed[0]).unwrap().recv().unwrap());
+        assert!(!rooms.cancel(confirmed[0]).unwrap().recv().unwrap());
+        let (tx, _) = mpsc::channel();
+        let user = Arc::new(User { id: 11, vip: false, adress: tx, allowed_facility_types: None });
+        let skeleton = BookingSkeleton::new(15, 16, rooms.facility.clone());
+        assert!(matches!(rooms.book(&skeleton, user).unwrap().recv().unwrap(), BookingResult::Confirmed { .. }));
+        assert_capacity_invariant(&rooms.facility);
+    }
+
+    #[test]
+    fn test_interval_overflow_rejected(){
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // we expect no skeleton because the end would wrap around to a tiny time
+        assert!(BookingSkeleton::from_duration(u32::MAX - 1, 10, rooms_arc.clone()).is_none());
+        let last = BookingSkeleton::from_duration(u32::MAX - 10, 10, rooms_arc.clone()).unwrap();
+        assert_eq!((last.start, last.end), (u32::MAX - 10, u32::MAX));
+
+        // the occurrences of a recurring booking that would wrap around are left out
+        let seminar = RecurringSkeleton { start: u32::MAX - 20, end: u32::MAX - 18, period: 10, count: 4, facility: rooms_arc.clone() };
+        let times: Vec<(u32, u32)> = expand_recurring(&seminar).iter().map(|b| (b.start, b.end)).collect();
+        assert_eq!(times, vec![(u32::MAX - 20, u32::MAX - 18), (u32::MAX - 10, u32::MAX - 8)]);
+    }
+
+    #[test]
+    fn test_reschedule_free_and_full_window(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // two bookings of the room at different times
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking1 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user.clone())));
+        let booking2 = Arc::new(RwLock::new(Booking::new(30, 40, rooms_arc.clone(), user)));
+        assert!(book_facility(booking1.clone(), program_time.clone(), &sink));
+        assert!(book_facility(booking2.clone(), program_time.clone(), &sink));
+
+        // we expect the move into the free window to succeed
+        let id = booking1.read().unwrap().id;
+        assert_eq!(reschedule(&booking1, 50, 60, program_time.clone()), BookingResult::Confirmed { id });
+        assert_eq!((booking1.read().unwrap().start, booking1.read().unwrap().end), (50, 60));
+        assert_eq!(rooms_arc.read().unwrap().overlapping(50, 60).len(), 1);
+        assert_eq!(rooms_arc.read().unwrap().overlapping(10, 20).len(), 0);
+
+        // we expect the move into the window of the other booking to leave the booking as it was
+        assert_eq!(reschedule(&booking1, 35, 45, program_time.clone()), BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+        assert_eq!((booking1.read().unwrap().start, booking1.read().unwrap().end), (50, 60));
+        assert_eq!(rooms_arc.read().unwrap().overlapping(50, 60).len(), 1);
+    }
+
+    #[test]
+    fn test_overlap_modes_adjacent(){
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking1 = Booking::new(1, 2, rooms_arc.clone(), user.clone());
+        let booking2 = Booking::new(2, 4, rooms_arc.clone(), user.clone());
+        let booking3 = Booking::new(3, 5, rooms_arc.clone(), user);
+
+        // we expect the touching bookings to overlap only in the closed mode
+        assert!(!overlap(&booking1, &booking2));
+        assert!(!overlap_in_mode(&booking1, &booking2, OverlapMode::HalfOpen));
+        assert!(overlap_in_mode(&booking1, &booking2, OverlapMode::Closed));
+        assert!(overlap_in_mode(&booking2, &booking1, OverlapMode::Closed));
+
+        // and bookings that are apart to overlap in no mode, while real overlaps are found in both
+        assert!(!overlap_in_mode(&booking1, &booking3, OverlapMode::Closed));
+        assert!(overlap_in_mode(&booking2, &booking3, OverlapMode::HalfOpen));
+        assert!(overlap_in_mode(&booking2, &booking3, OverlapMode::Closed));
+        assert_eq!(OverlapMode::default(), OverlapMode::HalfOpen);
+    }
+
+    #[test]
+    fn test_buffer_between_bookings(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create a room that needs 2 units of time between two bookings
+        let mut room = Facility::new(ROOM, 1);
+        room.buffer = 2;
+        let rooms_arc = Arc::new(RwLock::new(room));
+
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking1 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user.clone())));
+        let booking2 = Arc::new(RwLock::new(Booking::new(21, 25, rooms_arc.clone(), user.clone())));
+        let booking3 = Arc::new(RwLock::new(Booking::new(23, 25, rooms_arc.clone(), user)));
+
+        // we expect the booking within the buffer to be rejected, but the one after it to succeed
+        assert!(book_facility(booking1, program_time.clone(), &sink));
+        assert!(!book_facility(booking2, program_time.clone(), &sink));
+        assert!(book_facility(booking3, program_time.clone(), &sink));
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 2);
+    }
+
+    #[test]
+    fn test_book_batch_outcomes(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(5, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // one booking in the past, one that fits and one that exceeds the capacity
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let skeletons = vec![
+            BookingSkeleton::new(1, 3, rooms_arc.clone()),
+            BookingSkeleton::new(10, 20, rooms_arc.clone()),
+            BookingSkeleton::new(15, 25, rooms_arc.clone()),
+        ];
+        let results = book_batch(user, &skeletons, program_time);
+
+        // we expect the three outcomes in the order of the requests
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], (0, BookingResult::Rejected { reason: RejectReason::TimeInPast { current_time: 5 } }));
+        assert!(matches!(results[1], (1, BookingResult::Confirmed { .. })));
+        assert_eq!(results[2], (2, BookingResult::Rejected { reason: RejectReason::CapacityExceeded }));
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+
+        // and the confirmed booking to be published
+        let published = snapshot_of(rooms_arc.read().unwrap().id);
+        assert_eq!(published.iter().map(|v| (v.start, v.end, v.status)).collect::<Vec<_>>(), vec![(10, 20, CONFIRMED)]);
+    }
+
+    #[test]
+    fn test_capacity_varies_over_time(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create a room that is staffed for 2 users until time 50 and for 1 user afterwards
+        let mut room = Facility::new(ROOM, 2);
+        room.capacity_schedule = vec![(0..50, 2), (50..100, 1)];
+        let rooms_arc = Arc::new(RwLock::new(room));
+        assert_eq!(rooms_arc.read().unwrap().capacity_at(49), 2);
+        assert_eq!(rooms_arc.read().unwrap().capacity_at(50), 1);
+        assert_eq!(rooms_arc.read().unwrap().capacity_between(40, 60), 1);
+
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking1 = Arc::new(RwLock::new(Booking::new(30, 45, rooms_arc.clone(), user.clone())));
+        let booking2 = Arc::new(RwLock::new(Booking::new(40, 60, rooms_arc.clone(), user.clone())));
+        let booking3 = Arc::new(RwLock::new(Booking::new(20, 45, rooms_arc.clone(), user)));
+
+        // we expect the booking reaching into the capacity-1 segment to be rejected,
+        // while a second booking before time 50 still fits
+        assert!(book_facility(booking1, program_time.clone(), &sink));
+        assert!(!book_facility(booking2, program_time.clone(), &sink));
+        assert!(book_facility(booking3, program_time.clone(), &sink));
+    }
+
+    #[test]
+    fn test_program_time_monotonic_for_concurrent_readers(){
+        let program_time = start_program_time();
+
+        // several readers check the time while it ticks
+        let mut readers = Vec::new();
+        for _ in 0..4 {
+            let program_time = program_time.clone();
+            readers.push(thread::spawn(move || {
+                let mut last = program_time.get_current_time();
+                for _ in 0..50 {
+                    thread::sleep(Duration::from_millis(5));
+                    let now = program_time.get_current_time();
+                    // we expect the time to never go backwards
+                    assert!(now >= last);
+                    last = now;
+                }
+                last
+            }));
+        }
+        for r in readers {
+            assert!(r.join().unwrap() <= program_time.get_current_time());
+        }
+    }
+
+    #[test]
+    fn test_2users_fifo_earlier_request_wins(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // repeat the race, the outcome must be the same every run
+        for _ in 0..20 {
+            // create facilities
+            let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+            // both users want the only slot of the room, the request of user 1 comes first
+            let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+            let usr2_bookings = vec![BookingSkeleton::new(15, 25, rooms_arc.clone())];
+            start_users_fifo(vec![1, 2], vec![usr1_bookings, usr2_bookings], program_time.clone());
+
+            // we expect user 1 to always get the room
+            let rooms = rooms_arc.read().unwrap();
+            assert_eq!(rooms.bookings().len(), 1);
+            assert_eq!(rooms.bookings()[0].read().unwrap().user().id, 1);
+        }
+    }
+
+    #[test]
+    fn test_waitlist_full(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create a room with a waitlist of a single place
+        let mut room = Facility::new(ROOM, 1);
+        room.waitlist_enabled = true;
+        room.max_waitlist = Some(1);
+        let rooms_arc = Arc::new(RwLock::new(room));
+
+        // three users want the only slot of the room
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let skeletons = vec![
+            BookingSkeleton::new(10, 20, rooms_arc.clone()),
+            BookingSkeleton::new(10, 20, rooms_arc.clone()),
+            BookingSkeleton::new(10, 20, rooms_arc.clone()),
+        ];
+        let results = book_batch(user, &skeletons, program_time);
+
+        // we expect the first overflow booking to be queued and the second one to be rejected
+        assert!(matches!(results[0], (0, BookingResult::Confirmed { .. })));
+        assert!(matches!(results[1], (1, BookingResult::Waitlisted { .. })));
+        assert_eq!(results[2], (2, BookingResult::WaitlistFull));
+        let rooms = rooms_arc.read().unwrap();
+        assert_eq!(rooms.bookings().len(), 1);
+        assert_eq!(rooms.waitlist.len(), 1);
+        assert_eq!(rooms.waitlist[0].read().unwrap().status, WAITLISTED);
+    }
+
+    #[test]
+    fn test_3users_8bookings_on_confirm_hook(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 2)));
+
+        // count the confirmations seen by the hook
+        let confirmations = Arc::new(AtomicUsize::new(0));
+        let counter = confirmations.clone();
+        let on_confirm: OnConfirm = Arc::new(move |b: &Booking| {
+            assert_eq!(b.status, CONFIRMED);
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(25, 30, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, projectors_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone()), BookingSkeleton::new(25, 30, projectors_arc.clone()) ];
+        let usr3_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        let stats = start_users_with_hook(vec![1, 2, 3], vec![usr1_bookings, usr2_bookings, usr3_bookings], program_time, Arc::new(VecSink::new()), on_confirm);
+
+        // we expect the hook to fire once for each of the 6 confirmed bookings
+        assert_eq!(stats.read().unwrap().confirmed, 6);
+        assert_eq!(confirmations.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn test_sorted_bookings(){
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 3)));
+
+        // add bookings out of order, two of them starting at the same time
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        for (start, end) in [(30, 40), (10, 25), (20, 30), (10, 15)] {
+            let booking = Booking::new(start, end, rooms_arc.clone(), user.clone());
+            rooms_arc.write().unwrap().add_booking(Arc::new(RwLock::new(booking)), start, end);
+        }
+
+        // we expect them by ascending start, the shorter one first on the same start
+        let sorted: Vec<(u32, u32)> = sorted_bookings(&rooms_arc).iter().map(|b| (b.read().unwrap().start, b.read().unwrap().end)).collect();
+        assert_eq!(sorted, vec![(10, 15), (10, 25), (20, 30), (30, 40)]);
+
+        // bookings are equal only to themselves
+        let bookings = rooms_arc.read().unwrap().bookings().to_vec();
+        assert!(*bookings[0].read().unwrap() == *bookings[0].read().unwrap());
+        assert!(*bookings[0].read().unwrap() != *bookings[1].read().unwrap());
+    }
+
+    #[test]
+    fn test_pool_of_2projectors_3requests(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create a pool of two projectors, a room does not belong into it
+        let projector1_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+        let projector2_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+        let mut pool = FacilityPool::new(PROJECTOR);
+        assert!(pool.add(projector1_arc.clone()));
+        assert!(pool.add(projector2_arc.clone()));
+        assert!(!pool.add(Arc::new(RwLock::new(Facility::new(ROOM, 1)))));
+
+        // three users want any projector at overlapping times
+        let skeletons = [(10, 20), (15, 25), (12, 18)].map(|(start, end)| BookingSkeleton::new(start, end, projector1_arc.clone()));
+        let mut results = Vec::new();
+        for (i, skeleton) in skeletons.iter().enumerate() {
+            let (tx, _rx) = mpsc::channel();
+            let user = Arc::new(User { id: i as u32 + 1, vip: false, adress: tx, allowed_facility_types: None });
+            results.push(book_in_pool(&pool, skeleton, user, program_time.clone(), &sink));
+        }
+
+        // we expect one booking on each projector and the third request rejected
+        assert!(matches!(results[0], BookingResult::Confirmed { .. }));
+        assert!(matches!(results[1], BookingResult::Confirmed { .. }));
+        assert_eq!(results[2], BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+        assert_eq!(projector1_arc.read().unwrap().bookings().len(), 1);
+        assert_eq!(projector2_arc.read().unwrap().bookings().len(), 1);
+    }
+
+    #[test]
+    fn test_start_program_time_with_tick(){
+        let program_time = start_program_time_with_tick(Duration::from_millis(10));
+        let start = program_time.get_current_time();
+
+        // we expect the time to move forward, however busy the machine running the test is
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while program_time.get_current_time() <= start && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(program_time.get_current_time() > start);
+    }
+
+    #[test]
+    fn test_confirmed_and_cancelled_count(){
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 5)));
+
+        // add bookings with a mix of statuses
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        for status in [CONFIRMED, CANCELLED, CONFIRMED, UNCONFIRMED, WAITLISTED, CONFIRMED, CANCELLED] {
+            let mut booking = Booking::new(10, 20, rooms_arc.clone(), user.clone());
+            booking.status = status;
+            rooms_arc.write().unwrap().add_booking(Arc::new(RwLock::new(booking)), 10, 20);
+        }
+
+        // we expect only the confirmed and cancelled ones to be counted
+        let rooms = rooms_arc.read().unwrap();
+        assert_eq!(rooms.confirmed_count(), 3);
+        assert_eq!(rooms.cancelled_count(), 2);
+    }
+
+    #[test]
+    fn test_user_not_permitted_for_projector(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // the user may only book rooms
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: Some(ROOM) });
+        let skeletons = vec![
+            BookingSkeleton::new(10, 20, rooms_arc.clone()),
+            BookingSkeleton::new(10, 20, projectors_arc.clone()),
+        ];
+        let results = book_batch(user.clone(), &skeletons, program_time.clone());
+
+        // we expect the room to be booked but not the projector
+        assert!(matches!(results[0], (0, BookingResult::Confirmed { .. })));
+        assert_eq!(results[1], (1, BookingResult::NotPermitted));
+        assert_eq!(projectors_arc.read().unwrap().bookings().len(), 0);
+
+        // the same holds for single bookings, which are reported as rejected
+        let booking = Arc::new(RwLock::new(Booking::new(30, 40, projectors_arc.clone(), user)));
+        assert!(!book_facility(booking, program_time, &sink));
+        assert_eq!(projectors_arc.read().unwrap().bookings().len(), 0);
+    }
+
+    #[test]
+    fn test_format_time(){
+        // one second per tick from midnight
+        let mut program_time = ProgramTime::new(0, u32::MAX);
+        assert_eq!(program_time.format_time(3600), "01:00:00");
+        assert_eq!(program_time.format_time(3661), "01:01:01");
+
+        // a tick of 15 minutes from 8 o'clock
+        program_time.epoch = 8 * 3600;
+        program_time.seconds_per_tick = 900;
+        assert_eq!(program_time.format_time(0), "08:00:00");
+        assert_eq!(program_time.format_time(6), "09:30:00");
+    }
+
+    #[test]
+    fn test_max_duration(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create a room that may be booked for at most 10 units of time at once
+        let mut room = Facility::new(ROOM, 1);
+        room.max_duration = Some(10);
+        let rooms_arc = Arc::new(RwLock::new(room));
+
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let skeletons = vec![
+            BookingSkeleton::new(10, 25, rooms_arc.clone()),
+            BookingSkeleton::new(10, 18, rooms_arc.clone()),
+        ];
+        let results = book_batch(user, &skeletons, program_time);
+
+        // we expect the long booking to be rejected and the short one to be confirmed
+        assert_eq!(results[0], (0, BookingResult::RejectedTooLong));
+        assert!(matches!(results[1], (1, BookingResult::Confirmed { .. })));
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+    }
+
+    // This function builds a room of the capacity with a confirmed booking for each time span.
+    fn room_with_confirmed(capacity: u32, spans: &[(u32, u32)]) -> Arc<RwLock<Facility>> {
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, capacity)));
+        let (tx, _) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        for (start, end) in spans {
+            let mut booking = Booking::new(*start, *end, rooms_arc.clone(), user.clone());
+            booking.status = CONFIRMED;
+            rooms_arc.write().unwrap().add_booking(Arc::new(RwLock::new(booking)), *start, *end);
+        }
+        rooms_arc
+    }
+
+    #[test]
+    fn test_capacity_invariant_holds(){
+        // at most two of the bookings run at once, the adjacent ones don't overlap
+        let rooms_arc = room_with_confirmed(2, &[(10, 20), (15, 25), (20, 30), (25, 35), (40, 50)]);
+        assert_capacity_invariant(&rooms_arc);
+    }
+
+    #[test]
+    #[should_panic(expected = "at time 15: 3 confirmed bookings, capacity 2")]
+    fn test_capacity_invariant_violated(){
+        // three bookings run from time 15 on
+        let rooms_arc = room_with_confirmed(2, &[(10, 20), (15, 25), (12, 18)]);
+        assert_capacity_invariant(&rooms_arc);
+    }
+
+    #[test]
+    fn test_overbooking_allowance(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create a room of capacity 2 that may be overbooked by 50%
+        let mut room = Facility::new(ROOM, 2);
+        room.overbook_pct = 50;
+        let rooms_arc = Arc::new(RwLock::new(room));
+
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let skeletons: Vec<BookingSkeleton> = (0..4).map(|i| BookingSkeleton::new(10 + i, 20, rooms_arc.clone())).collect();
+        let results = book_batch(user, &skeletons, program_time);
+
+        // we expect 3 overlapping bookings to be admitted and the 4th to be rejected
+        assert!(results[..3].iter().all(|(_, r)| matches!(r, BookingResult::Confirmed { .. })));
+        assert_eq!(results[3], (3, BookingResult::Rejected { reason: RejectReason::CapacityExceeded }));
+        assert_eq!(rooms_arc.read().unwrap().confirmed_count(), 3);
+    }
+
+    #[test]
+    fn test_swap_bookings_same_facility(){
+        let rooms_arc = room_with_confirmed(1, &[(10, 20), (30, 45)]);
+        let (a, b) = {
+            let rooms = rooms_arc.read().unwrap();
+            (rooms.bookings()[0].clone(), rooms.bookings()[1].clone())
+        };
+
+        // we expect the time spans to be exchanged, the room is still never overbooked
+        assert!(swap_bookings(&a, &b));
+        assert_eq!((a.read().unwrap().start, a.read().unwrap().end), (30, 45));
+        assert_eq!((b.read().unwrap().start, b.read().unwrap().end), (10, 20));
+        assert_capacity_invariant(&rooms_arc);
+        assert_eq!(rooms_arc.read().unwrap().overlapping(30, 45).len(), 1);
+    }
+
+    #[test]
+    fn test_swap_bookings_over_capacity_rejected(){
+        // both rooms are taken from 30 to 40, the first one from 10 to 20 as well
+        let rooms_a = room_with_confirmed(1, &[(10, 20), (30, 40)]);
+        let rooms_b = room_with_confirmed(1, &[(30, 40)]);
+        let a = rooms_a.read().unwrap().bookings()[0].clone();
+        let b = rooms_b.read().unwrap().bookings()[0].clone();
+
+        // we expect the swap to be rejected, as the first room has no room from 30 to 40
+        assert!(!swap_bookings(&a, &b));
+        assert_eq!((a.read().unwrap().start, a.read().unwrap().end), (10, 20));
+        assert_eq!((b.read().unwrap().start, b.read().unwrap().end), (30, 40));
+        assert_capacity_invariant(&rooms_a);
+        assert_capacity_invariant(&rooms_b);
+    }
+
+    #[test]
+    fn test_facility_type_builder(){
+        let mut builder = FacilityTypeBuilder::new();
+        let mut other = FacilityTypeBuilder::new();
+
+        // the new types come after the built in ones, also those of another builder, and are known by their names
+        let whiteboard = builder.add("Whiteboard").unwrap();
+        let lab = other.add("Lab").unwrap();
+        assert_eq!(whiteboard, PROJECTOR << 1);
+        assert_eq!(lab, PROJECTOR << 2);
+        assert_eq!(facility_type_to_string(whiteboard), "Whiteboard");
+        assert_eq!(facility_type_from_string("Lab"), Some(lab));
+        assert_eq!(facility_type_to_string(ROOM), "Room");
+
+        // we expect the 33rd type to fail, as all 32 bits are taken then
+        for i in 5..=32 {
+            builder.add(&format!("Type {}", i)).unwrap();
+        }
+        assert_eq!(facility_type_to_string(1 << 31), "Type 32");
+        assert_eq!(builder.add("Type 33"), Err(FacilityTypeError::TooManyTypes));
+        assert_eq!(FacilityTypeBuilder::new().add("Type 33"), Err(FacilityTypeError::TooManyTypes));
+    }
+
+    #[test]
+    fn test_try_book_contended_facility(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // another thread holds the room until it is told to let go
+        let (locked_tx, locked_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let rooms = rooms_arc.clone();
+        let holder = thread::spawn(move || {
+            let _guard = rooms.write().unwrap();
+            locked_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+        locked_rx.recv().unwrap();
+
+        // we expect no result while the room is locked, and the booking to be confirmed after
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user)));
+        assert_eq!(try_book(booking.clone(), program_time.clone()), None);
+        assert_eq!(booking.read().unwrap().status, UNCONFIRMED);
+
+        release_tx.send(()).unwrap();
+        holder.join().unwrap();
+        assert!(matches!(try_book(booking.clone(), program_time), Some(BookingResult::Confirmed { .. })));
+        assert_eq!(snapshot_of(rooms_arc.read().unwrap().id).len(), 1);
+        assert_eq!(rooms_arc.read().unwrap().confirmed_count(), 1);
+    }
+
+    #[test]
+    fn test_skeleton_builder(){
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // we expect the built skeleton to equal the hand built one
+        let built = BookingSkeleton::builder().start(10).end(20).facility(rooms_arc.clone()).build().unwrap();
+        let literal = BookingSkeleton::new(10, 20, rooms_arc.clone());
+        assert_eq!(built, literal);
+        assert_eq!(built.units, 1);
+
+        // and the units to be taken over if given
+        let built = BookingSkeleton::builder().start(10).end(20).facility(rooms_arc.clone()).units(3).build().unwrap();
+        assert_eq!(built, BookingSkeleton { start: 10, end: 20, facility: rooms_arc.clone(), tags: Vec::new(), units: 3 });
+        assert_ne!(built, literal);
+
+        // we expect empty spans and missing facilities to be refused
+        assert_eq!(BookingSkeleton::builder().start(20).end(20).facility(rooms_arc.clone()).build(), Err(SkeletonError::EmptySpan { start: 20, end: 20 }));
+        assert_eq!(BookingSkeleton::builder().start(10).end(20).build(), Err(SkeletonError::MissingFacility));
+        assert_eq!(BookingSkeleton::builder().start(10).end(20).facility(rooms_arc).units(0).build(), Err(SkeletonError::NoUnits));
+    }
+
+    #[test]
+    fn test_capacity_mode_strict(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities, strict is the default
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        assert_eq!(rooms_arc.read().unwrap().capacity_mode, CapacityMode::Strict);
+
+        // we expect the capacity-2 room to admit exactly 2 overlapping bookings and reject the 3rd
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        assert!(book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user.clone()))), program_time.clone(), &sink));
+        assert!(book_facility(Arc::new(RwLock::new(Booking::new(15, 25, rooms_arc.clone(), user.clone()))), program_time.clone(), &sink));
+        assert!(!book_facility(Arc::new(RwLock::new(Booking::new(18, 22, rooms_arc.clone(), user))), program_time.clone(), &sink));
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 2);
+    }
+
+    #[test]
+    fn test_capacity_mode_allow_equal(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        rooms_arc.write().unwrap().capacity_mode = CapacityMode::AllowEqual;
+
+        // we expect the room to admit a 3rd booking while the overlaps only equal the capacity, but not a 4th
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        for _ in 0..3 {
+            assert!(book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user.clone()))), program_time.clone(), &sink));
+        }
+        assert!(!book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user))), program_time.clone(), &sink));
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 3);
+    }
+
+    #[test]
+    fn test_same_start_order(){
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking1 = Booking::new(10, 20, rooms_arc.clone(), user.clone());
+        let booking2 = Booking::new(10, 10, rooms_arc.clone(), user);
+
+        // we expect the booking created first to come first, and the overlap not to depend on the order of the arguments
+        assert!(booking1.order(&booking2).is_lt());
+        assert!(booking2.order(&booking1).is_gt());
+        assert_eq!(overlap(&booking1, &booking2), overlap(&booking2, &booking1));
+    }
+
+    #[test]
+    fn test_pause_program_time(){
+        let program_time = start_program_time_with_tick(Duration::from_millis(10));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // freeze the time and book a slot ahead of it
+        program_time.pause();
+        // a tick under way when pausing may still land
+        thread::sleep(Duration::from_millis(20));
+        let paused_at = program_time.get_current_time();
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking = Arc::new(RwLock::new(Booking::new(paused_at + 5, paused_at + 10, rooms_arc.clone(), user)));
+        assert!(book_facility(booking, program_time.clone(), &sink));
+        thread::sleep(Duration::from_millis(200));
+
+        // we expect the time not to have moved while paused
+        assert_eq!(program_time.get_current_time(), paused_at);
+
+        // and to advance again once resumed
+        program_time.resume();
+        thread::sleep(Duration::from_millis(200));
+        assert!(program_time.get_current_time() > paused_at);
+    }
+
+    #[test]
+    fn test_allow_equal_book_in_pool(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create a pool of one room admitting one booking more than its capacity
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        rooms_arc.write().unwrap().capacity_mode = CapacityMode::AllowEqual;
+        let mut pool = FacilityPool::new(ROOM);
+        pool.add(rooms_arc.clone());
+
+        // we expect the pool to admit as many bookings as booking the room directly does
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        assert!(book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user.clone()))), program_time.clone(), &sink));
+        let skeleton = BookingSkeleton::new(10, 20, rooms_arc.clone());
+        assert!(matches!(book_in_pool(&pool, &skeleton, user.clone(), program_time.clone(), &sink), BookingResult::Confirmed { .. }));
+        assert_eq!(book_in_pool(&pool, &skeleton, user, program_time, &sink), BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+    }
+
+    #[test]
+    fn test_allow_equal_reschedule(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create a room admitting one booking more than its capacity, with two bookings after each other
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        rooms_arc.write().unwrap().capacity_mode = CapacityMode::AllowEqual;
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let first = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user.clone())));
+        let second = Arc::new(RwLock::new(Booking::new(30, 40, rooms_arc.clone(), user)));
+        assert!(book_facility(first, program_time.clone(), &sink));
+        assert!(book_facility(second.clone(), program_time.clone(), &sink));
+
+        // we expect the second booking to be moved on top of the first one
+        assert!(matches!(reschedule(&second, 10, 20, program_time), BookingResult::Confirmed { .. }));
+        assert_capacity_invariant(&rooms_arc);
+    }
+
+    #[test]
+    fn test_allow_equal_actor(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // the room runs in its own thread and admits one booking more than its capacity
+        let mut room = Facility::new(ROOM, 1);
+        room.capacity_mode = CapacityMode::AllowEqual;
+        let rooms = spawn_facility(room, program_time);
+
+        // we expect the second overlapping booking to be confirmed and the third to be rejected
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let skeleton = BookingSkeleton::new(10, 20, rooms.facility.clone());
+        for _ in 0..2 {
+            assert!(matches!(rooms.book(&skeleton, user.clone()).unwrap().recv().unwrap(), BookingResult::Confirmed { .. }));
+        }
+        assert_eq!(rooms.book(&skeleton, user).unwrap().recv().unwrap(), BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+    }
+
+    #[test]
+    fn test_reschedule_checks_and_records(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // a confirmed and a cancelled booking of the room
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user.clone())));
+        let cancelled = Arc::new(RwLock::new(Booking::new(30, 40, rooms_arc.clone(), user)));
+        assert!(book_facility(booking.clone(), program_time.clone(), &sink));
+        assert!(book_facility(cancelled.clone(), program_time.clone(), &sink));
+        cancelled.write().unwrap().status = CANCELLED;
+
+        // we expect empty time spans and bookings that aren't confirmed to be left as they were
+        assert_eq!(reschedule(&booking, 50, 50, program_time.clone()), BookingResult::Rejected { reason: RejectReason::EmptySpan });
+        assert_eq!(reschedule(&booking, 60, 50, program_time.clone()), BookingResult::Rejected { reason: RejectReason::EmptySpan });
+        assert_eq!(reschedule(&cancelled, 50, 60, program_time.clone()), BookingResult::Rejected { reason: RejectReason::NotConfirmed });
+        assert_eq!((cancelled.read().unwrap().start, cancelled.read().unwrap().end), (30, 40));
+
+        // and a move to be recorded and published
+        assert!(matches!(reschedule(&booking, 50, 60, program_time), BookingResult::Confirmed { .. }));
+        let rooms = rooms_arc.read().unwrap();
+        let last = rooms.audit_log().last().unwrap();
+        assert_eq!((last.action, last.start, last.end), (AuditAction::Rescheduled, 50, 60));
+        let id = rooms.id;
+        drop(rooms);
+        assert_eq!((snapshot_of(id)[0].start, snapshot_of(id)[0].end), (50, 60));
+    }
+
+    #[test]
+    fn test_facility_actor_checks_and_notifies(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // the room runs in its own thread, it may be booked for at most 10 and only by users allowed rooms
+        let mut room = Facility::new(ROOM, 1);
+        room.max_duration = Some(10);
+        let rooms = spawn_facility(room, program_time);
+        let (tx, rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let projector_user = Arc::new(User { id: 2, vip: false, adress: tx, allowed_facility_types: Some(PROJECTOR) });
+        let skeleton = |start, end| BookingSkeleton::new(start, end, rooms.facility.clone());
+
+        // we expect the same checks as when booking the room directly
+        assert_eq!(rooms.book(&skeleton(10, 30), user.clone()).unwrap().recv().unwrap(), BookingResult::RejectedTooLong);
+        assert_eq!(rooms.book(&skeleton(10, 20), projector_user).unwrap().recv().unwrap(), BookingResult::NotPermitted);
+        let id = match rooms.book(&skeleton(10, 20), user).unwrap().recv().unwrap() {
+            BookingResult::Confirmed { id } => id,
+            result => panic!("booking not confirmed: {:?}", result),
+        };
+
+        // and the user to be notified of the cancellation
+        assert!(rooms.cancel(id).unwrap().recv().unwrap());
+        assert_eq!(rx.try_recv().unwrap().read().unwrap().id, id);
+    }
+
+    #[test]
+    fn test_reschedule_while_cancelling_no_deadlock(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // a confirmed booking of the room
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user)));
+        assert!(book_facility(booking.clone(), program_time.clone(), &sink));
+
+        // one thread moves the booking back and forth while another one cancels the bookings of someone else,
+        // which locks the facility and then its bookings
+        let (done_tx, done_rx) = mpsc::channel();
+        let start = Arc::new(Barrier::new(2));
+        let (facilities, pt, s) = (vec![rooms_arc.clone()], program_time.clone(), start.clone());
+        let canceller = thread::spawn(move || {
+            s.wait();
+            for _ in 0..10000 {
+                cancel_all_for_user(2, &facilities, pt.clone());
+            }
+        });
+        let (b, pt) = (booking.clone(), program_time.clone());
+        thread::spawn(move || {
+            start.wait();
+            for i in 0..10000 {
+                reschedule(&b, 10 + i % 2 * 20, 20 + i % 2 * 20, pt.clone());
+            }
+            canceller.join().unwrap();
+            done_tx.send(()).unwrap();
+        });
+
+        // we expect both to finish
+        assert!(done_rx.recv_timeout(Duration::from_secs(10)).is_ok());
+        assert_eq!(booking.read().unwrap().status, CONFIRMED);
+    }
+
+    #[test]
+    fn test_swap_and_confirm_while_cancelling_no_deadlock(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // two confirmed bookings of the room
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 10)));
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let a = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user.clone())));
+        let b = Arc::new(RwLock::new(Booking::new(30, 40, rooms_arc.clone(), user.clone())));
+        assert!(book_facility(a.clone(), program_time.clone(), &sink));
+        assert!(book_facility(b.clone(), program_time.clone(), &sink));
+
+        // one thread swaps the bookings and confirms tentative ones while another one cancels the bookings
+        // of someone else, which locks the facility and then its bookings
+        let (done_tx, done_rx) = mpsc::channel();
+        let start = Arc::new(Barrier::new(2));
+        let (facilities, pt, s) = (vec![rooms_arc.clone()], program_time.clone(), start.clone());
+        let canceller = thread::spawn(move || {
+            s.wait();
+            for _ in 0..10000 {
+                cancel_all_for_user(2, &facilities, pt.clone());
+            }
+        });
+        let (rooms, pt) = (rooms_arc.clone(), program_time.clone());
+        let (tx, _rx) = mpsc::channel();
+        let other = Arc::new(User { id: 3, vip: false, adress: tx, allowed_facility_types: None });
+        thread::spawn(move || {
+            start.wait();
+            for i in 0..10000 {
+                swap_bookings(&a, &b);
+                if i % 100 == 0 {
+                    let tentative = reserve(&rooms, 50, 60, other.clone(), pt.clone()).unwrap();
+                    confirm(&tentative, pt.clone());
+                    cancel_all_for_user(3, std::slice::from_ref(&rooms), pt.clone());
+                }
+            }
+            canceller.join().unwrap();
+            done_tx.send(()).unwrap();
+        });
+
+        // we expect both to finish
+        assert!(done_rx.recv_timeout(Duration::from_secs(10)).is_ok());
+    }
+
+    #[test]
+    fn test_reschedule_checks_the_rules_of_a_new_booking(){
+        let program_time = Arc::new(ProgramTime::new(20, 100));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // a confirmed booking of a room which can be booked for at most 10, at least 5 in advance
+        let mut room = Facility::new(ROOM, 1);
+        room.max_duration = Some(10);
+        room.min_lead = 5;
+        let rooms_arc = Arc::new(RwLock::new(room));
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking = Arc::new(RwLock::new(Booking::new(40, 50, rooms_arc.clone(), user)));
+        assert!(book_facility(booking.clone(), program_time.clone(), &sink));
+
+        // we expect moves which a new booking couldn't make to leave the booking as it was
+        assert_eq!(reschedule(&booking, 40, 60, program_time.clone()), BookingResult::RejectedTooLong);
+        assert_eq!(reschedule(&booking, 10, 15, program_time.clone()), BookingResult::Rejected { reason: RejectReason::TimeInPast { current_time: 20 } });
+        assert_eq!(reschedule(&booking, 22, 30, program_time.clone()), BookingResult::RejectedTooSoon);
+        assert_eq!(reschedule(&booking, 115, 125, program_time.clone()), BookingResult::Rejected { reason: RejectReason::BeyondHorizon { horizon_end: 120 } });
+        assert_eq!((booking.read().unwrap().start, booking.read().unwrap().end), (40, 50));
+
+        // and a move within the rules to be made
+        assert!(matches!(reschedule(&booking, 25, 35, program_time), BookingResult::Confirmed { .. }));
+    }
+
+    #[test]
+    fn test_book_in_pool_checks_holds_and_rules(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // a pool of three rooms, the first is held and the second only takes bookings of at most 5
+        let rooms: Vec<Arc<RwLock<Facility>>> = (0..3).map(|_| Arc::new(RwLock::new(Facility::new(ROOM, 1)))).collect();
+        rooms[1].write().unwrap().max_duration = Some(5);
+        let mut pool = FacilityPool::new(ROOM);
+        for r in &rooms {
+            pool.add(r.clone());
+        }
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let mut hold = Booking::new(10, 20, rooms[0].clone(), user.clone());
+        hold.status = HELD;
+        rooms[0].write().unwrap().add_booking(Arc::new(RwLock::new(hold)), 10, 20);
+
+        // we expect the booking to go to the third room
+        let skeleton = BookingSkeleton::new(10, 20, rooms[0].clone());
+        assert!(matches!(book_in_pool(&pool, &skeleton, user.clone(), program_time.clone(), &sink), BookingResult::Confirmed { .. }));
+        assert_eq!(rooms[2].read().unwrap().confirmed_count(), 1);
+
+        // the next one to be rejected as the rooms are full, even though one of them doesn't admit it at all
+        assert_eq!(book_in_pool(&pool, &skeleton, user, program_time.clone(), &sink), BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+
+        // and a user who may only book projectors to be told so
+        let projector_user = Arc::new(User { id: 2, vip: false, adress: tx, allowed_facility_types: Some(PROJECTOR) });
+        assert_eq!(book_in_pool(&pool, &skeleton, projector_user, program_time, &sink), BookingResult::NotPermitted);
+    }
+
+    #[test]
+    fn test_units_take_up_capacity(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // a room of capacity 3, of which a booking takes 2 seats
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 3)));
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let skeleton = |start, end, units| BookingSkeleton::builder().start(start).end(end).facility(rooms_arc.clone()).units(units).build().unwrap();
+        let pair = Arc::new(RwLock::new(Booking::from_skeleton(&skeleton(10, 20, 2), user.clone())));
+        assert_eq!(pair.read().unwrap().units, 2);
+        assert!(book_facility(pair.clone(), program_time.clone(), &sink));
+
+        // we expect another 2 seats not to fit next to it, but a single one to
+        assert!(!book_facility(Arc::new(RwLock::new(Booking::from_skeleton(&skeleton(15, 25, 2), user.clone()))), program_time.clone(), &sink));
+        let single = Arc::new(RwLock::new(Booking::from_skeleton(&skeleton(15, 25, 1), user.clone())));
+        assert!(book_facility(single.clone(), program_time.clone(), &sink));
+        assert_eq!(confirmed_overlaps(&rooms_arc.read().unwrap(), 10, 20), 3);
+
+        // and the pair to take its seats along when it is moved
+        let other = Arc::new(RwLock::new(Booking::from_skeleton(&skeleton(30, 40, 2), user)));
+        assert!(book_facility(other, program_time.clone(), &sink));
+        assert_eq!(reschedule(&pair, 30, 40, program_time.clone()), BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+        assert!(matches!(reschedule(&single, 30, 40, program_time), BookingResult::Confirmed { .. }));
+        assert_capacity_invariant(&rooms_arc);
+    }
+
+}
\ No newline at end of file
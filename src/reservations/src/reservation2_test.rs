@@ -0,0 +1,2177 @@
+#[cfg(test)]
+use crate::ProgramTime;
+use crate::Clock;
+use crate::ManualClock;
+use crate::RejectReason;
+use crate::start_program_time;
+use crate::BookingSkeleton;
+use crate::ROOM;
+use crate::PROJECTOR;
+use crate::Facility;
+use crate::vip::start_users;
+use std::sync::{Arc, RwLock};
+use crate::overlap;
+use std::thread;
+use std::time::{Duration};
+use crate::CANCELLED;
+use crate::CONFIRMED;
+use crate::UNCONFIRMED;
+use crate::Booking;
+use crate::User;
+use crate::vip::book_facility;
+use crate::vip::book_facility_with_mode;
+use crate::vip::book_facility_with_policy;
+use crate::vip::DisplacementPolicy;
+use crate::vip::SharedPolicy;
+use crate::export_audit_ndjson;
+use crate::utilization;
+use crate::user_total_cost;
+use crate::bookings_in_range;
+use crate::bookings_with_tag;
+use crate::render_metrics;
+use crate::cancel_all_for_user;
+use crate::schedule_maintenance;
+use crate::set_capacity;
+use crate::AuditReason;
+use crate::http::start_server;
+use crate::parse_args;
+use crate::cli_scenario;
+use crate::CliConfig;
+use crate::Stats;
+use crate::BookingView;
+use crate::all_bookings;
+use crate::compact;
+use crate::SharedClock;
+use crate::start_compactor;
+use std::collections::HashSet;
+use crate::user_summary;
+use crate::UserSummary;
+use std::sync::atomic::{AtomicU32, Ordering};
+use crate::free_windows;
+use crate::render_calendar;
+use crate::book_n_of;
+use crate::BookingError;
+use crate::vip::run_user;
+use crate::VecSink;
+use crate::BookingEvent;
+use crate::EventSink;
+use crate::SharedSink;
+use crate::StdoutSink;
+use crate::LogSink;
+use crate::LogStyle;
+use crate::LogLevel;
+use crate::VecLogger;
+use crate::vip::example_scenario;
+use crate::VictimPolicy;
+use crate::FacilityStore;
+use crate::facilities_of_type;
+use crate::export_state;
+use crate::FacilityState;
+use crate::book_across_pools;
+use crate::HELD;
+use crate::load_scenario;
+use crate::generate_scenario;
+use crate::Fairness;
+use crate::BookingResult;
+use crate::vip::start_users_with_sink;
+use crate::vip::start_users_with_retry;
+use crate::vip::StartError;
+use crate::vip::displaceable;
+use crate::vip::can_book;
+use crate::confirmed_overlaps;
+use crate::overlapping_confirmed;
+use std::sync::mpsc;
+use crate::test_util::assert_capacity_invariant;
+use crate::snapshot_of;
+use crate::CapacityMode;
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_current_time() {
+        let program_time = ProgramTime::new(0, u32::MAX);
+        assert_eq!(program_time.get_current_time(), 0);
+    }
+
+    #[test]
+    fn test_start_program_time() {
+        let program_time = start_program_time();
+        assert_eq!(program_time.get_current_time(), 0);
+    }
+
+    #[test]
+    fn test_1user_2bookings_1possible_overlap(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        start_users(vec![1], vec![true], vec![usr1_bookings], program_time.clone()).unwrap();
+
+        thread::sleep(Duration::from_secs(2));
+        // assert that the bookings were done
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+    }
+
+    #[test]
+    fn test_1user_2bookings_2possible_no_overlap(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(25, 30, rooms_arc.clone())];
+        start_users(vec![1], vec![true], vec![usr1_bookings], program_time.clone()).unwrap();
+
+        thread::sleep(Duration::from_secs(2));
+        
+        // we expect this output because the only one room is available,
+        // but there is no overlap between the two bookings
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 2);
+        assert!(!overlap(&rooms_arc.read().unwrap().bookings()[0].read().unwrap(), &rooms_arc.read().unwrap().bookings()[1].read().unwrap()));
+
+    }
+
+    #[test]
+    fn test_1user_2bookings_2possible_different_facilities(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+        let projectors = Facility::new(PROJECTOR, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(25, 30, projectors_arc.clone())];
+        start_users(vec![1], vec![true], vec![usr1_bookings], program_time.clone()).unwrap();
+
+        thread::sleep(Duration::from_secs(2));
+        
+        // we expect this output because the only one room is available,
+        // and one projector is available, but there is no overlap between the two bookings
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        assert_eq!(projectors_arc.read().unwrap().bookings().len(), 1);
+        assert!(!overlap(&rooms_arc.read().unwrap().bookings()[0].read().unwrap(), &projectors_arc.read().unwrap().bookings()[0].read().unwrap()));
+    }
+
+    #[test]
+    fn test_2users_2bookings_2possible_no_overlap(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(25, 30, rooms_arc.clone())];
+        start_users(vec![1, 2], vec![false,true], vec![usr1_bookings, usr2_bookings], program_time.clone()).unwrap();
+
+        thread::sleep(Duration::from_secs(2));
+        
+
+        // we expect this output because the only one room is available,
+        // but there is no overlap between the two bookings
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 2);
+        assert!(!overlap(&rooms_arc.read().unwrap().bookings()[0].read().unwrap(), &rooms_arc.read().unwrap().bookings()[1].read().unwrap()));
+
+        let bookings = rooms_arc.read().unwrap().bookings().to_vec();
+        let user_id_0 = bookings[0].read().unwrap().user().id;
+        let user_id_1 = bookings[1].read().unwrap().user().id;
+        assert!((user_id_0 == 1 && user_id_1 == 2) || (user_id_0 == 2 && user_id_1 == 1));
+        
+    }
+
+    #[test]
+    fn test_2users_2bookings_2possible_different_facilities(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+        let projectors = Facility::new(PROJECTOR, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users(vec![1, 2], vec![true,false], vec![usr1_bookings, usr2_bookings], program_time.clone()).unwrap();
+
+        thread::sleep(Duration::from_secs(2));
+        
+        
+        // we expect this output because the only one room is available,
+        // and one projector is available, but there is no overlap between the two bookings
+        // and there are 2 different facilities
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        assert_eq!(projectors_arc.read().unwrap().bookings().len(), 1); 
+        
+        assert!(overlap(&rooms_arc.read().unwrap().bookings()[0].read().unwrap(), &projectors_arc.read().unwrap().bookings()[0].read().unwrap()));
+
+        assert_eq!(rooms_arc.read().unwrap().bookings()[0].read().unwrap().user().id, 1);
+        assert_eq!(projectors_arc.read().unwrap().bookings()[0].read().unwrap().user().id, 2); 
+    }
+
+    #[test]
+    fn test_2users_2bookings_1vip_overlap(){
+        // a clock that only advances when ticked, so nothing has to be waited for
+        let clock = Arc::new(ManualClock::new());
+        let sink = Arc::new(VecSink::new());
+        let shared_sink: SharedSink = sink.clone();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+
+        let (tx, _rx) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+
+        // the non-vip books first, then the vip
+        let booking1 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user1)));
+        let booking2 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user2.clone())));
+        book_facility(booking1.clone(), clock.clone(), &shared_sink);
+        book_facility(booking2.clone(), clock.clone(), &shared_sink);
+
+        // we expect this output because the only one room is available,
+        // but there is overlap between the two bookings of 2 users
+        // one of them is vip, so he gets the room and the non-vip booking is cancelled
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 2);
+        assert_eq!(booking1.read().unwrap().status, CANCELLED);
+        assert_eq!(booking2.read().unwrap().status, CONFIRMED);
+
+        // once the clock has passed the start, the slot can't be booked anymore
+        clock.tick(11);
+        let booking3 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user2)));
+        assert_eq!(book_facility(booking3.clone(), clock.clone(), &shared_sink).0, BookingResult::Rejected { reason: RejectReason::TimeInPast { current_time: 11 } });
+        let (id1, id2, id3) = (booking1.read().unwrap().id, booking2.read().unwrap().id, booking3.read().unwrap().id);
+        assert_eq!(sink.events(), vec![
+            BookingEvent::Confirmed { id: id1, user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20 },
+            BookingEvent::CancelledByVip { id: id1, user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20 },
+            BookingEvent::Confirmed { id: id2, user_id: 2, vip: true, fac_type: ROOM, start: 10, end: 20 },
+            BookingEvent::Rejected { id: id3, user_id: 2, vip: true, fac_type: ROOM, start: 10, end: 20, reason: RejectReason::TimeInPast { current_time: 11 } },
+        ]);
+    }
+
+    #[test]
+    fn test_3users_8bookings_6possible(){
+        
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 2);
+        let projectors = Facility::new(PROJECTOR, 2);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(25, 30, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, projectors_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone()), BookingSkeleton::new(25, 30, projectors_arc.clone())];
+        let usr3_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users(vec![1, 2, 3], vec![false, false, true], vec![usr1_bookings, usr2_bookings, usr3_bookings], program_time.clone()).unwrap();
+
+
+        // write me here correct assertion based on previous tests
+        thread::sleep(Duration::from_secs(2));
+
+        // check how many rooms we have
+        let confirmed_rooms = rooms_arc.read().unwrap().confirmed_count();
+        let confirmed_projectors = projectors_arc.read().unwrap().confirmed_count();
+
+        // we expect this output because the projectors & rooms are available,
+        // but only one user is vip, that means that bookings of vip user are confirmed,
+        // and some of non-vip user are confirmed and the other ones are cancelled or unconfirmed
+        assert_eq!(confirmed_rooms, 3);
+        assert_eq!(confirmed_projectors, 3);
+
+    }
+
+    #[test]
+    fn test_1user_2bookings_1possible_horizon(){
+        // program time that does not advance and only allows bookings ending 50 units ahead
+        let program_time = Arc::new(ProgramTime::new(0, 50));
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(100, 110, rooms_arc.clone())];
+        start_users(vec![1], vec![false], vec![usr1_bookings], program_time.clone()).unwrap();
+
+        thread::sleep(Duration::from_secs(2));
+
+        // we expect this output because the second booking ends beyond the horizon
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        assert_eq!(rooms_arc.read().unwrap().bookings()[0].read().unwrap().start, 10);
+        assert!(rooms_arc.read().unwrap().bookings()[0].read().unwrap().status == CONFIRMED);
+    }
+
+    #[test]
+    fn test_export_audit_ndjson_vip_displacement(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // a non-vip user books the room, then a vip user takes it over
+        let (tx1, _rx1) = mpsc::channel();
+        let (tx2, _rx2) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx1, allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: true, adress: tx2, allowed_facility_types: None });
+        let booking1 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user1)));
+        let booking2 = Arc::new(RwLock::new(Booking::new(15, 25, rooms_arc.clone(), user2)));
+        let sink: SharedSink = Arc::new(StdoutSink);
+        assert!(matches!(book_facility(booking1, program_time.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        assert!(matches!(book_facility(booking2, program_time.clone(), &sink).0, BookingResult::Confirmed { .. }));
+
+        // every line has to be a valid json object with the expected fields
+        let ndjson = export_audit_ndjson(rooms_arc.read().unwrap().audit_log());
+        let lines: Vec<serde_json::Value> = ndjson.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 5);
+
+        assert_eq!(lines[0]["action"], "booked");
+        assert_eq!(lines[0]["user_id"], 1);
+        assert_eq!(lines[0]["reason"], "user request");
+        assert_eq!(lines[1]["action"], "confirmed");
+        assert_eq!(lines[1]["user_id"], 1);
+
+        assert_eq!(lines[2]["action"], "cancelled");
+        assert_eq!(lines[2]["user_id"], 1);
+        assert_eq!(lines[2]["start"], 10);
+        assert_eq!(lines[2]["end"], 20);
+        assert_eq!(lines[2]["reason"], "vip booking");
+
+        assert_eq!(lines[3]["action"], "booked");
+        assert_eq!(lines[3]["user_id"], 2);
+        assert_eq!(lines[4]["action"], "confirmed");
+        assert_eq!(lines[4]["user_id"], 2);
+        for line in &lines {
+            assert_eq!(line["timestamp"], "0");
+        }
+    }
+
+    #[test]
+    fn test_utilization_1booking_capacity2(){
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+
+        // put one confirmed booking into the room
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let mut booking = Booking::new(10, 20, rooms_arc.clone(), user);
+        booking.status = CONFIRMED;
+        rooms_arc.write().unwrap().add_booking(Arc::new(RwLock::new(booking)), 10, 20);
+
+        // we expect this output because one of two rooms is booked for half of the window
+        assert_eq!(utilization(&rooms_arc, 0, 20), 0.25);
+    }
+
+    #[test]
+    fn test_book_3_of_5_facilities_4free(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create 5 rooms, the first of which is already booked
+        let rooms: Vec<_> = (0..5).map(|_| Arc::new(RwLock::new(Facility::new(ROOM, 1)))).collect();
+        let (tx, _rx) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let mut booking = Booking::new(10, 20, rooms[0].clone(), user1);
+        booking.status = CONFIRMED;
+        rooms[0].write().unwrap().add_booking(Arc::new(RwLock::new(booking)), 10, 20);
+
+        // a workshop needs any 3 of the rooms
+        let sink: SharedSink = Arc::new(StdoutSink);
+        let user2 = Arc::new(User { id: 2, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let ids = book_n_of(&rooms, 3, 10, 20, user2.clone(), program_time.clone(), &sink).unwrap();
+
+        // we expect this output because 4 rooms are free, but only 3 were requested
+        assert_eq!(ids.len(), 3);
+        let booked: Vec<_> = rooms.iter().filter(|r| r.read().unwrap().bookings().iter().any(|b| b.read().unwrap().user().id == 2 && b.read().unwrap().status == CONFIRMED)).collect();
+        assert_eq!(booked.len(), 3);
+        assert_eq!(rooms[0].read().unwrap().bookings().len(), 1);
+
+        // only one room is left, so asking for 2 more fails without booking anything
+        assert_eq!(book_n_of(&rooms, 2, 10, 20, user2, program_time.clone(), &sink), Err(BookingError::NotEnoughFacilities { available: 1, requested: 2 }));
+        let total: usize = rooms.iter().map(|r| r.read().unwrap().bookings().len()).sum();
+        assert_eq!(total, 4);
+    }
+
+    #[test]
+    fn test_events_vip_displacement(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // collect the events instead of printing them
+        let vec_sink = Arc::new(VecSink::new());
+        let sink: SharedSink = vec_sink.clone();
+
+        // a non-vip user books the room, then a vip user takes it over
+        let (tx1, rx1) = mpsc::channel();
+        let (tx2, _rx2) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx1, allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: true, adress: tx2, allowed_facility_types: None });
+        let booking1 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user1)));
+        let booking2 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user2)));
+        let (id1, id2) = (booking1.read().unwrap().id, booking2.read().unwrap().id);
+        assert_eq!(book_facility(booking1, program_time.clone(), &sink).0, BookingResult::Confirmed { id: id1 });
+        assert_eq!(book_facility(booking2, program_time.clone(), &sink).0, BookingResult::Confirmed { id: id2 });
+
+        // let the displaced user read its inbox, it is shut down already so it stops once the inbox is empty
+        let pt = program_time.clone();
+        let user_sink = sink.clone();
+        thread::spawn(move || run_user(Arc::new(Vec::new()), pt, rx1, Arc::new(RwLock::new(true)), user_sink, None)).join().unwrap();
+
+        // we expect these events because the vip user displaces the non-vip user,
+        // who is then notified of the cancellation
+        assert_eq!(vec_sink.events(), vec![
+            BookingEvent::Confirmed { id: id1, user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20 },
+            BookingEvent::CancelledByVip { id: id1, user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20 },
+            BookingEvent::Confirmed { id: id2, user_id: 2, vip: true, fac_type: ROOM, start: 10, end: 20 },
+            BookingEvent::Notified { id: id1, user_id: 1, vip: false },
+        ]);
+    }
+
+    #[test]
+    fn test_free_windows_2qualifying_1too_small(){
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // put confirmed bookings into the room, leaving gaps of 10, 2 and 20
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        for (start, end) in [(10, 20), (22, 50), (70, 80)] {
+            let mut booking = Booking::new(start, end, rooms_arc.clone(), user.clone());
+            booking.status = CONFIRMED;
+            rooms_arc.write().unwrap().add_booking(Arc::new(RwLock::new(booking)), start, end);
+        }
+
+        // we expect this output because the gap from 20 to 22 is shorter than 5
+        assert_eq!(free_windows(&rooms_arc, 0, 80, 5), vec![(0, 10), (50, 70)]);
+    }
+
+    #[test]
+    fn test_stats_example_scenario(){
+        // program time that does not advance, so no booking ends up in the past
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 2)));
+
+        // run the scenario of main
+        let (user_ids, user_stati, bookings) = example_scenario(&rooms_arc, &projectors_arc);
+        let users = start_users(user_ids, user_stati, bookings, program_time.clone()).unwrap();
+
+        thread::sleep(Duration::from_secs(2));
+
+        // we expect these tallies because every one of the 10 bookings is either confirmed
+        // or rejected for capacity or the other vips, and in the end both rooms and both projectors
+        // are held, so all confirmations beyond these 4 were cancelled again
+        let stats = *users.stats.read().unwrap();
+        assert_eq!(stats.confirmed + stats.rejected_capacity + stats.rejected_priority, 10);
+        assert_eq!(stats.confirmed - stats.cancelled, 4);
+        assert_eq!(stats.rejected_past, 0);
+        assert_eq!(stats.rejected_horizon, 0);
+    }
+
+    #[test]
+    fn test_vip_displaces_least_remaining_time(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(StdoutSink);
+
+        // create facilities
+        let mut rooms = Facility::new(ROOM, 3);
+        rooms.victim_policy = VictimPolicy::LeastRemainingTime;
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+
+        // two non-vip bookings with different remaining times and a vip booking
+        // which would be closest to its end, but cannot be displaced
+        let (tx, _rx) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let user3 = Arc::new(User { id: 3, vip: true, adress: tx.clone(), allowed_facility_types: None });
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(10, 50, rooms_arc.clone(), user1))), program_time.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user2))), program_time.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(10, 16, rooms_arc.clone(), user3.clone()))), program_time.clone(), &sink).0, BookingResult::Confirmed { .. }));
+
+        // another vip booking needs the full room
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(11, 15, rooms_arc.clone(), user3))), program_time.clone(), &sink).0, BookingResult::Confirmed { .. }));
+
+        // we expect this output because the booking of user 2 ends before the one of user 1
+        let bookings = rooms_arc.read().unwrap().bookings().to_vec();
+        assert!(bookings[0].read().unwrap().status == CONFIRMED);
+        assert!(bookings[1].read().unwrap().status == CANCELLED);
+        assert!(bookings[2].read().unwrap().status == CONFIRMED);
+        assert!(bookings[3].read().unwrap().status == CONFIRMED);
+    }
+
+    #[test]
+    fn test_facilities_of_type_mixed_types_id_order(){
+        // create the facilities, the later room is registered first
+        let room1 = Facility::new(ROOM, 1);
+        let projector = Facility::new(PROJECTOR, 1);
+        let room2 = Facility::new(ROOM, 2);
+        let (id1, id2) = (room1.id, room2.id);
+
+        let mut store = FacilityStore::new();
+        store.register(room2);
+        store.register(projector);
+        store.register(room1);
+
+        // we expect only the rooms, in the order of their ids
+        let rooms = facilities_of_type(&store, ROOM);
+        let ids: Vec<u32> = rooms.iter().map(|f| f.read().unwrap().id).collect();
+        assert_eq!(ids, vec![id1, id2]);
+        assert_eq!(facilities_of_type(&store, PROJECTOR).len(), 1);
+    }
+
+    #[test]
+    fn test_export_state_2rooms_booked(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let room1_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let room2_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // book both rooms
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(1, 3, room1_arc.clone(), user.clone()))), program_time.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(5, 8, room2_arc.clone(), user))), program_time.clone(), &sink).0, BookingResult::Confirmed { .. }));
+
+        // we expect both bookings in the export, each referring to its room by id
+        let json = export_state(&[room1_arc.clone(), room2_arc.clone()]);
+        let states: Vec<FacilityState> = serde_json::from_str(&json).unwrap();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0].bookings.len(), 1);
+        assert_eq!(states[1].bookings.len(), 1);
+        assert_eq!((states[0].bookings[0].start, states[0].bookings[0].end), (1, 3));
+        assert_eq!((states[1].bookings[0].start, states[1].bookings[0].end), (5, 8));
+        assert_eq!(states[0].bookings[0].facility_id, room1_arc.read().unwrap().id);
+        assert_eq!(states[1].bookings[0].facility_id, room2_arc.read().unwrap().id);
+        assert_eq!(states[0].bookings[0].status, "confirmed");
+        assert_eq!(states[1].bookings[0].status, "confirmed");
+    }
+
+    #[test]
+    fn test_book_across_pools_1pool_full(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink = Arc::new(VecSink::new());
+        let shared: SharedSink = sink.clone();
+
+        // create the pools, the only projector is already booked
+        let rooms: Vec<_> = (0..2).map(|_| Arc::new(RwLock::new(Facility::new(ROOM, 1)))).collect();
+        let projectors = vec![Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)))];
+        let (tx, _rx) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let mut booking = Booking::new(10, 20, projectors[0].clone(), user1);
+        booking.status = CONFIRMED;
+        projectors[0].write().unwrap().add_booking(Arc::new(RwLock::new(booking)), 10, 20);
+
+        // book a room and a projector
+        let user2 = Arc::new(User { id: 2, vip: false, adress: tx, allowed_facility_types: None });
+        let result = book_across_pools(&[rooms.clone(), projectors.clone()], 10, 20, user2, program_time.clone(), &shared);
+
+        // we expect this output because the projector pool has no capacity left,
+        // so the hold on the room is released again and nothing is confirmed
+        assert_eq!(result, Err(BookingError::PoolExhausted { pool: 1 }));
+        for room in &rooms {
+            assert!(room.read().unwrap().bookings().iter().all(|b| b.read().unwrap().status != HELD));
+            assert_eq!(room.read().unwrap().bookings().len(), 0);
+        }
+        assert_eq!(projectors[0].read().unwrap().bookings().len(), 1);
+        assert!(sink.events().is_empty());
+    }
+
+    #[test]
+    fn test_load_scenario_3confirmed(){
+        // program time that does not advance, so no booking ends up in the past
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // write the scenario, one room and two projectors shared by three users
+        let path = std::env::temp_dir().join(format!("reservations_scenario_{}.json", std::process::id()));
+        let config = r#"{
+            "facilities": [{"fac_type": "Room", "capacity": 1}, {"fac_type": "Projector", "capacity": 2}],
+            "users": [
+                {"id": 1, "vip": false, "bookings": [{"facility": 0, "start": 1, "end": 2}]},
+                {"id": 2, "vip": false, "bookings": [{"facility": 0, "start": 1, "end": 2}, {"facility": 1, "start": 1, "end": 2}]},
+                {"id": 3, "vip": false, "bookings": [{"facility": 1, "start": 1, "end": 2}]}
+            ]
+        }"#;
+        std::fs::write(&path, config).unwrap();
+
+        // load and run it
+        let (facilities, bookings, user_ids, user_stati) = load_scenario(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(facilities.len(), 2);
+        assert_eq!(user_ids, vec![1, 2, 3]);
+        let users = start_users(user_ids, user_stati, bookings, program_time.clone()).unwrap();
+
+        thread::sleep(Duration::from_secs(1));
+
+        // we expect this output because only one of the two room bookings fits
+        assert_eq!(users.stats.read().unwrap().confirmed, 3);
+        assert_eq!(users.stats.read().unwrap().rejected_capacity, 1);
+        assert_eq!(facilities[1].read().unwrap().bookings().len(), 2);
+    }
+
+    #[test]
+    fn test_vip_booking_returns_displaced(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // a non-vip takes the only slot
+        let (tx, _rx) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        let booking1 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user1)));
+        assert_eq!(book_facility(booking1.clone(), program_time.clone(), &sink).1.len(), 0);
+
+        // we expect the vip booking to return exactly the displaced non-vip booking
+        let cancelled = book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user2))), program_time.clone(), &sink).1;
+        assert_eq!(cancelled.len(), 1);
+        assert!(Arc::ptr_eq(&cancelled[0], &booking1));
+        assert_eq!(cancelled[0].read().unwrap().status, CANCELLED);
+    }
+
+    #[test]
+    fn test_format_booking(){
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // a confirmed booking of a vip
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 3, vip: true, adress: tx, allowed_facility_types: None });
+        let mut booking = Booking::new(10, 20, rooms_arc.clone(), user);
+        booking.status = CONFIRMED;
+
+        // we expect the user, the facility and the status in the output
+        let text = booking.to_string();
+        assert_eq!(text, "User 3 VIP booked Room [10,20] CONFIRMED");
+
+        // formatting must not wait for the facility while it is locked
+        let _facility = rooms_arc.write().unwrap();
+        let debug = format!("{:?}", booking);
+        assert!(debug.contains("id: 3"));
+        assert!(debug.contains("confirmed"));
+    }
+
+    #[test]
+    fn test_overlapping_index_matches_naive_scan(){
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // add many non-overlapping bookings of varying length
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let mut start = 0;
+        for i in 0..5000 {
+            let end = start + 1 + i % 7;
+            let booking = Arc::new(RwLock::new(Booking::new(start, end, rooms_arc.clone(), user.clone())));
+            rooms_arc.write().unwrap().add_booking(booking, start, end);
+            start = end + i % 3;
+        }
+
+        // we expect the index to find exactly the bookings the naive scan finds, in the same order
+        let facility = rooms_arc.read().unwrap();
+        for (from, to) in [(0, 1), (100, 250), (7777, 7780), (12000, 12001), (start - 5, start + 5), (0, start)] {
+            let indexed: Vec<u64> = facility.overlapping(from, to).iter().map(|b| b.read().unwrap().id).collect();
+            let naive: Vec<u64> = facility.bookings().iter().filter(|b| { let b = b.read().unwrap(); b.start < to && from < b.end }).map(|b| b.read().unwrap().id).collect();
+            assert_eq!(indexed, naive);
+        }
+    }
+
+    #[test]
+    fn test_many_users_capacity1_never_overbooked(){
+        // program time that does not advance, so all bookings are in time
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // watch the confirmed bookings while the users are booking
+        let done = Arc::new(RwLock::new(false));
+        let watcher = {
+            let rooms_arc = rooms_arc.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                let mut max_confirmed = 0;
+                while !*done.read().unwrap() {
+                    max_confirmed = max_confirmed.max(confirmed_overlaps(&rooms_arc.read().unwrap(), 10, 20));
+                }
+                max_confirmed
+            })
+        };
+
+        // many non-vip users try to book the same slot at once
+        let (tx, _rx) = mpsc::channel();
+        let handles: Vec<_> = (1..=32).map(|id| {
+            let user = Arc::new(User { id, vip: false, adress: tx.clone(), allowed_facility_types: None });
+            let booking = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user)));
+            let program_time = program_time.clone();
+            let sink = sink.clone();
+            thread::spawn(move || matches!(book_facility(booking, program_time, &sink).0, BookingResult::Confirmed { .. }))
+        }).collect();
+        let successes = handles.into_iter().map(|h| h.join().unwrap()).filter(|s| *s).count();
+        *done.write().unwrap() = true;
+
+        // we expect exactly one user to get the room, and never more than one confirmed booking
+        assert_eq!(successes, 1);
+        assert_eq!(confirmed_overlaps(&rooms_arc.read().unwrap(), 10, 20), 1);
+        assert!(watcher.join().unwrap() <= 1);
+    }
+
+    #[test]
+    fn test_generated_scenarios_capacity_respected(){
+        // the same seed gives the same scenario
+        let (_, bookings_a, _, stati_a) = generate_scenario(7, 5, 2, 3);
+        let (_, bookings_b, _, stati_b) = generate_scenario(7, 5, 2, 3);
+        assert_eq!(stati_a, stati_b);
+        for (a, b) in bookings_a.iter().flatten().zip(bookings_b.iter().flatten()) {
+            assert_eq!((a.start, a.end), (b.start, b.end));
+        }
+
+        // run the users of several seeds on a clock that does not advance
+        let clock = Arc::new(ManualClock::new());
+        let mut all_facilities = Vec::new();
+        for seed in 1..=5 {
+            let (facilities, bookings, user_ids, user_stati) = generate_scenario(seed, 12, 3, 4);
+            start_users_with_sink(user_ids, user_stati, bookings, clock.clone(), Arc::new(VecSink::new())).unwrap();
+            all_facilities.extend(facilities);
+        }
+
+        thread::sleep(Duration::from_secs(3));
+
+        // we expect that at no time more confirmed bookings overlap than the capacity allows,
+        // the most overlap is always at the start of one of the bookings
+        for facility in &all_facilities {
+            let facility = facility.read().unwrap();
+            for b in facility.bookings() {
+                let b = b.read().unwrap();
+                if b.status != CONFIRMED {
+                    continue;
+                }
+                let at_start = facility.bookings().iter().filter(|c| c.try_read().map(|c| c.status == CONFIRMED && c.start <= b.start && b.start < c.end).unwrap_or(true)).count();
+                assert!(at_start as u32 <= facility.capacity, "facility {} has {} confirmed bookings at time {}", facility.id, at_start, b.start);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fairness_spreads_vip_cancellations(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities, the room is fair
+        let fairness = Arc::new(Fairness::new());
+        let mut rooms = Facility::new(ROOM, 2);
+        rooms.fairness = Some(fairness.clone());
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+
+        let (tx, _rx) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let vip = Arc::new(User { id: 3, vip: true, adress: tx, allowed_facility_types: None });
+
+        // in each wave both non-vip users book the same slot, then a vip takes one of them
+        for wave in 0..6 {
+            let (start, end) = (10 * wave, 10 * wave + 5);
+            for user in [&user1, &user2] {
+                book_facility(Arc::new(RwLock::new(Booking::new(start, end, rooms_arc.clone(), user.clone()))), clock.clone(), &sink);
+            }
+            book_facility(Arc::new(RwLock::new(Booking::new(start, end, rooms_arc.clone(), vip.clone()))), clock.clone(), &sink);
+        }
+
+        // we expect the cancellations to alternate, not to hit the first user each time
+        assert_eq!(fairness.cancellations(1), 3);
+        assert_eq!(fairness.cancellations(2), 3);
+        assert_eq!(fairness.cancellations(3), 0);
+        assert_capacity_invariant(&rooms_arc);
+    }
+
+    #[test]
+    fn test_vip_cannot_displace_vip(){
+        let clock = Arc::new(ManualClock::new());
+        let sink = Arc::new(VecSink::new());
+        let shared_sink: SharedSink = sink.clone();
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // a vip takes the only slot
+        let (tx, _rx) = mpsc::channel();
+        let vip1 = Arc::new(User { id: 1, vip: true, adress: tx.clone(), allowed_facility_types: None });
+        let vip2 = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        let booking1 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip1)));
+        let id = booking1.read().unwrap().id;
+        assert_eq!(book_facility(booking1.clone(), clock.clone(), &shared_sink).0, BookingResult::Confirmed { id });
+
+        // we expect the second vip to be rejected as of equal priority, not as of capacity
+        let booking2 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip2)));
+        let (result, cancelled) = book_facility(booking2.clone(), clock.clone(), &shared_sink);
+        assert_eq!(result, BookingResult::RejectedEqualPriority);
+        assert!(cancelled.is_empty());
+        assert_eq!(booking1.read().unwrap().status, CONFIRMED);
+        assert_eq!(sink.events()[1], BookingEvent::Rejected { id: booking2.read().unwrap().id, user_id: 2, vip: true, fac_type: ROOM, start: 10, end: 20, reason: RejectReason::EqualPriority });
+    }
+
+    #[test]
+    fn test_join_all_example_scenario(){
+        let clock = Arc::new(ManualClock::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 2)));
+
+        // run the scenario of main and wait for all users to be done
+        let (user_ids, user_stati, bookings) = example_scenario(&rooms_arc, &projectors_arc);
+        let users = start_users_with_sink(user_ids, user_stati, bookings, clock.clone(), Arc::new(VecSink::new())).unwrap();
+        let stats = users.stats.clone();
+        users.join_all();
+
+        // we expect every one of the 10 bookings to be decided once the users are joined
+        let stats = *stats.read().unwrap();
+        assert_eq!(stats.confirmed + stats.rejected_capacity + stats.rejected_priority, 10);
+    }
+
+    #[test]
+    fn test_displace_user_with_closed_channel(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // the non-vip user stops listening before its booking is displaced
+        let (tx1, rx1) = mpsc::channel();
+        let (tx2, _rx2) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx1, allowed_facility_types: None });
+        let vip = Arc::new(User { id: 2, vip: true, adress: tx2, allowed_facility_types: None });
+        let booking1 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user1.clone())));
+        assert!(matches!(book_facility(booking1.clone(), clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        drop(rx1);
+
+        // we expect the vip booking to go through and the closed channel to be reported, not to panic
+        let (result, cancelled) = book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip))), clock.clone(), &sink);
+        assert!(matches!(result, BookingResult::Confirmed { .. }));
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(booking1.read().unwrap().status, CANCELLED);
+        assert_eq!(user1.notify(booking1.clone()), Err(BookingError::ChannelClosed));
+    }
+
+    #[test]
+    fn test_dry_run_vip_over_full_room(){
+        let clock = Arc::new(ManualClock::new());
+        let sink = Arc::new(VecSink::new());
+        let shared_sink: SharedSink = sink.clone();
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // a non-vip user takes the only slot
+        let (tx, _rx) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let vip = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        let booking1 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user1)));
+        assert!(matches!(book_facility(booking1.clone(), clock.clone(), &shared_sink).0, BookingResult::Confirmed { .. }));
+
+        // we expect the dry run to name the booking that would be displaced
+        let booking2 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip)));
+        let (result, cancelled) = book_facility_with_mode(booking2.clone(), clock.clone(), &shared_sink, true);
+        assert!(matches!(result, BookingResult::Confirmed { .. }));
+        assert_eq!(cancelled.len(), 1);
+        assert!(Arc::ptr_eq(&cancelled[0], &booking1));
+
+        // and to leave everything as it was
+        let rooms = rooms_arc.read().unwrap();
+        assert_eq!(rooms.bookings().len(), 1);
+        assert_eq!(rooms.audit_log().len(), 2);
+        assert_eq!(booking1.read().unwrap().status, CONFIRMED);
+        assert_eq!(booking2.read().unwrap().status, UNCONFIRMED);
+        assert_eq!(sink.events().len(), 1);
+    }
+
+    #[test]
+    fn test_user_total_cost(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities, a room costs 3 per unit of time
+        let mut room = Facility::new(ROOM, 2);
+        room.cost_per_unit_time = 3;
+        let rooms_arc = Arc::new(RwLock::new(room));
+
+        // user 1 holds two rooms, another user one room
+        let (tx, _rx) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: false, adress: tx, allowed_facility_types: None });
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user1.clone()))), clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(30, 35, rooms_arc.clone(), user1.clone()))), clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user2))), clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+
+        // a cancelled booking of user 1 is not charged
+        let mut cancelled = Booking::new(40, 50, rooms_arc.clone(), user1);
+        cancelled.status = CANCELLED;
+        rooms_arc.write().unwrap().add_booking(Arc::new(RwLock::new(cancelled)), 40, 50);
+
+        // we expect (10 + 5) * 3 for user 1 and 10 * 3 for user 2
+        let facilities = vec![rooms_arc];
+        assert_eq!(user_total_cost(1, &facilities), 45);
+        assert_eq!(user_total_cost(2, &facilities), 30);
+        assert_eq!(user_total_cost(3, &facilities), 0);
+    }
+
+    #[test]
+    fn test_bookings_in_range(){
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 3)));
+
+        // three bookings at different times, one of them cancelled
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let mut ids = Vec::new();
+        for (start, end, status) in [(10, 16, CONFIRMED), (20, 30, CANCELLED), (25, 40, CONFIRMED)] {
+            let mut booking = Booking::new(start, end, rooms_arc.clone(), user.clone());
+            booking.status = status;
+            ids.push(booking.id);
+            rooms_arc.write().unwrap().add_booking(Arc::new(RwLock::new(booking)), start, end);
+        }
+
+        // we expect the first two bookings to overlap [15,25], the last one starts at its end
+        let room_id = rooms_arc.read().unwrap().id;
+        assert_eq!(bookings_in_range(&rooms_arc, 15, 25, true), vec![
+            BookingView { id: ids[0], facility_id: room_id, fac_type: ROOM, user_id: 1, start: 10, end: 16, status: CONFIRMED },
+            BookingView { id: ids[1], facility_id: room_id, fac_type: ROOM, user_id: 1, start: 20, end: 30, status: CANCELLED },
+        ]);
+        assert_eq!(bookings_in_range(&rooms_arc, 15, 25, false), vec![
+            BookingView { id: ids[0], facility_id: room_id, fac_type: ROOM, user_id: 1, start: 10, end: 16, status: CONFIRMED },
+        ]);
+    }
+
+    #[test]
+    fn test_render_metrics(){
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // seed the room with two confirmed bookings and a cancelled one
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        for (start, end, status) in [(10, 20, CONFIRMED), (10, 20, CANCELLED), (30, 40, CONFIRMED)] {
+            let mut booking = Booking::new(start, end, rooms_arc.clone(), user.clone());
+            booking.status = status;
+            rooms_arc.write().unwrap().add_booking(Arc::new(RwLock::new(booking)), start, end);
+        }
+        let stats = Stats { confirmed: 2, cancelled: 1, ..Stats::default() };
+
+        // we expect the totals and a line per facility
+        let room_id = rooms_arc.read().unwrap().id;
+        let projector_id = projectors_arc.read().unwrap().id;
+        let metrics = render_metrics(&[rooms_arc, projectors_arc], &stats);
+        assert!(metrics.contains("reservations_confirmed_total 2\n"));
+        assert!(metrics.contains("reservations_cancelled_total 1\n"));
+        assert!(metrics.contains(&format!("facility_capacity{{facility_id=\"{}\",facility_type=\"room\"}} 2\n", room_id)));
+        assert!(metrics.contains(&format!("facility_active_bookings{{facility_id=\"{}\",facility_type=\"room\"}} 2\n", room_id)));
+        assert!(metrics.contains(&format!("facility_active_bookings{{facility_id=\"{}\",facility_type=\"projector\"}} 0\n", projector_id)));
+    }
+
+    #[test]
+    fn test_http_book_and_availability(){
+        // create a store with a room of capacity 1 and serve it on a free port
+        let mut store = FacilityStore::new();
+        let room_id = store.register(Facility::new(ROOM, 1)).read().unwrap().id;
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        start_server(listener, Arc::new(store), program_time, sink);
+
+        let request = |req: String| {
+            use std::io::{Read, Write};
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            stream.write_all(req.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let post = |body: String| request(format!("POST /book HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body));
+
+        // the first booking is confirmed, the second one exceeds the capacity
+        let body = format!("{{\"user_id\":1,\"facility_id\":{},\"start\":10,\"end\":20}}", room_id);
+        let response = post(body.clone());
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\"status\":\"confirmed\""));
+        let response = post(body);
+        assert!(response.contains("\"status\":\"rejected\""));
+        assert!(response.contains("\"reason\":\"capacity_exceeded\""));
+
+        // the room is fully booked from 10 to 20 but free afterwards
+        let response = request(format!("GET /availability?facility={}&start=15&end=25 HTTP/1.1\r\n\r\n", room_id));
+        assert!(response.contains("\"available\":false"));
+        let response = request(format!("GET /availability?facility={}&start=20&end=30 HTTP/1.1\r\n\r\n", room_id));
+        assert!(response.contains("\"available\":true"));
+
+        // unknown facilities are not found
+        let response = post("{\"user_id\":1,\"facility_id\":4294967295,\"start\":10,\"end\":20}".to_string());
+        assert!(response.starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_parse_args(){
+        let args: Vec<String> = ["--room-capacity", "1", "--projector-capacity", "3", "--runtime-secs", "5", "--users", "2:vip", "--users", "3:nonvip"].iter().map(|a| a.to_string()).collect();
+
+        // we expect the options and both groups of users in order
+        let config = parse_args(&args).unwrap();
+        assert_eq!(config, CliConfig { room_capacity: 1, projector_capacity: 3, runtime_secs: 5, users: vec![(2, true), (3, false)], log_style: LogStyle::Emoji });
+
+        // the scenario has a user per member of the groups
+        let (facilities, bookings, user_ids, user_stati) = cli_scenario(&config);
+        assert_eq!(facilities[0].read().unwrap().capacity, 1);
+        assert_eq!(facilities[1].read().unwrap().capacity, 3);
+        assert_eq!(bookings.len(), 5);
+        assert_eq!(user_ids, vec![1, 2, 3, 4, 5]);
+        assert_eq!(user_stati, vec![true, true, false, false, false]);
+
+        // missing options keep their defaults, unknown ones and bad values are errors
+        assert_eq!(parse_args(&[]).unwrap(), CliConfig::default());
+        assert!(parse_args(&["--users".to_string(), "2:admin".to_string()]).is_err());
+        assert!(parse_args(&["--rooms".to_string(), "2".to_string()]).is_err());
+        assert!(parse_args(&["--runtime-secs".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_retrying_user_books_freed_slot(){
+        // a clock the test advances itself
+        let clock = Arc::new(ManualClock::new());
+
+        // create a room that is booked already
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let (tx, _rx) = mpsc::channel();
+        let blocker = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), Arc::new(User { id: 9, vip: false, adress: tx, allowed_facility_types: None }))));
+        let sink: SharedSink = Arc::new(VecSink::new());
+        assert!(matches!(book_facility(blocker.clone(), clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+
+        // a user retrying up to 10 times, waiting a tick before each attempt
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        let users = start_users_with_retry(vec![1], vec![false], vec![usr1_bookings], clock.clone(), sink, (10, 1)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        // the booking blocking the room is cancelled, then time passes
+        blocker.write().unwrap().status = CANCELLED;
+        for _ in 0..5 {
+            clock.tick(1);
+            thread::sleep(Duration::from_millis(50));
+        }
+        users.join_all();
+
+        // we expect the retrying user to have got the room
+        let rooms = rooms_arc.read().unwrap();
+        assert_eq!(rooms.confirmed_count(), 1);
+        assert!(rooms.bookings().iter().any(|b| b.read().unwrap().status == CONFIRMED && b.read().unwrap().user().id == 1));
+    }
+
+    #[test]
+    fn test_max_duration_vip(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let vec_sink = Arc::new(VecSink::new());
+        let sink: SharedSink = vec_sink.clone();
+
+        // create a room that may be booked for at most 10 units of time at once
+        let mut room = Facility::new(ROOM, 1);
+        room.max_duration = Some(10);
+        let rooms_arc = Arc::new(RwLock::new(room));
+
+        // not even a vip may book it for longer
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: true, adress: tx, allowed_facility_types: None });
+        let booking = Arc::new(RwLock::new(Booking::new(10, 25, rooms_arc.clone(), user)));
+        assert_eq!(book_facility(booking, program_time, &sink).0, BookingResult::RejectedTooLong);
+        assert!(matches!(vec_sink.events()[0], BookingEvent::Rejected { reason: RejectReason::TooLong { max_duration: 10 }, .. }));
+    }
+
+    #[test]
+    fn test_cancel_all_for_user(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 2)));
+
+        // user 5 books a room and a projector, user 6 books a room
+        let (tx5, rx5) = mpsc::channel();
+        let user5 = Arc::new(User { id: 5, vip: false, adress: tx5, allowed_facility_types: None });
+        let (tx6, rx6) = mpsc::channel();
+        let user6 = Arc::new(User { id: 6, vip: false, adress: tx6, allowed_facility_types: None });
+        for (facility, user) in [(&rooms_arc, &user5), (&projectors_arc, &user5), (&rooms_arc, &user6)] {
+            let booking = Arc::new(RwLock::new(Booking::new(10, 20, facility.clone(), user.clone())));
+            book_facility(booking, program_time.clone(), &sink);
+        }
+
+        // we expect both bookings of user 5 to be cancelled and user 5 to be told about each
+        let facilities = vec![rooms_arc.clone(), projectors_arc.clone()];
+        assert_eq!(cancel_all_for_user(5, &facilities, program_time), 2);
+        assert_eq!(rx5.try_iter().count(), 2);
+        assert_eq!(rooms_arc.read().unwrap().cancelled_count(), 1);
+        assert_eq!(projectors_arc.read().unwrap().cancelled_count(), 1);
+
+        // the booking of user 6 is left alone
+        assert_eq!(rooms_arc.read().unwrap().confirmed_count(), 1);
+        assert_eq!(rx6.try_iter().count(), 0);
+    }
+
+    // A policy letting the users of department A displace those of department B.
+    struct DepartmentPolicy {
+        departments: std::collections::HashMap<u32, char>,
+    }
+
+    impl DisplacementPolicy for DepartmentPolicy {
+        fn can_displace(&self, incoming: &Booking, existing: &Booking) -> bool {
+            self.departments.get(&incoming.user().id) == Some(&'A') && self.departments.get(&existing.user().id) == Some(&'B')
+        }
+    }
+
+    #[test]
+    fn test_department_displacement_policy(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+        let policy: SharedPolicy = Arc::new(DepartmentPolicy { departments: [(1, 'B'), (2, 'A'), (3, 'B')].into_iter().collect() });
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // none of the users is a vip
+        let mut bookings = Vec::new();
+        let mut inboxes = Vec::new();
+        for id in 1..=3 {
+            let (tx, rx) = mpsc::channel();
+            let user = Arc::new(User { id, vip: false, adress: tx, allowed_facility_types: None });
+            bookings.push(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user))));
+            inboxes.push(rx);
+        }
+
+        // we expect department A to displace department B, but not the other way round
+        assert!(matches!(book_facility_with_policy(bookings[0].clone(), program_time.clone(), &sink, policy.clone()).0, BookingResult::Confirmed { .. }));
+        let (result, cancelled) = book_facility_with_policy(bookings[1].clone(), program_time.clone(), &sink, policy.clone());
+        assert!(matches!(result, BookingResult::Confirmed { .. }));
+        assert_eq!(cancelled.len(), 1);
+        assert!(Arc::ptr_eq(&cancelled[0], &bookings[0]));
+        assert_eq!(inboxes[0].try_iter().count(), 1);
+        let (result, _) = book_facility_with_policy(bookings[2].clone(), program_time, &sink, policy);
+        assert_eq!(result, BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+        assert_eq!(bookings[1].read().unwrap().status, CONFIRMED);
+    }
+
+    #[test]
+    fn test_log_levels_vip_displacement(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(5, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // log the events instead of printing them
+        let logger = Arc::new(VecLogger::new());
+        let sink: SharedSink = Arc::new(LogSink { logger: logger.clone(), style: LogStyle::Emoji });
+
+        // a non-vip user books the room, then a vip user takes it over
+        let (tx1, _rx1) = mpsc::channel();
+        let (tx2, _rx2) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx1, allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: true, adress: tx2, allowed_facility_types: None });
+        book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user1))), program_time.clone(), &sink);
+        book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user2.clone()))), program_time.clone(), &sink);
+
+        // we expect the displacement as the only warning, both bookings as info
+        let levels: Vec<LogLevel> = logger.messages().iter().map(|(level, _)| *level).collect();
+        assert_eq!(levels.iter().filter(|l| **l == LogLevel::Warn).count(), 1);
+        assert_eq!(levels.iter().filter(|l| **l == LogLevel::Info).count(), 2);
+        assert!(logger.messages().iter().any(|(level, message)| *level == LogLevel::Warn && message.contains("was cancelled")));
+
+        // a booking in the past is an error
+        book_facility(Arc::new(RwLock::new(Booking::new(1, 3, rooms_arc.clone(), user2))), program_time, &sink);
+        assert_eq!(logger.messages().last().unwrap().0, LogLevel::Error);
+    }
+
+    #[test]
+    fn test_group_booking_displaced_notifies_all(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // a non-vip user books the room for a group of three
+        let (tx1, rx1) = mpsc::channel();
+        let (tx2, rx2) = mpsc::channel();
+        let (tx3, rx3) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx1, allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: false, adress: tx2, allowed_facility_types: None });
+        let user3 = Arc::new(User { id: 3, vip: false, adress: tx3, allowed_facility_types: None });
+        let group = Arc::new(RwLock::new(Booking::for_group(10, 20, rooms_arc.clone(), user1, vec![user2, user3])));
+        assert!(matches!(book_facility(group.clone(), program_time.clone(), &sink).0, BookingResult::Confirmed { .. }));
+
+        // a vip takes the room over
+        let (tx4, _rx4) = mpsc::channel();
+        let vip = Arc::new(User { id: 4, vip: true, adress: tx4, allowed_facility_types: None });
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip))), program_time, &sink).0, BookingResult::Confirmed { .. }));
+
+        // we expect every member of the group to be notified of the cancellation
+        assert_eq!(group.read().unwrap().status, CANCELLED);
+        for rx in [rx1, rx2, rx3] {
+            assert!(Arc::ptr_eq(&rx.try_recv().unwrap(), &group));
+            assert!(rx.try_recv().is_err());
+        }
+    }
+
+    #[test]
+    fn test_render_calendar(){
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 2)));
+
+        // put a confirmed booking from 10 to 20 into each of them
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        for facility in [&rooms_arc, &projectors_arc] {
+            let mut booking = Booking::new(10, 20, facility.clone(), user.clone());
+            booking.status = CONFIRMED;
+            facility.write().unwrap().add_booking(Arc::new(RwLock::new(booking)), 10, 20);
+        }
+
+        // we expect the second quarter of the window to be marked, as full for the room only
+        assert_eq!(render_calendar(&rooms_arc, 0, 40, 4), ".#..");
+        assert_eq!(render_calendar(&projectors_arc, 0, 40, 4), ".+..");
+        assert_eq!(render_calendar(&rooms_arc, 0, 40, 8), "..##....");
+    }
+
+    #[test]
+    fn test_min_lead_time(){
+        // program time that stays at 10
+        let program_time = Arc::new(ProgramTime::new(10, u32::MAX));
+        let vec_sink = Arc::new(VecSink::new());
+        let sink: SharedSink = vec_sink.clone();
+
+        // create a room that has to be booked at least 5 units of time ahead
+        let mut room = Facility::new(ROOM, 1);
+        room.min_lead = 5;
+        let rooms_arc = Arc::new(RwLock::new(room));
+
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+
+        // we expect the booking starting at 12 to be too soon, the one starting at 16 not
+        let booking = Arc::new(RwLock::new(Booking::new(12, 15, rooms_arc.clone(), user.clone())));
+        assert_eq!(book_facility(booking, program_time.clone(), &sink).0, BookingResult::RejectedTooSoon);
+        assert!(matches!(vec_sink.events()[0], BookingEvent::Rejected { reason: RejectReason::TooSoon { min_lead: 5 }, .. }));
+        let booking = Arc::new(RwLock::new(Booking::new(16, 20, rooms_arc.clone(), user)));
+        assert!(matches!(book_facility(booking, program_time, &sink).0, BookingResult::Confirmed { .. }));
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+    }
+
+    #[test]
+    fn test_all_bookings(){
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 3)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 3)));
+
+        // two bookings of the room and three of the projector
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        for (facility, count) in [(&rooms_arc, 2), (&projectors_arc, 3)] {
+            for i in 0..count {
+                let booking = Booking::new(10 * i, 10 * i + 5, facility.clone(), user.clone());
+                facility.write().unwrap().add_booking(Arc::new(RwLock::new(booking)), 10 * i, 10 * i + 5);
+            }
+        }
+
+        // we expect all five bookings, each with the type and id of its facility
+        let facilities = vec![rooms_arc.clone(), projectors_arc.clone()];
+        let views: Vec<BookingView> = all_bookings(&facilities).collect();
+        assert_eq!(views.len(), 5);
+        let room_id = rooms_arc.read().unwrap().id;
+        let projector_id = projectors_arc.read().unwrap().id;
+        assert!(views[..2].iter().all(|v| v.fac_type == ROOM && v.facility_id == room_id));
+        assert!(views[2..].iter().all(|v| v.fac_type == PROJECTOR && v.facility_id == projector_id));
+        assert_eq!(all_bookings(&facilities).filter(|v| v.start >= 20).count(), 1);
+    }
+
+    #[test]
+    fn test_vip_displaces_most_recent_and_oldest(){
+        for (policy, victim) in [(VictimPolicy::MostRecent, 1), (VictimPolicy::Oldest, 0)] {
+            let clock = Arc::new(ManualClock::new());
+            let sink: SharedSink = Arc::new(VecSink::new());
+
+            // create facilities
+            let mut rooms = Facility::new(ROOM, 2);
+            rooms.victim_policy = policy;
+            let rooms_arc = Arc::new(RwLock::new(rooms));
+
+            // two non-vip bookings created at different times, then a vip booking needs the room
+            let (tx, _rx) = mpsc::channel();
+            let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+            let user2 = Arc::new(User { id: 2, vip: false, adress: tx.clone(), allowed_facility_types: None });
+            let vip = Arc::new(User { id: 3, vip: true, adress: tx, allowed_facility_types: None });
+            book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user1))), clock.clone(), &sink);
+            clock.tick(2);
+            book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user2))), clock.clone(), &sink);
+            clock.tick(1);
+            let (_, displaced) = book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip))), clock.clone(), &sink);
+
+            // we expect the later booking to be displaced for MostRecent, the earlier one for Oldest
+            let bookings = rooms_arc.read().unwrap().bookings().to_vec();
+            assert_eq!((bookings[0].read().unwrap().created_at, bookings[1].read().unwrap().created_at), (0, 2));
+            assert_eq!(displaced.len(), 1);
+            assert!(Arc::ptr_eq(&displaced[0], &bookings[victim]));
+            assert_eq!(bookings[1 - victim].read().unwrap().status, CONFIRMED);
+        }
+    }
+
+    #[test]
+    fn test_schedule_maintenance(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+
+        // a user books the room from 10 to 20, then the room is closed from 15 to 25
+        let (tx, rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user.clone())));
+        book_facility(booking.clone(), clock.clone(), &sink);
+        assert_eq!(schedule_maintenance(&rooms_arc, 15, 25, clock.clone()), 1);
+
+        // we expect the booking to be cancelled and its owner notified
+        assert_eq!(booking.read().unwrap().status, CANCELLED);
+        assert!(Arc::ptr_eq(&rx.try_recv().unwrap(), &booking));
+        assert_eq!(rooms_arc.read().unwrap().audit_log().last().unwrap().reason, AuditReason::Maintenance);
+
+        // new bookings in the window are rejected, those after it are not
+        let inside = Arc::new(RwLock::new(Booking::new(18, 22, rooms_arc.clone(), user.clone())));
+        assert_eq!(book_facility(inside, clock.clone(), &sink).0, BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+        let after = Arc::new(RwLock::new(Booking::new(25, 30, rooms_arc.clone(), user)));
+        assert!(matches!(book_facility(after, clock, &sink).0, BookingResult::Confirmed { .. }));
+    }
+
+    #[test]
+    fn test_on_full_fires_once(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities, counting how often the room is reported full
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let fired = Arc::new(AtomicU32::new(0));
+        let counter = fired.clone();
+        rooms_arc.write().unwrap().on_full = Some(Arc::new(move |_: &Facility| { counter.fetch_add(1, Ordering::SeqCst); }));
+
+        // the first booking takes the only slot, the second one is rejected
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let first = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user.clone())));
+        assert!(matches!(book_facility(first, clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        let second = Arc::new(RwLock::new(Booking::new(15, 25, rooms_arc.clone(), user)));
+        assert_eq!(book_facility(second, clock, &sink).0, BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+
+        // we expect the room to be reported full exactly once
+        assert_eq!(fired.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_start_users_mismatched_lengths(){
+        let clock = Arc::new(ManualClock::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // we expect two users with one vip status to be refused instead of panicking
+        let bookings = vec![vec![BookingSkeleton::new(10, 20, rooms_arc.clone())], vec![]];
+        let result = start_users_with_sink(vec![1, 2], vec![true], bookings, clock.clone(), Arc::new(VecSink::new()));
+        assert_eq!(result.err(), Some(StartError::MismatchedLengths { user_ids: 2, user_stati: 1, bookings: 2 }));
+
+        // and likewise one user with the bookings of two
+        let result = start_users_with_sink(vec![1], vec![true], vec![vec![], vec![]], clock, Arc::new(VecSink::new()));
+        assert_eq!(result.err(), Some(StartError::MismatchedLengths { user_ids: 1, user_stati: 1, bookings: 2 }));
+        assert!(rooms_arc.read().unwrap().bookings().is_empty());
+    }
+
+    #[test]
+    fn test_start_users_empty_booking_list(){
+        let clock = Arc::new(ManualClock::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // a user without bookings next to a user booking the room
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        let users = start_users_with_sink(vec![1, 2], vec![false, false], vec![vec![], usr2_bookings], clock, Arc::new(VecSink::new())).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        let stats = users.stats.clone();
+        users.join_all();
+
+        // we expect the user without bookings to have done nothing
+        let rooms = rooms_arc.read().unwrap();
+        assert_eq!(stats.read().unwrap().confirmed, 1);
+        assert!(rooms.bookings().iter().all(|b| b.read().unwrap().user().id == 2));
+    }
+
+    #[test]
+    fn test_user_summary_after_displacement(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // a non-vip books the room, then a vip takes it over
+        let (tx1, _rx1) = mpsc::channel();
+        let (tx2, _rx2) = mpsc::channel();
+        let nonvip = Arc::new(User { id: 1, vip: false, adress: tx1, allowed_facility_types: None });
+        let vip = Arc::new(User { id: 2, vip: true, adress: tx2, allowed_facility_types: None });
+        book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), nonvip))), clock.clone(), &sink);
+        book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip))), clock, &sink);
+
+        // we expect the displaced non-vip to show one cancellation
+        let summaries = user_summary(&[rooms_arc], &[1, 2]);
+        assert_eq!(summaries, vec![UserSummary { user_id: 1, confirmed: 0, cancelled: 1 }, UserSummary { user_id: 2, confirmed: 1, cancelled: 0 }]);
+    }
+
+    #[test]
+    fn test_weighted_capacity(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities, where vip bookings weigh twice as much as the others
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 3)));
+        rooms_arc.write().unwrap().weight = Some(Arc::new(|user: &User| if user.vip { 2 } else { 1 }));
+
+        // two vips book the room at the same time
+        let (tx, _rx) = mpsc::channel();
+        let vip1 = Arc::new(User { id: 1, vip: true, adress: tx.clone(), allowed_facility_types: None });
+        let vip2 = Arc::new(User { id: 2, vip: true, adress: tx.clone(), allowed_facility_types: None });
+        let nonvip = Arc::new(User { id: 3, vip: false, adress: tx, allowed_facility_types: None });
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip1))), clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip2))), clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+
+        // we expect the two vips to fill the capacity of 3, so a non-vip doesn't fit anymore
+        let late = Arc::new(RwLock::new(Booking::new(15, 25, rooms_arc.clone(), nonvip)));
+        assert_eq!(book_facility(late, clock, &sink).0, BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+        assert_eq!(rooms_arc.read().unwrap().confirmed_count(), 2);
+    }
+
+    #[test]
+    fn test_displaceable_for_prospective_vip(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+
+        // a vip and a non-vip fill the room from 10 to 20, another non-vip books it later on
+        let (tx, _rx) = mpsc::channel();
+        let vip = Arc::new(User { id: 1, vip: true, adress: tx.clone(), allowed_facility_types: None });
+        let nonvip1 = Arc::new(User { id: 2, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let nonvip2 = Arc::new(User { id: 3, vip: false, adress: tx, allowed_facility_types: None });
+        book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip))), clock.clone(), &sink);
+        book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), nonvip1))), clock.clone(), &sink);
+        book_facility(Arc::new(RwLock::new(Booking::new(30, 40, rooms_arc.clone(), nonvip2))), clock, &sink);
+
+        // we expect only the overlapping non-vip booking to be bumped, and nothing to have changed
+        let views = displaceable(&rooms_arc, 15, 25);
+        assert_eq!(views.len(), 1);
+        assert_eq!(views[0].user_id, 2);
+        assert_eq!(rooms_arc.read().unwrap().confirmed_count(), 3);
+
+        // where the room has spare capacity nobody is bumped
+        assert!(displaceable(&rooms_arc, 30, 40).is_empty());
+    }
+
+    #[test]
+    fn test_bookings_with_tag(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 3)));
+
+        // two of the three skeletons are tagged as exams
+        let skeletons = vec![
+            BookingSkeleton::builder().start(10).end(20).facility(rooms_arc.clone()).tag("exam").build().unwrap(),
+            BookingSkeleton::builder().start(15).end(25).facility(rooms_arc.clone()).tag("lecture").build().unwrap(),
+            BookingSkeleton::builder().start(30).end(40).facility(rooms_arc.clone()).tag("exam").tag("oral").build().unwrap(),
+        ];
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        for skeleton in &skeletons {
+            book_facility(Arc::new(RwLock::new(Booking::from_skeleton(skeleton, user.clone()))), clock.clone(), &sink);
+        }
+
+        // we expect exactly the two exams to be found
+        let exams: Vec<(u32, u32)> = bookings_with_tag(&rooms_arc, "exam").iter().map(|v| (v.start, v.end)).collect();
+        assert_eq!(exams, vec![(10, 20), (30, 40)]);
+        assert!(bookings_with_tag(&rooms_arc, "maintenance").is_empty());
+    }
+
+    #[test]
+    fn test_booking_ids_unique_across_threads(){
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // many threads create bookings at the same time
+        let threads: Vec<_> = (0..16).map(|i| {
+            let rooms_arc = rooms_arc.clone();
+            thread::spawn(move || {
+                let (tx, _rx) = mpsc::channel();
+                let user = Arc::new(User { id: i, vip: false, adress: tx, allowed_facility_types: None });
+                (0..100).map(|_| Booking::new(10, 20, rooms_arc.clone(), user.clone()).id).collect::<Vec<u64>>()
+            })
+        }).collect();
+        let ids: Vec<u64> = threads.into_iter().flat_map(|t| t.join().unwrap()).collect();
+
+        // we expect no id to be handed out twice
+        let unique: HashSet<u64> = ids.iter().copied().collect();
+        assert_eq!(unique.len(), ids.len());
+    }
+
+    #[test]
+    fn test_log_lines_carry_booking_id(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // log the events instead of printing them
+        let logger = Arc::new(VecLogger::new());
+        let sink: SharedSink = Arc::new(LogSink { logger: logger.clone(), style: LogStyle::Emoji });
+
+        // a non-vip user books the room, then a vip user takes it over
+        let (tx, _rx) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        let booking1 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user1)));
+        let booking2 = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user2)));
+        let (id1, id2) = (booking1.read().unwrap().id, booking2.read().unwrap().id);
+        book_facility(booking1, program_time.clone(), &sink);
+        book_facility(booking2, program_time, &sink);
+
+        // we expect the confirmation and the cancellation of the first booking to be told apart by its id
+        let messages: Vec<String> = logger.messages().into_iter().map(|(_, message)| message).collect();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].starts_with(&format!("[#{}] ", id1)) && messages[0].contains("User 1 booked"));
+        assert!(messages[1].starts_with(&format!("[#{}] ", id1)) && messages[1].contains("was cancelled"));
+        assert!(messages[2].starts_with(&format!("[#{}] ", id2)));
+    }
+
+    #[test]
+    fn test_can_book_full_room(){
+        let clock = Arc::new(ManualClock::new());
+        let vec_sink = Arc::new(VecSink::new());
+        let sink: SharedSink = vec_sink.clone();
+
+        // create facilities, filled by a non-vip
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let (tx, _rx) = mpsc::channel();
+        let nonvip = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let vip = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        let booking = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), nonvip.clone())));
+        book_facility(booking.clone(), clock.clone(), &sink);
+
+        // we expect the vip to be able to displace the booking, the non-vip not
+        assert!(can_book(&vip, &rooms_arc, 15, 25, clock.clone()));
+        assert!(!can_book(&nonvip, &rooms_arc, 15, 25, clock.clone()));
+
+        // and nothing to have happened to the room
+        assert_eq!(booking.read().unwrap().status, CONFIRMED);
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        assert_eq!(vec_sink.events().len(), 1);
+
+        // a slot in the past can't be booked, not even by a vip
+        clock.tick(30);
+        assert!(!can_book(&vip, &rooms_arc, 20, 25, clock));
+    }
+
+    #[test]
+    fn test_compact_removes_stale_bookings(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // a booking that will be over, one displaced by a vip and two that are still to come
+        let (tx, _rx) = mpsc::channel();
+        let nonvip = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let vip = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        let over = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), nonvip.clone())));
+        let displaced = Arc::new(RwLock::new(Booking::new(30, 40, rooms_arc.clone(), nonvip.clone())));
+        let taken = Arc::new(RwLock::new(Booking::new(30, 40, rooms_arc.clone(), vip)));
+        let later = Arc::new(RwLock::new(Booking::new(50, 60, rooms_arc.clone(), nonvip)));
+        for b in [&over, &displaced, &taken, &later] {
+            book_facility(b.clone(), clock.clone(), &sink);
+        }
+        clock.tick(25);
+
+        // we expect one pass to leave only the live bookings
+        let facilities = vec![rooms_arc.clone()];
+        let program_time: SharedClock = clock;
+        assert_eq!(compact(&facilities, &program_time), 2);
+        let rooms = rooms_arc.read().unwrap();
+        assert_eq!(rooms.bookings().len(), 2);
+        assert!(rooms.bookings().iter().all(|b| Arc::ptr_eq(b, &taken) || Arc::ptr_eq(b, &later)));
+        assert_eq!(rooms.overlapping(0, 100).len(), 2);
+    }
+
+    #[test]
+    fn test_compactor_runs_until_stopped(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities with a cancelled booking
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user)));
+        book_facility(booking.clone(), clock.clone(), &sink);
+        booking.write().unwrap().status = CANCELLED;
+
+        // we expect the compactor to remove it in the background
+        let compactor = start_compactor(vec![rooms_arc.clone()], Duration::from_millis(10), clock);
+        thread::sleep(Duration::from_millis(100));
+        compactor.stop();
+        assert!(rooms_arc.read().unwrap().bookings().is_empty());
+    }
+
+    #[test]
+    fn test_published_snapshots(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let published = rooms_arc.read().unwrap().published();
+        assert!(published.current().is_empty());
+
+        // a non-vip books the room
+        let (tx, _rx) = mpsc::channel();
+        let nonvip = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let vip = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), nonvip))), clock.clone(), &sink);
+        let old = snapshot_of(rooms_arc.read().unwrap().id);
+
+        // then a vip takes it over
+        book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip))), clock, &sink);
+
+        // we expect the old snapshot to be unchanged, while the new one shows the displacement
+        assert_eq!(old.len(), 1);
+        assert_eq!((old[0].user_id, old[0].status), (1, CONFIRMED));
+        let new = snapshot_of(rooms_arc.read().unwrap().id);
+        assert_eq!(new.iter().map(|v| (v.user_id, v.status)).collect::<Vec<_>>(), vec![(1, CANCELLED), (2, CONFIRMED)]);
+
+        // and the snapshots to be readable while the facility is locked
+        let _guard = rooms_arc.write().unwrap();
+        assert_eq!(published.current().len(), 2);
+    }
+
+    #[test]
+    fn test_vip_without_displacement(){
+        let clock = Arc::new(ManualClock::new());
+        let events = Arc::new(VecSink::new());
+        let sink: SharedSink = events.clone();
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        rooms_arc.write().unwrap().allow_displacement = false;
+
+        // a non-vip fills the room
+        let (tx, _rx) = mpsc::channel();
+        let nonvip = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let vip = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        let (result, _) = book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), nonvip))), clock.clone(), &sink);
+        assert!(matches!(result, BookingResult::Confirmed { .. }));
+
+        // we expect the vip to be rejected and nobody to be bumped
+        assert!(displaceable(&rooms_arc, 10, 20).is_empty());
+        let (result, cancelled) = book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip))), clock, &sink);
+        assert_eq!(result, BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+        assert!(cancelled.is_empty());
+        assert!(matches!(events.events().last(), Some(BookingEvent::Rejected { user_id: 2, reason: RejectReason::CapacityExceeded, .. })));
+        let facility = rooms_arc.read().unwrap();
+        assert_eq!(facility.bookings().iter().map(|b| (b.read().unwrap().user().id, b.read().unwrap().status)).collect::<Vec<_>>(), vec![(1, CONFIRMED)]);
+    }
+
+    #[test]
+    fn test_join_all_returns_results(){
+        let clock = Arc::new(ManualClock::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        rooms_arc.write().unwrap().max_duration = Some(20);
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // user 1 books a mix of bookings, user 2 has nothing to book
+        let skeleton = |start, end, facility: &Arc<RwLock<Facility>>| BookingSkeleton::new(start, end, facility.clone());
+        let bookings = vec![
+            vec![skeleton(10, 20, &rooms_arc), skeleton(15, 25, &rooms_arc), skeleton(30, 60, &rooms_arc), skeleton(10, 20, &projectors_arc)],
+            Vec::new(),
+        ];
+        let users = start_users_with_sink(vec![1, 2], vec![false, false], bookings, clock, Arc::new(VecSink::new())).unwrap();
+        let results = users.join_all();
+
+        // we expect the results in the order of the bookings
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0].as_slice(), [
+            BookingResult::Confirmed { .. },
+            BookingResult::Rejected { reason: RejectReason::CapacityExceeded },
+            BookingResult::RejectedTooLong,
+            BookingResult::Confirmed { .. },
+        ]));
+        assert!(results[1].is_empty());
+        let room_id = rooms_arc.read().unwrap().bookings()[0].read().unwrap().id;
+        assert_eq!(results[0][0], BookingResult::Confirmed { id: room_id });
+    }
+
+    #[test]
+    fn test_overlapping_confirmed(){
+        // create facilities, bookings less than 2 apart conflict
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 5)));
+        rooms_arc.write().unwrap().buffer = 2;
+
+        // fill the room by hand
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let spans = [(0, 9, CONFIRMED), (5, 12, CONFIRMED), (12, 14, CANCELLED), (21, 25, CONFIRMED), (22, 30, CONFIRMED), (30, 40, CONFIRMED)];
+        let mut bookings = Vec::new();
+        for (start, end, status) in spans {
+            let booking = Arc::new(RwLock::new(Booking::new(start, end, rooms_arc.clone(), user.clone())));
+            booking.write().unwrap().status = status;
+            rooms_arc.write().unwrap().add_booking(booking.clone(), start, end);
+            bookings.push(booking);
+        }
+
+        // we expect the confirmed bookings overlapping 10 to 20 or ending / starting within the buffer
+        let probe = Booking::new(10, 20, rooms_arc.clone(), user);
+        let facility = rooms_arc.read().unwrap();
+        let overlapping = overlapping_confirmed(&facility, &probe);
+        assert_eq!(overlapping.len(), 3);
+        for i in [0, 1, 3] {
+            assert!(overlapping.iter().any(|b| Arc::ptr_eq(b, &bookings[i])));
+        }
+    }
+
+    #[test]
+    fn test_protect_window(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities, bookings starting within 5 units of time are protected
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        rooms_arc.write().unwrap().protect_window = Some(5);
+
+        // a non-vip books the room shortly ahead, another one later on
+        let (tx, _rx) = mpsc::channel();
+        let nonvip = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let vip = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        let soon = Arc::new(RwLock::new(Booking::new(5, 15, rooms_arc.clone(), nonvip.clone())));
+        let later = Arc::new(RwLock::new(Booking::new(20, 30, rooms_arc.clone(), nonvip)));
+        book_facility(soon.clone(), clock.clone(), &sink);
+        book_facility(later.clone(), clock.clone(), &sink);
+
+        // we expect the vip not to displace the booking starting soon
+        let (result, cancelled) = book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip.clone()))), clock.clone(), &sink);
+        assert_eq!(result, BookingResult::Rejected { reason: RejectReason::CapacityExceeded });
+        assert!(cancelled.is_empty());
+        assert_eq!(soon.read().unwrap().status, CONFIRMED);
+
+        // but the one outside of the window
+        let (result, cancelled) = book_facility(Arc::new(RwLock::new(Booking::new(20, 30, rooms_arc.clone(), vip))), clock, &sink);
+        assert!(matches!(result, BookingResult::Confirmed { .. }));
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(later.read().unwrap().status, CANCELLED);
+    }
+
+    #[test]
+    fn test_set_capacity_cancels_newest(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities, the newest booking goes first
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 3)));
+        rooms_arc.write().unwrap().victim_policy = VictimPolicy::MostRecent;
+
+        // three users fill the room one after the other
+        let mut inboxes = Vec::new();
+        let mut bookings = Vec::new();
+        for id in 1..=3 {
+            let (tx, rx) = mpsc::channel();
+            let user = Arc::new(User { id, vip: false, adress: tx, allowed_facility_types: None });
+            let booking = Arc::new(RwLock::new(Booking::new(10 + id, 20, rooms_arc.clone(), user)));
+            book_facility(booking.clone(), clock.clone(), &sink);
+            inboxes.push(rx);
+            bookings.push(booking);
+            clock.tick(1);
+        }
+        assert_eq!(confirmed_overlaps(&rooms_arc.read().unwrap(), 13, 20), 3);
+
+        // we expect the capacity of 2 to cost the newest booking
+        assert_eq!(set_capacity(&rooms_arc, 2, clock.clone()), 1);
+        assert_eq!(rooms_arc.read().unwrap().capacity, 2);
+        assert_eq!(bookings.iter().map(|b| b.read().unwrap().status).collect::<Vec<_>>(), vec![CONFIRMED, CONFIRMED, CANCELLED]);
+        assert!(Arc::ptr_eq(&inboxes[2].try_recv().unwrap(), &bookings[2]));
+        assert!(inboxes[0].try_recv().is_err());
+        assert_eq!(confirmed_overlaps(&rooms_arc.read().unwrap(), 10, 20), 2);
+
+        // and lowering it to where the bookings fit already changes nothing
+        assert_eq!(set_capacity(&rooms_arc, 2, clock), 0);
+    }
+
+    #[test]
+    fn test_same_start_victim_is_deterministic(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+        let (tx, _rx) = mpsc::channel();
+        let nonvip = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let vip = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+
+        // the same three bookings land in the room in every order
+        for order in [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]] {
+            let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 3)));
+            let bookings: Vec<_> = (0..3).map(|_| Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), nonvip.clone())))).collect();
+            for i in order {
+                book_facility(bookings[i].clone(), clock.clone(), &sink);
+            }
+
+            // we expect the vip to displace the booking created first, whatever the order
+            let (_, cancelled) = book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), vip.clone()))), clock.clone(), &sink);
+            assert_eq!(cancelled.len(), 1);
+            assert!(Arc::ptr_eq(&cancelled[0], &bookings[0]));
+        }
+    }
+
+    #[test]
+    fn test_log_styles(){
+        let event = BookingEvent::Confirmed { id: 7, user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20 };
+
+        // log the same event in each style
+        let messages: Vec<String> = [LogStyle::Emoji, LogStyle::Plain, LogStyle::Json].into_iter().map(|style| {
+            let logger = Arc::new(VecLogger::new());
+            LogSink { logger: logger.clone(), style }.emit(&event);
+            logger.messages()[0].1.clone()
+        }).collect();
+
+        // we expect the emoji by default, plain words instead of them and a JSON object
+        assert_eq!(LogStyle::default(), LogStyle::Emoji);
+        assert_eq!(messages[0], "[#7] ✅: Non-VIP User 1 booked Room from time 10 to time 20.");
+        assert_eq!(messages[1], "[#7] OK: Non-VIP User 1 booked Room from time 10 to time 20.");
+        let json: serde_json::Value = serde_json::from_str(&messages[2]).unwrap();
+        assert_eq!(json, serde_json::json!({ "event": "confirmed", "id": 7, "user_id": 1, "vip": false, "fac_type": ROOM, "start": 10, "end": 20 }));
+
+        // a failure is marked as one in the plain style
+        let logger = Arc::new(VecLogger::new());
+        LogSink { logger: logger.clone(), style: LogStyle::Plain }.emit(&BookingEvent::Notified { id: 7, user_id: 1, vip: false });
+        assert_eq!(logger.messages()[0].1, "[#7] FAIL: Non-VIP User 1 received cancellation message.");
+        assert_eq!(parse_args(&["--log-style".to_string(), "json".to_string()]).unwrap().log_style, LogStyle::Json);
+        assert!(parse_args(&["--log-style".to_string(), "fancy".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_allow_equal_book_n_of(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create a room admitting one booking more than its capacity, which is booked already
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        rooms_arc.write().unwrap().capacity_mode = CapacityMode::AllowEqual;
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        book_n_of(std::slice::from_ref(&rooms_arc), 1, 10, 20, user.clone(), clock.clone(), &sink).unwrap();
+
+        // we expect the room to take one more booking, but not another one
+        assert!(book_n_of(std::slice::from_ref(&rooms_arc), 1, 10, 20, user.clone(), clock.clone(), &sink).is_ok());
+        assert_eq!(book_n_of(std::slice::from_ref(&rooms_arc), 1, 10, 20, user, clock, &sink), Err(BookingError::NotEnoughFacilities { available: 0, requested: 1 }));
+    }
+
+    #[test]
+    fn test_allow_equal_book_across_pools(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create a room admitting one booking more than its capacity, which is booked already
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        rooms_arc.write().unwrap().capacity_mode = CapacityMode::AllowEqual;
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let pools = vec![vec![rooms_arc.clone()]];
+        book_across_pools(&pools, 10, 20, user.clone(), clock.clone(), &sink).unwrap();
+
+        // we expect the room to take one more booking, but not another one
+        assert!(book_across_pools(&pools, 10, 20, user.clone(), clock.clone(), &sink).is_ok());
+        assert_eq!(book_across_pools(&pools, 10, 20, user, clock, &sink), Err(BookingError::PoolExhausted { pool: 0 }));
+    }
+
+    #[test]
+    fn test_allow_equal_http_availability(){
+        // create a store with a room admitting one booking more than its capacity, which is booked once
+        let mut store = FacilityStore::new();
+        let rooms_arc = store.register(Facility::new(ROOM, 1));
+        rooms_arc.write().unwrap().capacity_mode = CapacityMode::AllowEqual;
+        let room_id = rooms_arc.read().unwrap().id;
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user))), program_time.clone(), &sink);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        start_server(listener, Arc::new(store), program_time, sink);
+
+        // we expect the room to be reported available, as it admits another booking
+        use std::io::{Read, Write};
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.write_all(format!("GET /availability?facility={}&start=10&end=20 HTTP/1.1\r\n\r\n", room_id).as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.contains("\"available\":true"));
+    }
+
+    #[test]
+    fn test_allow_equal_retrying_user(){
+        // a clock the test advances itself
+        let clock = Arc::new(ManualClock::new());
+
+        // create a room admitting one booking more than its capacity, which is fully booked
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        rooms_arc.write().unwrap().capacity_mode = CapacityMode::AllowEqual;
+        let (tx, _rx) = mpsc::channel();
+        let blocker = Arc::new(User { id: 9, vip: false, adress: tx, allowed_facility_types: None });
+        let blocking = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), blocker.clone())));
+        let sink: SharedSink = Arc::new(VecSink::new());
+        assert!(matches!(book_facility(blocking.clone(), clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), blocker))), clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+
+        // a user retrying up to 10 times, waiting a tick before each attempt
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        let users = start_users_with_retry(vec![1], vec![false], vec![usr1_bookings], clock.clone(), sink, (10, 1)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        // one of the bookings is cancelled, which leaves room for another one, then time passes
+        blocking.write().unwrap().status = CANCELLED;
+        for _ in 0..5 {
+            clock.tick(1);
+            thread::sleep(Duration::from_millis(50));
+        }
+        users.join_all();
+
+        // we expect the retrying user to have got the room
+        let rooms = rooms_arc.read().unwrap();
+        assert!(rooms.bookings().iter().any(|b| b.read().unwrap().status == CONFIRMED && b.read().unwrap().user().id == 1));
+    }
+
+    #[test]
+    fn test_http_limits(){
+        // serve a store without facilities on a free port
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        start_server(listener, Arc::new(FacilityStore::new()), program_time, sink);
+
+        // we expect a request announcing a huge body to be refused without the body being sent
+        use std::io::{Read, Write};
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.write_all(b"POST /book HTTP/1.1\r\nContent-Length: 1000000000\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+
+        // and a client that stops sending halfway to be cut off, instead of holding on to its thread for good
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.write_all(b"POST /book HTTP/1.1\r\nContent-Length: 10\r\n\r\n{").unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400"));
+    }
+
+    #[test]
+    fn test_render_calendar_peak(){
+        // create a room for two, booked twice after each other and twice at the same time
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        for (start, end) in [(10, 15), (15, 20), (30, 40), (30, 40)] {
+            let mut booking = Booking::new(start, end, rooms_arc.clone(), user.clone());
+            booking.status = CONFIRMED;
+            rooms_arc.write().unwrap().add_booking(Arc::new(RwLock::new(booking)), start, end);
+        }
+
+        // we expect only the column with both bookings at the same time to be full
+        assert_eq!(render_calendar(&rooms_arc, 0, 40, 4), ".+.#");
+    }
+
+    #[test]
+    fn test_utilization_capacity_schedule(){
+        // create a room for two that only has one place during the second half of the window
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        rooms_arc.write().unwrap().capacity_schedule = vec![(10..20, 1)];
+
+        // put one confirmed booking into the second half
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let mut booking = Booking::new(10, 20, rooms_arc.clone(), user);
+        booking.status = CONFIRMED;
+        rooms_arc.write().unwrap().add_booking(Arc::new(RwLock::new(booking)), 10, 20);
+
+        // we expect 10 of 2 * 10 + 1 * 10 units of capacity-time to be used
+        assert_eq!(utilization(&rooms_arc, 0, 20), 10.0 / 30.0);
+    }
+
+    #[test]
+    fn test_dry_run_booking_takes_no_id(){
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking = Booking::new(10, 20, rooms_arc.clone(), user.clone());
+        let probe = Booking::dry_run(10, 20, rooms_arc, user);
+
+        // we expect the probe to have no id of the counter but to order like a booking made after the other one
+        assert_eq!(probe.id, u64::MAX);
+        assert_eq!(booking.order(&probe), std::cmp::Ordering::Less);
+    }
+
+    #[test]
+    fn test_set_capacity_keeps_overbooking_and_capacity_mode(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities, overbooking by half of the capacity
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 4)));
+        rooms_arc.write().unwrap().overbook_pct = 50;
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        for _ in 0..5 {
+            book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user.clone()))), clock.clone(), &sink);
+        }
+        assert_eq!(confirmed_overlaps(&rooms_arc.read().unwrap(), 10, 20), 5);
+
+        // we expect a capacity of 2 to keep the 3 bookings it admits with the overbooking
+        assert_eq!(set_capacity(&rooms_arc, 2, clock.clone()), 2);
+        assert_eq!(confirmed_overlaps(&rooms_arc.read().unwrap(), 10, 20), 3);
+
+        // and a capacity admitting equal overlaps to keep one more than the capacity
+        rooms_arc.write().unwrap().overbook_pct = 0;
+        rooms_arc.write().unwrap().capacity_mode = CapacityMode::AllowEqual;
+        assert_eq!(set_capacity(&rooms_arc, 2, clock.clone()), 0);
+        assert_eq!(set_capacity(&rooms_arc, 1, clock), 1);
+        assert_eq!(confirmed_overlaps(&rooms_arc.read().unwrap(), 10, 20), 2);
+    }
+
+    #[test]
+    fn test_book_n_of_checks_holds_and_rules(){
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // of 5 facilities only the last one can be booked for 10 right away by a user allowed rooms:
+        // the first is held, the second only takes bookings of at most 5, the third only bookings
+        // made 20 in advance and the fourth is a projector
+        let mut facilities = vec![Facility::new(ROOM, 1), Facility::new(ROOM, 1), Facility::new(ROOM, 1), Facility::new(PROJECTOR, 1), Facility::new(ROOM, 1)];
+        facilities[1].max_duration = Some(5);
+        facilities[2].min_lead = 20;
+        let facilities: Vec<Arc<RwLock<Facility>>> = facilities.into_iter().map(|f| Arc::new(RwLock::new(f))).collect();
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: Some(ROOM) });
+        let mut hold = Booking::new(10, 20, facilities[0].clone(), user.clone());
+        hold.status = HELD;
+        facilities[0].write().unwrap().add_booking(Arc::new(RwLock::new(hold)), 10, 20);
+
+        // we expect only the last one to count as free
+        assert_eq!(book_n_of(&facilities, 2, 10, 20, user.clone(), program_time.clone(), &sink), Err(BookingError::NotEnoughFacilities { available: 1, requested: 2 }));
+        assert_eq!(book_n_of(&facilities, 1, 10, 20, user, program_time, &sink).unwrap().len(), 1);
+        assert_eq!(facilities[4].read().unwrap().confirmed_count(), 1);
+        assert!(facilities[..4].iter().all(|f| f.read().unwrap().confirmed_count() == 0));
+    }
+
+    #[test]
+    fn test_vip_units_displace_enough(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // a room of capacity 3 with three single non-vip bookings
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 3)));
+        let (tx, _rx) = mpsc::channel();
+        let nonvip = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let bookings: Vec<Arc<RwLock<Booking>>> = (0..3).map(|_| Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), nonvip.clone())))).collect();
+        for b in &bookings {
+            assert!(matches!(book_facility(b.clone(), clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        }
+
+        // we expect a vip booking of 2 seats to displace two of them, the first ones booked
+        let vip = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        let mut pair = Booking::new(10, 20, rooms_arc.clone(), vip);
+        pair.units = 2;
+        let (result, cancelled) = book_facility(Arc::new(RwLock::new(pair)), clock, &sink);
+        assert!(matches!(result, BookingResult::Confirmed { .. }));
+        assert_eq!(cancelled.len(), 2);
+        assert_eq!(bookings.iter().map(|b| b.read().unwrap().status).collect::<Vec<_>>(), vec![CANCELLED, CANCELLED, CONFIRMED]);
+        assert_eq!(confirmed_overlaps(&rooms_arc.read().unwrap(), 10, 20), 3);
+    }
+
+    #[test]
+    fn test_user_total_cost_of_units(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities, a seat of the room costs 2 per unit of time
+        let mut room = Facility::new(ROOM, 5);
+        room.cost_per_unit_time = 2;
+        let rooms_arc = Arc::new(RwLock::new(room));
+
+        // the user books 3 seats and then a single one
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let seats = BookingSkeleton::builder().start(10).end(20).facility(rooms_arc.clone()).units(3).build().unwrap();
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::from_skeleton(&seats, user.clone()))), clock.clone(), &sink).0, BookingResult::Confirmed { .. }));
+        assert!(matches!(book_facility(Arc::new(RwLock::new(Booking::new(30, 35, rooms_arc.clone(), user))), clock, &sink).0, BookingResult::Confirmed { .. }));
+
+        // we expect 10 * 2 * 3 for the seats and 5 * 2 for the single one
+        assert_eq!(user_total_cost(1, &[rooms_arc]), 70);
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,1142 @@
+#[cfg(test)]
+use crate::ProgramTime;
+use crate::Clock;
+use crate::ManualClock;
+use crate::SharedClock;
+use crate::start_program_time;
+use crate::BookingSkeleton;
+use crate::ROOM;
+use crate::PROJECTOR;
+use crate::Facility;
+use crate::compound::start_users;
+use std::sync::{Arc, Barrier, RwLock};
+use crate::overlap;
+use std::thread;
+use std::time::{Duration};
+use crate::CANCELLED;
+use crate::CONFIRMED;
+use crate::UNCONFIRMED;
+use crate::compound::start_users_with_sink;
+use crate::compound::start_users_with_atomic;
+use crate::compound::start_users_with_required;
+use crate::compound::start_users_with_compounds;
+use crate::compound::cancel_compound;
+use crate::compound::CompoundTransaction;
+use crate::SharedSink;
+use crate::snapshot;
+use crate::restore;
+use crate::RejectReason;
+use crate::AuditAction;
+use crate::AuditReason;
+use crate::VecSink;
+use crate::BookingEvent;
+use crate::Booking;
+use crate::User;
+use crate::reap_unconfirmed;
+use crate::verify_consistency;
+use crate::occupied_overlaps;
+use crate::reserve;
+use crate::confirm;
+use crate::TENTATIVE;
+use std::sync::mpsc;
+use crate::test_util::assert_capacity_invariant;
+use crate::test_util::booking_id;
+use crate::snapshot_of;
+use crate::basic::book_facility;
+use crate::reschedule;
+use crate::swap_bookings;
+use crate::cancel_all_for_user;
+use crate::book_in_pool;
+use crate::book_n_of;
+use crate::book_across_pools;
+use crate::FacilityPool;
+use crate::WAITLISTED;
+use crate::VictimPolicy;
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_current_time() {
+        let program_time = ProgramTime::new(0, u32::MAX);
+        assert_eq!(program_time.get_current_time(), 0);
+    }
+
+    #[test]
+    fn test_start_program_time() {
+        let program_time = start_program_time();
+        assert_eq!(program_time.get_current_time(), 0);
+    }
+
+    #[test]
+    fn test_1user_1compound_0possible(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+        let projectors = Facility::new(PROJECTOR, 0);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+        
+        // create user bookings
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users(vec![1], vec![true], vec![usr1_bookings], program_time.clone());
+
+        thread::sleep(Duration::from_secs(2));
+
+        let bookings = rooms_arc.read().unwrap().bookings().to_vec();
+
+        // we expect this output because the projector is not available,
+        // that means that the hold on the room is released again
+        assert!(bookings.is_empty());
+
+    }
+
+    #[test]
+    fn test_1user_1compound_1possible(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+        let projectors = Facility::new(PROJECTOR, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+        
+        // create user bookings
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(25, 30, projectors_arc.clone())];
+        start_users(vec![1], vec![true], vec![usr1_bookings], program_time.clone());
+
+        thread::sleep(Duration::from_secs(2));
+
+        // check how many rooms we have
+        let len_rooms = &rooms_arc.read().unwrap().bookings().len();
+        let len_projectors = &projectors_arc.read().unwrap().bookings().len();
+
+        let mut confirmed_rooms = 0;
+        let mut confirmed_projectors = 0;
+
+        for i in 0..*len_rooms {
+            if rooms_arc.read().unwrap().bookings()[i].read().unwrap().status == CONFIRMED {
+                confirmed_rooms += 1;
+            }
+        }
+        for i in 0..*len_projectors {
+            if projectors_arc.read().unwrap().bookings()[i].read().unwrap().status == CONFIRMED {
+                confirmed_projectors += 1;
+            }
+        }
+
+        // we expect this output because the projector & room are available,
+        // that means that the booking is confirmed
+        assert_eq!(confirmed_rooms, 1);
+        assert_eq!(confirmed_projectors, 1);
+    }
+
+    #[test]
+    fn test_2users_2compounds_2possible_no_overlap(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+
+        // create user bookings        
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(25, 30, rooms_arc.clone())];
+        start_users(vec![1, 2], vec![false,true], vec![usr1_bookings, usr2_bookings], program_time.clone());
+
+        thread::sleep(Duration::from_secs(2));
+
+        // check how many rooms we have
+        let len_rooms = &rooms_arc.read().unwrap().bookings().len();
+
+        let mut confirmed_rooms = 0;
+
+        for i in 0..*len_rooms {
+            if rooms_arc.read().unwrap().bookings()[i].read().unwrap().status == CONFIRMED {
+                confirmed_rooms += 1;
+            }
+        }
+
+        // we expect this output because the projectors & rooms are available,
+        // that means that compound bookings are confirmed
+        assert_eq!(confirmed_rooms, 2);
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 2);
+        assert!(!overlap(&rooms_arc.read().unwrap().bookings()[0].read().unwrap(), &rooms_arc.read().unwrap().bookings()[1].read().unwrap()));
+
+        let bookings = rooms_arc.read().unwrap().bookings().to_vec();
+        let user_id_0 = bookings[0].read().unwrap().user().id;
+        let user_id_1 = bookings[1].read().unwrap().user().id;
+        assert!((user_id_0 == 1 && user_id_1 == 2) || (user_id_0 == 2 && user_id_1 == 1));
+        
+    }
+
+    #[test]
+    fn test_2users_2compounds_1vip_1possible(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        // create user bookings
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        start_users(vec![1, 2], vec![false,true], vec![usr1_bookings, usr2_bookings], program_time.clone());
+
+        thread::sleep(Duration::from_secs(2));
+
+        // check how many rooms we have
+        let len = &rooms_arc.read().unwrap().bookings().len();
+        let bookings = rooms_arc.read().unwrap().bookings().to_vec();
+        let booking0_user_vip = bookings[0].read().unwrap().user().vip;
+
+
+        // we expect this output because the projectors & rooms are available,
+        // but only one user is vip, that means that compound bookings are confirmed only
+        // for the vip user and the other one is cancelled or unconfirmed
+        if *len == 1 {
+            assert!(booking0_user_vip);
+            assert!(bookings[0].read().unwrap().status == CONFIRMED);
+        } else {
+            let booking1_user_vip = bookings[1].read().unwrap().user().vip;
+            let booking0_status = bookings[0].read().unwrap().status;
+            assert!(booking1_user_vip);
+            assert!(booking0_status == CANCELLED);
+        }    
+
+    }
+
+    #[test]
+    fn test_3users_8bookings_2compund_1vip_2possible(){
+        
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 2);
+        let projectors = Facility::new(PROJECTOR, 2);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+        // create user bookings
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        let usr3_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users(vec![1, 2, 3], vec![false, false, true], vec![usr1_bookings, usr2_bookings, usr3_bookings], program_time.clone());
+
+
+        // write me here correct assertion based on previous tests
+        thread::sleep(Duration::from_secs(6));
+
+        // check how many rooms we have
+        let confirmed_rooms = rooms_arc.read().unwrap().confirmed_count();
+        let confirmed_projectors = projectors_arc.read().unwrap().confirmed_count();
+        let cancelled_rooms = rooms_arc.read().unwrap().cancelled_count();
+        let cancelled_projectors = projectors_arc.read().unwrap().cancelled_count();
+
+        // we expect this output because the projectors & rooms are available,
+        // but only one user is vip, that means that one compound of vip user is confirmed,
+        // one of non-vip user is confirmed and the other one is cancelled or unconfirmed
+        assert_eq!(confirmed_rooms, 2);
+        assert_eq!(confirmed_projectors, 2);
+
+        assert!((cancelled_rooms == 0 && cancelled_projectors == 0) || (cancelled_rooms == 1 && cancelled_projectors == 1));
+        assert_capacity_invariant(&rooms_arc);
+        assert_capacity_invariant(&projectors_arc);
+
+    }
+
+    #[test]
+    fn test_2users_2compounds_1possible_horizon(){
+        // program time that does not advance and only allows bookings ending 50 units ahead
+        let program_time = Arc::new(ProgramTime::new(0, 50));
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+
+        // create user bookings
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(100, 110, rooms_arc.clone())];
+        start_users(vec![1, 2], vec![false, false], vec![usr1_bookings, usr2_bookings], program_time.clone());
+
+        thread::sleep(Duration::from_secs(2));
+
+        // we expect this output because the compound of user 2 ends beyond the horizon
+        // and is therefore never pushed to the facility
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        assert_eq!(rooms_arc.read().unwrap().bookings()[0].read().unwrap().start, 10);
+        assert!(rooms_arc.read().unwrap().bookings()[0].read().unwrap().status == CONFIRMED);
+    }
+
+    #[test]
+    fn test_1user_1compound_events(){
+        // start program time
+        let program_time = start_program_time();
+
+        // create facilities
+        let rooms = Facility::new(ROOM, 1);
+        let projectors = Facility::new(PROJECTOR, 1);
+
+        // generate arcs on RwLockes
+        let rooms_arc = Arc::new(RwLock::new(rooms));
+        let projectors_arc = Arc::new(RwLock::new(projectors));
+
+        // collect the events instead of printing them
+        let sink = Arc::new(VecSink::new());
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users_with_sink(vec![1], vec![false], vec![usr1_bookings], program_time.clone(), sink.clone());
+
+        thread::sleep(Duration::from_secs(2));
+
+        // we expect these events because both parts of the compound are available
+        assert_eq!(sink.events(), vec![
+            BookingEvent::Confirmed { id: booking_id(&rooms_arc, 10, CONFIRMED), user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20 },
+            BookingEvent::Confirmed { id: booking_id(&projectors_arc, 10, CONFIRMED), user_id: 1, vip: false, fac_type: PROJECTOR, start: 10, end: 20 },
+            BookingEvent::CompoundConfirmed { user_id: 1, vip: false },
+        ]);
+    }
+
+    #[test]
+    fn test_reaper_expires_stalled_hold(){
+        // program time that only advances when we say so
+        let clock = Arc::new(ManualClock::new());
+        let program_time: SharedClock = clock.clone();
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let facilities = vec![rooms_arc.clone()];
+
+        // the cancellations of the room are counted
+        let hooked = Arc::new(RwLock::new(0));
+        let hooked_clone = hooked.clone();
+        rooms_arc.write().unwrap().on_cancel = Some(Arc::new(move |_: &Booking| *hooked_clone.write().unwrap() += 1));
+        let events = Arc::new(VecSink::new());
+        let sink: SharedSink = events.clone();
+
+        // a pending part of a compound whose user never comes back
+        let (tx, rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let booking = Arc::new(RwLock::new(Booking::new(50, 60, rooms_arc.clone(), user)));
+        rooms_arc.write().unwrap().add_booking(booking.clone(), 50, 60);
+        assert_eq!(occupied_overlaps(&rooms_arc.read().unwrap(), 50, 60), 1);
+
+        // within the ttl the hold is kept
+        clock.tick(10);
+        assert_eq!(reap_unconfirmed(&facilities, 10, &program_time, &sink), 0);
+        assert_eq!(booking.read().unwrap().status, UNCONFIRMED);
+
+        // we expect the hold to be cancelled and the slot to be free once the ttl has passed
+        clock.tick(1);
+        assert_eq!(reap_unconfirmed(&facilities, 10, &program_time, &sink), 1);
+        assert_eq!(booking.read().unwrap().status, CANCELLED);
+        assert_eq!(occupied_overlaps(&rooms_arc.read().unwrap(), 50, 60), 0);
+
+        // and the expiry to be reported, the user to be notified and the hook to be run
+        let id = booking.read().unwrap().id;
+        assert_eq!(events.events(), vec![BookingEvent::Expired { id, user_id: 1, vip: false, fac_type: ROOM, start: 50, end: 60 }]);
+        assert!(Arc::ptr_eq(&rx.try_recv().unwrap(), &booking));
+        assert_eq!(*hooked.read().unwrap(), 1);
+        assert_eq!(snapshot_of(rooms_arc.read().unwrap().id)[0].status, CANCELLED);
+    }
+
+    #[test]
+    fn test_1user_1compound_not_atomic_1part_possible(){
+        // frozen program time, so the booking can't run into the past
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // the room is already full
+        let (tx, _rx) = mpsc::channel();
+        let other = Arc::new(User { id: 9, vip: false, adress: tx, allowed_facility_types: None });
+        let mut full = Booking::new(10, 20, rooms_arc.clone(), other);
+        full.status = CONFIRMED;
+        rooms_arc.write().unwrap().add_booking(Arc::new(RwLock::new(full)), 10, 20);
+
+        // the user books both, but not all or nothing
+        let sink = Arc::new(VecSink::new());
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users_with_atomic(vec![1], vec![false], vec![false], vec![usr1_bookings], program_time.clone(), sink.clone());
+
+        thread::sleep(Duration::from_secs(2));
+
+        // the projector is confirmed on its own, the room is reported as failed
+        assert_eq!(projectors_arc.read().unwrap().bookings().len(), 1);
+        assert_eq!(projectors_arc.read().unwrap().bookings()[0].read().unwrap().status, CONFIRMED);
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        let events = sink.events();
+        assert_eq!(events, vec![
+            BookingEvent::Rejected { id: events[0].booking_id().unwrap(), user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20, reason: RejectReason::CapacityExceeded },
+            BookingEvent::Confirmed { id: booking_id(&projectors_arc, 10, CONFIRMED), user_id: 1, vip: false, fac_type: PROJECTOR, start: 10, end: 20 },
+            BookingEvent::CompoundRejected { user_id: 1, vip: false },
+        ]);
+    }
+
+    #[test]
+    fn test_1user_1compound_optional_part_full(){
+        // frozen program time, so the booking can't run into the past
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // the projector is already full
+        let (tx, _rx) = mpsc::channel();
+        let other = Arc::new(User { id: 9, vip: false, adress: tx, allowed_facility_types: None });
+        let mut full = Booking::new(10, 20, projectors_arc.clone(), other);
+        full.status = CONFIRMED;
+        projectors_arc.write().unwrap().add_booking(Arc::new(RwLock::new(full)), 10, 20);
+
+        // the room is required, the projector is nice to have
+        let sink = Arc::new(VecSink::new());
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users_with_required(vec![1], vec![false], vec![true], vec![vec![true, false]], vec![usr1_bookings], program_time.clone(), sink.clone());
+
+        thread::sleep(Duration::from_secs(2));
+
+        // the compound is confirmed with just the room
+        assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        assert_eq!(rooms_arc.read().unwrap().bookings()[0].read().unwrap().status, CONFIRMED);
+        assert_eq!(projectors_arc.read().unwrap().bookings().len(), 1);
+        let events = sink.events();
+        assert_eq!(events, vec![
+            BookingEvent::Confirmed { id: booking_id(&rooms_arc, 10, CONFIRMED), user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20 },
+            BookingEvent::Rejected { id: events[1].booking_id().unwrap(), user_id: 1, vip: false, fac_type: PROJECTOR, start: 10, end: 20, reason: RejectReason::CapacityExceeded },
+            BookingEvent::CompoundConfirmed { user_id: 1, vip: false },
+        ]);
+    }
+
+    #[test]
+    fn test_audit_log_vip_displacement(){
+        let clock = Arc::new(ManualClock::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // a non-vip user books the room, then a vip user takes it over
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        start_users_with_sink(vec![1], vec![false], vec![usr1_bookings], clock.clone(), Arc::new(VecSink::new())).join_all();
+        clock.tick(1);
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        start_users_with_sink(vec![1], vec![true], vec![usr2_bookings], clock.clone(), Arc::new(VecSink::new())).join_all();
+
+        // we expect the booking and confirmation of both users in the log, and the cancellation for the vip
+        let facility = rooms_arc.read().unwrap();
+        let log: Vec<(AuditAction, u32)> = facility.audit_log().iter().map(|e| (e.action, e.time)).collect();
+        assert_eq!(log, vec![(AuditAction::Booked, 0), (AuditAction::Confirmed, 0), (AuditAction::Booked, 1), (AuditAction::Confirmed, 1), (AuditAction::Cancelled, 1)]);
+    }
+
+    #[test]
+    fn test_1user_1compound_2rooms_same_facility_capacity2(){
+        // frozen program time, so the booking can't run into the past
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+
+        // the user wants two rooms of the same pool at once
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        start_users(vec![1], vec![false], vec![usr1_bookings], program_time.clone()).join_all();
+
+        // we expect both rooms to be confirmed
+        let rooms = rooms_arc.read().unwrap();
+        assert_eq!(rooms.bookings().len(), 2);
+        assert!(rooms.bookings().iter().all(|b| b.read().unwrap().status == CONFIRMED));
+    }
+
+    #[test]
+    fn test_1user_1compound_2rooms_same_facility_capacity1(){
+        // frozen program time, so the booking can't run into the past
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        // the user wants two rooms of the same pool at once, but there is only one
+        let sink = Arc::new(VecSink::new());
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        start_users_with_sink(vec![1], vec![false], vec![usr1_bookings], program_time.clone(), sink.clone()).join_all();
+
+        // we expect neither room to be confirmed and the room to be free again
+        let rooms = rooms_arc.read().unwrap();
+        assert!(rooms.bookings().iter().all(|b| b.read().unwrap().status != CONFIRMED));
+        assert_eq!(occupied_overlaps(&rooms, 10, 20), 0);
+        assert_eq!(sink.events().last(), Some(&BookingEvent::CompoundRejected { user_id: 1, vip: false }));
+    }
+
+    #[test]
+    fn test_1user_2compounds_1possible(){
+        // frozen program time, so the booking can't run into the past
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities, there is no projector to be had
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 0)));
+
+        // the user wants a room and a projector in the morning, and just a room in the afternoon
+        let morning = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        let afternoon = vec![BookingSkeleton::new(30, 40, rooms_arc.clone())];
+        let sink = Arc::new(VecSink::new());
+        start_users_with_compounds(vec![1], vec![false], vec![vec![morning, afternoon]], program_time.clone(), sink.clone()).join_all();
+
+        // we expect only the afternoon compound to be confirmed
+        let rooms = rooms_arc.read().unwrap();
+        let confirmed: Vec<(u32, u32)> = rooms.bookings().iter().map(|b| b.read().unwrap()).filter(|b| b.status == CONFIRMED).map(|b| (b.start, b.end)).collect();
+        assert_eq!(confirmed, vec![(30, 40)]);
+        assert_eq!(occupied_overlaps(&rooms, 10, 20), 0);
+        let compounds: Vec<BookingEvent> = sink.events().into_iter().filter(|e| matches!(e, BookingEvent::CompoundConfirmed { .. } | BookingEvent::CompoundRejected { .. })).collect();
+        assert_eq!(compounds, vec![BookingEvent::CompoundRejected { user_id: 1, vip: false }, BookingEvent::CompoundConfirmed { user_id: 1, vip: false }]);
+    }
+
+    #[test]
+    fn test_reserve_then_confirm(){
+        let program_time: SharedClock = Arc::new(ManualClock::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        let (tx, _rx) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: false, adress: tx, allowed_facility_types: None });
+
+        // the tentative booking blocks the slot for others
+        let booking = reserve(&rooms_arc, 10, 20, user1, program_time.clone()).unwrap();
+        assert_eq!(booking.read().unwrap().status, TENTATIVE);
+        assert_eq!(reserve(&rooms_arc, 15, 25, user2.clone(), program_time.clone()).err(), Some(RejectReason::CapacityExceeded));
+
+        // we expect the confirmation to promote it once and only once
+        assert!(confirm(&booking, program_time.clone()));
+        assert_eq!(booking.read().unwrap().status, CONFIRMED);
+        assert!(!confirm(&booking, program_time.clone()));
+        assert_eq!(reserve(&rooms_arc, 15, 25, user2, program_time.clone()).err(), Some(RejectReason::CapacityExceeded));
+        let log: Vec<AuditAction> = rooms_arc.read().unwrap().audit_log().iter().map(|e| e.action).collect();
+        assert_eq!(log, vec![AuditAction::Booked, AuditAction::Confirmed]);
+    }
+
+    #[test]
+    fn test_reserve_expires(){
+        // program time that only advances when we say so
+        let clock = Arc::new(ManualClock::new());
+        let program_time: SharedClock = clock.clone();
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let facilities = vec![rooms_arc.clone()];
+
+        let (tx, _rx) = mpsc::channel();
+        let user1 = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let user2 = Arc::new(User { id: 2, vip: false, adress: tx, allowed_facility_types: None });
+        let booking = reserve(&rooms_arc, 50, 60, user1, program_time.clone()).unwrap();
+
+        // we expect the tentative booking to expire once the ttl has passed, freeing the slot
+        clock.tick(11);
+        assert_eq!(reap_unconfirmed(&facilities, 10, &program_time, &(Arc::new(VecSink::new()) as SharedSink)), 1);
+        assert_eq!(booking.read().unwrap().status, CANCELLED);
+        assert!(!confirm(&booking, program_time.clone()));
+        assert_eq!(occupied_overlaps(&rooms_arc.read().unwrap(), 50, 60), 0);
+        assert!(reserve(&rooms_arc, 50, 60, user2, program_time.clone()).is_ok());
+    }
+
+    #[test]
+    fn test_cancel_compound_via_one_member(){
+        // frozen program time, so the booking can't run into the past
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // the user books a room and a projector together
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users(vec![1], vec![false], vec![usr1_bookings], program_time.clone()).join_all();
+        let room_booking = rooms_arc.read().unwrap().bookings()[0].clone();
+        let projector_booking = projectors_arc.read().unwrap().bookings()[0].clone();
+        assert_eq!(projector_booking.read().unwrap().status, CONFIRMED);
+
+        // we expect cancelling via the room to cancel the projector as well and free both facilities
+        assert_eq!(cancel_compound(&room_booking, program_time.clone()), 2);
+        assert_eq!(room_booking.read().unwrap().status, CANCELLED);
+        assert_eq!(projector_booking.read().unwrap().status, CANCELLED);
+        assert_eq!(occupied_overlaps(&rooms_arc.read().unwrap(), 10, 20), 0);
+        assert_eq!(occupied_overlaps(&projectors_arc.read().unwrap(), 10, 20), 0);
+        assert_eq!(projectors_arc.read().unwrap().audit_log().last().unwrap().action, AuditAction::Cancelled);
+
+        // cancelling again finds nothing left to cancel
+        assert_eq!(cancel_compound(&projector_booking, program_time.clone()), 0);
+    }
+
+    #[test]
+    fn test_2users_crossing_compounds_no_deadlock(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        for _ in 0..20 {
+            // create facilities
+            let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+            let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+            // two users whose compounds take the same facilities in opposite order,
+            // started separately so their compounds are booked at the same time
+            let (done_tx, done_rx) = mpsc::channel();
+            let (rooms, projectors, pt) = (rooms_arc.clone(), projectors_arc.clone(), program_time.clone());
+            thread::spawn(move || {
+                let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms.clone()), BookingSkeleton::new(10, 20, projectors.clone())];
+                let usr2_bookings = vec![BookingSkeleton::new(10, 20, projectors), BookingSkeleton::new(10, 20, rooms)];
+                let users1 = start_users_with_sink(vec![1], vec![false], vec![usr1_bookings], pt.clone(), Arc::new(VecSink::new()));
+                let users2 = start_users_with_sink(vec![2], vec![false], vec![usr2_bookings], pt, Arc::new(VecSink::new()));
+                users1.join_all();
+                users2.join_all();
+                done_tx.send(()).unwrap();
+            });
+
+            // we expect the users to finish, and exactly one of the compounds to be booked
+            assert!(done_rx.recv_timeout(Duration::from_secs(10)).is_ok());
+            let confirmed = |facility: &Arc<RwLock<Facility>>| facility.read().unwrap().bookings().iter().filter(|b| b.read().unwrap().status == CONFIRMED).cloned().collect::<Vec<_>>();
+            let (room_confirmed, projector_confirmed) = (confirmed(&rooms_arc), confirmed(&projectors_arc));
+            assert_eq!(room_confirmed.len(), 1);
+            assert_eq!(projector_confirmed.len(), 1);
+            assert!(Arc::ptr_eq(room_confirmed[0].read().unwrap().user(), projector_confirmed[0].read().unwrap().user()));
+        }
+    }
+
+    #[test]
+    fn test_snapshot_restore_compound(){
+        // program time that does not advance, so the scenario is deterministic
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // a vip books a compound of a room and a projector
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        let users = start_users_with_sink(vec![1], vec![true], vec![usr1_bookings], program_time, Arc::new(VecSink::new()));
+        users.join_all();
+
+        // snapshot the facilities and rebuild them
+        let original = snapshot(&[rooms_arc.clone(), projectors_arc.clone()]);
+        assert_eq!(original.compounds.len(), 1);
+        let restored = restore(&original);
+
+        // we expect the same facilities and bookings, in fresh locks
+        assert_eq!(restored.len(), 2);
+        assert!(!Arc::ptr_eq(&restored[0], &rooms_arc));
+        assert_eq!(snapshot(&restored), original);
+
+        // the restored bookings reference the restored facilities, the same user and the same compound
+        let room_booking = restored[0].read().unwrap().bookings()[0].clone();
+        let projector_booking = restored[1].read().unwrap().bookings()[0].clone();
+        let room = room_booking.read().unwrap();
+        let projector = projector_booking.read().unwrap();
+        assert_eq!((room.status, projector.status), (CONFIRMED, CONFIRMED));
+        assert!(Arc::ptr_eq(&room.facility, &restored[0]));
+        assert!(Arc::ptr_eq(&projector.facility, &restored[1]));
+        assert!(Arc::ptr_eq(room.user(), projector.user()));
+        assert!(room.user().vip);
+        let compound = room.compound.clone().unwrap();
+        assert!(Arc::ptr_eq(&compound, projector.compound.as_ref().unwrap()));
+        assert_eq!(compound.len(), 2);
+        assert!(Arc::ptr_eq(&compound[0], &room_booking));
+        assert!(Arc::ptr_eq(&compound[1], &projector_booking));
+
+        // new bookings don't reuse the restored ids
+        let (tx, _rx) = mpsc::channel();
+        let fresh = Booking::new(30, 40, restored[0].clone(), Arc::new(User { id: 2, vip: false, adress: tx, allowed_facility_types: None }));
+        assert!(fresh.id > room.id && fresh.id > projector.id);
+    }
+
+    // This function makes the bookings of a compound of the user from the skeletons, aware of their compound.
+    fn compound_of(user: &Arc<User>, skeletons: Vec<BookingSkeleton>) -> Arc<Vec<Arc<RwLock<Booking>>>> {
+        let parts: Arc<Vec<Arc<RwLock<Booking>>>> = Arc::new(skeletons.into_iter().map(|s| Arc::new(RwLock::new(Booking::new(s.start, s.end, s.facility, user.clone())))).collect());
+        for b in parts.iter() {
+            b.write().unwrap().compound = Some(parts.clone());
+        }
+        parts
+    }
+
+    #[test]
+    fn test_transaction_commit(){
+        let clock: SharedClock = Arc::new(ManualClock::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let parts = compound_of(&user, vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())]);
+
+        // both parts are held, then confirmed together
+        let transaction = CompoundTransaction::begin(parts.clone(), clock);
+        assert!(transaction.possible());
+        assert_eq!(transaction.results(), &[Ok(()), Ok(())]);
+        let sink: SharedSink = Arc::new(VecSink::new());
+        transaction.commit(&sink);
+
+        // we expect all parts confirmed and logged
+        assert!(parts.iter().all(|b| b.read().unwrap().status == CONFIRMED));
+        for facility in [&rooms_arc, &projectors_arc] {
+            let facility = facility.read().unwrap();
+            assert_eq!(facility.bookings().len(), 1);
+            let log: Vec<AuditAction> = facility.audit_log().iter().map(|e| e.action).collect();
+            assert_eq!(log, vec![AuditAction::Booked, AuditAction::Confirmed]);
+        }
+    }
+
+    #[test]
+    fn test_transaction_abort(){
+        let clock: SharedClock = Arc::new(ManualClock::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // a non-vip user books the room
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        start_users_with_sink(vec![1], vec![false], vec![usr1_bookings], clock.clone(), Arc::new(VecSink::new())).join_all();
+        let log_len = rooms_arc.read().unwrap().audit_log().len();
+
+        // a vip holds a compound that would displace the room booking, then backs out
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        let parts = compound_of(&user, vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())]);
+        let transaction = CompoundTransaction::begin(parts.clone(), clock);
+        assert!(transaction.possible());
+        transaction.abort();
+
+        // we expect the facilities as they were before the transaction
+        let rooms = rooms_arc.read().unwrap();
+        assert_eq!(rooms.bookings().len(), 1);
+        assert_eq!(rooms.bookings()[0].read().unwrap().status, CONFIRMED);
+        assert_eq!(rooms.audit_log().len(), log_len);
+        let projectors = projectors_arc.read().unwrap();
+        assert!(projectors.bookings().is_empty());
+        assert!(projectors.audit_log().is_empty());
+        assert!(parts.iter().all(|b| b.read().unwrap().status == UNCONFIRMED));
+    }
+
+    #[test]
+    fn test_transaction_dropped_aborts(){
+        let clock: SharedClock = Arc::new(ManualClock::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: None });
+        let parts = compound_of(&user, vec![BookingSkeleton::new(10, 20, rooms_arc.clone())]);
+
+        // the transaction holds the room until it goes out of scope
+        {
+            let _transaction = CompoundTransaction::begin(parts.clone(), clock.clone());
+            assert_eq!(rooms_arc.read().unwrap().bookings().len(), 1);
+        }
+
+        // we expect the hold released, so another user can book the room
+        assert!(rooms_arc.read().unwrap().bookings().is_empty());
+        assert_eq!(parts[0].read().unwrap().status, UNCONFIRMED);
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        start_users_with_sink(vec![2], vec![false], vec![usr2_bookings], clock, Arc::new(VecSink::new())).join_all();
+        assert_eq!(rooms_arc.read().unwrap().confirmed_count(), 1);
+    }
+
+    #[test]
+    fn test_1user_1compound_self_overlap_rejected_early(){
+        // frozen program time, so the booking can't run into the past
+        let program_time = Arc::new(ProgramTime::new(0, u32::MAX));
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // the user wants the only room twice at overlapping times, and a projector
+        let sink = Arc::new(VecSink::new());
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(15, 25, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users_with_sink(vec![1], vec![false], vec![usr1_bookings], program_time, sink.clone()).join_all();
+
+        // we expect the overlapping parts rejected and the facilities untouched
+        let events = sink.events();
+        let ids: Vec<u64> = events.iter().filter_map(|e| e.booking_id()).collect();
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+        assert_eq!(events, vec![
+            BookingEvent::Rejected { id: ids[0], user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20, reason: RejectReason::SelfOverlap },
+            BookingEvent::Rejected { id: ids[1], user_id: 1, vip: false, fac_type: ROOM, start: 15, end: 25, reason: RejectReason::SelfOverlap },
+            BookingEvent::CompoundRejected { user_id: 1, vip: false },
+        ]);
+        for facility in [&rooms_arc, &projectors_arc] {
+            let facility = facility.read().unwrap();
+            assert!(facility.bookings().is_empty());
+            assert!(facility.audit_log().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_compound_partner_cancellation_reason(){
+        let clock = Arc::new(ManualClock::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // a non-vip user books a room and a projector together
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone())];
+        start_users_with_sink(vec![1], vec![false], vec![usr1_bookings], clock.clone(), Arc::new(VecSink::new())).join_all();
+
+        // a vip takes the room over, so the compound of the first user can't be kept
+        let sink = Arc::new(VecSink::new());
+        let usr2_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone())];
+        start_users_with_sink(vec![1], vec![true], vec![usr2_bookings], clock, sink.clone()).join_all();
+
+        // we expect the room to be cancelled as of the vip and the projector as of the compound failing
+        let events = sink.events();
+        let (room_id, projector_id) = (booking_id(&rooms_arc, 10, CANCELLED), booking_id(&projectors_arc, 10, CANCELLED));
+        assert!(events.contains(&BookingEvent::CancelledByVip { id: room_id, user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20 }));
+        assert!(events.contains(&BookingEvent::CancelledCompoundFailure { id: projector_id, user_id: 1, vip: false, fac_type: PROJECTOR, start: 10, end: 20 }));
+        assert!(!events.contains(&BookingEvent::CancelledByVip { id: projector_id, user_id: 1, vip: false, fac_type: PROJECTOR, start: 10, end: 20 }));
+        assert_eq!(rooms_arc.read().unwrap().audit_log().last().unwrap().reason, AuditReason::VipBooking);
+        assert_eq!(projectors_arc.read().unwrap().audit_log().last().unwrap().reason, AuditReason::CompoundFailure);
+    }
+
+    #[test]
+    fn test_cascade_3interlinked_compounds(){
+        let clock = Arc::new(ManualClock::new());
+
+        // create facilities, the compounds are linked in a ring over them
+        let x_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let y_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+        let z_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, x_arc.clone()), BookingSkeleton::new(10, 20, y_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(30, 40, y_arc.clone()), BookingSkeleton::new(30, 40, z_arc.clone())];
+        let usr3_bookings = vec![BookingSkeleton::new(50, 60, z_arc.clone()), BookingSkeleton::new(50, 60, x_arc.clone())];
+        start_users_with_sink(vec![1, 2, 3], vec![false, false, false], vec![usr1_bookings, usr2_bookings, usr3_bookings], clock.clone(), Arc::new(VecSink::new())).join_all();
+
+        // a vip takes both parts of the first compound and one of the second
+        let sink = Arc::new(VecSink::new());
+        let vip_bookings = vec![BookingSkeleton::new(10, 20, x_arc.clone()), BookingSkeleton::new(10, 20, y_arc.clone()), BookingSkeleton::new(30, 40, z_arc.clone())];
+        start_users_with_sink(vec![1], vec![true], vec![vip_bookings], clock, sink.clone()).join_all();
+
+        // we expect the first two compounds to be cancelled, each booking once, and the third one to be untouched
+        let cancellations: Vec<BookingEvent> = sink.events().into_iter().filter(|e| matches!(e, BookingEvent::CancelledByVip { .. } | BookingEvent::CancelledCompoundFailure { .. })).collect();
+        assert_eq!(cancellations, vec![
+            BookingEvent::CancelledByVip { id: booking_id(&x_arc, 10, CANCELLED), user_id: 1, vip: false, fac_type: ROOM, start: 10, end: 20 },
+            BookingEvent::CancelledCompoundFailure { id: booking_id(&y_arc, 10, CANCELLED), user_id: 1, vip: false, fac_type: PROJECTOR, start: 10, end: 20 },
+            BookingEvent::CancelledByVip { id: booking_id(&z_arc, 30, CANCELLED), user_id: 2, vip: false, fac_type: ROOM, start: 30, end: 40 },
+            BookingEvent::CancelledCompoundFailure { id: booking_id(&y_arc, 30, CANCELLED), user_id: 2, vip: false, fac_type: PROJECTOR, start: 30, end: 40 },
+        ]);
+        for (facility, cancelled) in [(&x_arc, 1), (&y_arc, 2), (&z_arc, 1)] {
+            let facility = facility.read().unwrap();
+            assert_eq!(facility.audit_log().iter().filter(|e| e.action == AuditAction::Cancelled).count(), cancelled);
+            assert!(facility.bookings().iter().filter(|b| b.read().unwrap().start == 50).all(|b| b.read().unwrap().status == CONFIRMED));
+        }
+        assert_eq!(sink.events().last(), Some(&BookingEvent::CompoundConfirmed { user_id: 1, vip: true }));
+    }
+
+    #[test]
+    fn test_on_cancel_once_per_booking_in_cascade(){
+        let clock = Arc::new(ManualClock::new());
+
+        // create facilities, each noting down the ids of its cancelled bookings
+        let x_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let y_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+        let z_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let cancelled_ids = Arc::new(RwLock::new(Vec::new()));
+        for facility in [&x_arc, &y_arc, &z_arc] {
+            let cancelled_ids = cancelled_ids.clone();
+            facility.write().unwrap().on_cancel = Some(Arc::new(move |b: &Booking| cancelled_ids.write().unwrap().push(b.id)));
+        }
+
+        // two linked compounds of non-vips, then a vip taking one part of each
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, x_arc.clone()), BookingSkeleton::new(10, 20, y_arc.clone())];
+        let usr2_bookings = vec![BookingSkeleton::new(30, 40, y_arc.clone()), BookingSkeleton::new(30, 40, z_arc.clone())];
+        start_users_with_sink(vec![1, 2], vec![false, false], vec![usr1_bookings, usr2_bookings], clock.clone(), Arc::new(VecSink::new())).join_all();
+        assert!(cancelled_ids.read().unwrap().is_empty());
+        let vip_bookings = vec![BookingSkeleton::new(10, 20, x_arc.clone()), BookingSkeleton::new(30, 40, z_arc.clone())];
+        start_users_with_sink(vec![1], vec![true], vec![vip_bookings], clock, Arc::new(VecSink::new())).join_all();
+
+        // we expect the hook to have run once for each of the four cancelled bookings
+        let mut ids = cancelled_ids.read().unwrap().clone();
+        ids.sort();
+        let mut expected = vec![booking_id(&x_arc, 10, CANCELLED), booking_id(&y_arc, 10, CANCELLED), booking_id(&y_arc, 30, CANCELLED), booking_id(&z_arc, 30, CANCELLED)];
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn test_verify_consistency(){
+        let clock = Arc::new(ManualClock::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // a non-vip books a compound, a vip takes the room over
+        let usr1_bookings = vec![BookingSkeleton::new(10, 20, rooms_arc.clone()), BookingSkeleton::new(10, 20, projectors_arc.clone()), BookingSkeleton::new(30, 40, projectors_arc.clone())];
+        start_users_with_sink(vec![1], vec![false], vec![usr1_bookings], clock.clone(), Arc::new(VecSink::new())).join_all();
+        let usr2_bookings = vec![BookingSkeleton::new(15, 25, rooms_arc.clone())];
+        start_users_with_sink(vec![1], vec![true], vec![usr2_bookings], clock, Arc::new(VecSink::new())).join_all();
+        let facilities = vec![rooms_arc.clone(), projectors_arc.clone()];
+
+        // we expect the bookings to be consistent after the cascade
+        assert_eq!(verify_consistency(&facilities), Ok(()));
+
+        // but not once the vip booking refers to the projectors
+        let vip_booking = rooms_arc.read().unwrap().bookings().iter().find(|b| b.read().unwrap().status == CONFIRMED).unwrap().clone();
+        vip_booking.write().unwrap().facility = projectors_arc.clone();
+        let id = vip_booking.read().unwrap().id;
+        let projectors_id = projectors_arc.read().unwrap().id;
+        let rooms_id = rooms_arc.read().unwrap().id;
+        assert_eq!(verify_consistency(&facilities), Err(vec![format!("booking #{} is confirmed in facility {} but refers to facility {}", id, rooms_id, projectors_id)]));
+    }
+
+    #[test]
+    fn test_vip_compound_parts_displace_distinct_bookings(){
+        let clock = Arc::new(ManualClock::new());
+
+        // create facilities, two non-vips fill the room
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        let skeleton = || BookingSkeleton::new(10, 20, rooms_arc.clone());
+        start_users_with_sink(vec![1, 2], vec![false, false], vec![vec![skeleton()], vec![skeleton()]], clock.clone(), Arc::new(VecSink::new())).join_all();
+        assert_eq!(rooms_arc.read().unwrap().confirmed_count(), 2);
+
+        // a vip books two parts of a compound in the room
+        let sink = Arc::new(VecSink::new());
+        start_users_with_sink(vec![1], vec![true], vec![vec![skeleton(), skeleton()]], clock.clone(), sink.clone()).join_all();
+
+        // we expect each part to displace a booking of its own
+        let rooms = rooms_arc.read().unwrap();
+        assert!(rooms.confirmed_count() <= rooms.capacity as usize);
+        assert_eq!(rooms.bookings().iter().filter(|b| b.read().unwrap().status == CONFIRMED).map(|b| b.read().unwrap().user().vip).collect::<Vec<_>>(), vec![true, true]);
+        assert_eq!(sink.events().iter().filter(|e| matches!(e, BookingEvent::CancelledByVip { .. })).count(), 2);
+        drop(rooms);
+        assert_eq!(verify_consistency(std::slice::from_ref(&rooms_arc)), Ok(()));
+    }
+
+    #[test]
+    fn test_vip_compound_part_without_victim_fails(){
+        let clock = Arc::new(ManualClock::new());
+
+        // create facilities, a non-vip and a vip fill the room
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        let skeleton = || BookingSkeleton::new(10, 20, rooms_arc.clone());
+        start_users_with_sink(vec![1, 2], vec![false, true], vec![vec![skeleton()], vec![skeleton()]], clock.clone(), Arc::new(VecSink::new())).join_all();
+
+        // another vip books two parts in the room, only one booking can be displaced
+        start_users_with_sink(vec![1], vec![true], vec![vec![skeleton(), skeleton()]], clock, Arc::new(VecSink::new())).join_all();
+
+        // we expect the room to stay within its capacity
+        let rooms = rooms_arc.read().unwrap();
+        assert!(rooms.confirmed_count() <= rooms.capacity as usize);
+        drop(rooms);
+        assert_eq!(verify_consistency(std::slice::from_ref(&rooms_arc)), Ok(()));
+    }
+
+    #[test]
+    fn test_snapshot_after_each_mutation(){
+        let clock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+        let (tx, _rx) = mpsc::channel();
+        let user = |id: u32| Arc::new(User { id, vip: false, adress: tx.clone(), allowed_facility_types: None });
+
+        // create facilities, the room queues bookings it has no room for
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        rooms_arc.write().unwrap().waitlist_enabled = true;
+        let other_arc = Arc::new(RwLock::new(Facility::new(ROOM, 3)));
+        let (room, other) = (rooms_arc.read().unwrap().id, other_arc.read().unwrap().id);
+        let spans = |id: u32| snapshot_of(id).iter().map(|v| (v.user_id, v.start, v.status)).collect::<Vec<_>>();
+        assert!(spans(room).is_empty());
+
+        // a booking and one on the waitlist
+        let first = Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user(1))));
+        book_facility(first.clone(), clock.clone(), &sink);
+        book_facility(Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), user(2)))), clock.clone(), &sink);
+        assert_eq!(spans(room), vec![(1, 10, CONFIRMED), (2, 10, WAITLISTED)]);
+
+        // moving the booking
+        reschedule(&first, 30, 40, clock.clone());
+        assert_eq!(spans(room), vec![(1, 30, CONFIRMED), (2, 10, WAITLISTED)]);
+
+        // a tentative booking, which is confirmed, and one which expires
+        let tentative = reserve(&rooms_arc, 50, 60, user(3), clock.clone()).unwrap();
+        assert_eq!(spans(room)[1], (3, 50, TENTATIVE));
+        confirm(&tentative, clock.clone());
+        assert_eq!(spans(room)[1], (3, 50, CONFIRMED));
+        reserve(&rooms_arc, 70, 80, user(4), clock.clone()).unwrap();
+        clock.tick(2);
+        reap_unconfirmed(std::slice::from_ref(&rooms_arc), 1, &(clock.clone() as SharedClock), &sink);
+        assert_eq!(spans(room)[2], (4, 70, CANCELLED));
+
+        // exchanging two bookings and cancelling one
+        swap_bookings(&first, &tentative);
+        assert_eq!(spans(room), vec![(1, 50, CONFIRMED), (3, 30, CONFIRMED), (4, 70, CANCELLED), (2, 10, WAITLISTED)]);
+        cancel_compound(&first, clock.clone());
+        assert_eq!(spans(room)[0], (1, 50, CANCELLED));
+
+        // the bookings of pools
+        let mut pool = FacilityPool::new(ROOM);
+        pool.add(other_arc.clone());
+        book_in_pool(&pool, &BookingSkeleton::new(10, 20, other_arc.clone()), user(5), clock.clone(), &sink);
+        book_n_of(std::slice::from_ref(&other_arc), 1, 10, 20, user(6), clock.clone(), &sink).unwrap();
+        book_across_pools(&[vec![other_arc.clone()]], 10, 20, user(7), clock.clone(), &sink).unwrap();
+        assert_eq!(spans(other), vec![(5, 10, CONFIRMED), (6, 10, CONFIRMED), (7, 10, CONFIRMED)]);
+
+        // and a restored facility
+        let before = spans(room);
+        restore(&snapshot(std::slice::from_ref(&rooms_arc)));
+        assert_eq!(spans(room), before);
+    }
+
+    #[test]
+    fn test_commit_and_cancel_while_cancelling_no_deadlock(){
+        let clock: SharedClock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // create facilities
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // one thread books compounds of a non-vip, displaces them by those of a vip and cancels these, while
+        // another one cancels the bookings of someone else, which locks the facilities and then their bookings
+        let (done_tx, done_rx) = mpsc::channel();
+        let start = Arc::new(Barrier::new(2));
+        let (facilities, c, s) = (vec![rooms_arc.clone(), projectors_arc.clone()], clock.clone(), start.clone());
+        let canceller = thread::spawn(move || {
+            s.wait();
+            for _ in 0..1000 {
+                cancel_all_for_user(3, &facilities, c.clone());
+            }
+        });
+        let (rooms, projectors) = (rooms_arc.clone(), projectors_arc.clone());
+        thread::spawn(move || {
+            let (tx, _rx) = mpsc::channel();
+            let nonvip = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+            let vip = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+            start.wait();
+            for i in 0..100 {
+                for user in [&nonvip, &vip] {
+                    let parts = compound_of(user, vec![BookingSkeleton::new(i * 10, i * 10 + 10, rooms.clone()), BookingSkeleton::new(i * 10, i * 10 + 10, projectors.clone())]);
+                    CompoundTransaction::begin(parts.clone(), clock.clone()).commit(&sink);
+                    if user.vip {
+                        cancel_compound(&parts[0], clock.clone());
+                    }
+                }
+            }
+            canceller.join().unwrap();
+            done_tx.send(()).unwrap();
+        });
+
+        // we expect both to finish, with nothing left booked
+        assert!(done_rx.recv_timeout(Duration::from_secs(10)).is_ok());
+        assert_eq!(rooms_arc.read().unwrap().confirmed_count(), 0);
+        assert_eq!(projectors_arc.read().unwrap().confirmed_count(), 0);
+    }
+
+    #[test]
+    fn test_compound_displacement_off_and_victim_policy(){
+        let clock: SharedClock = Arc::new(ManualClock::new());
+        let skeleton = |facility: &Arc<RwLock<Facility>>, start: u32, end: u32| BookingSkeleton::new(start, end, facility.clone());
+
+        // a full room which doesn't allow displacements
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        rooms_arc.write().unwrap().allow_displacement = false;
+        start_users_with_sink(vec![1], vec![false], vec![vec![skeleton(&rooms_arc, 10, 20)]], clock.clone(), Arc::new(VecSink::new())).join_all();
+
+        // we expect the vip compound to be rejected and the non-vip booking to survive
+        let vec_sink = Arc::new(VecSink::new());
+        start_users_with_sink(vec![2], vec![true], vec![vec![skeleton(&rooms_arc, 10, 20)]], clock.clone(), vec_sink.clone()).join_all();
+        assert_eq!(vec_sink.events(), vec![BookingEvent::CompoundRejected { user_id: 1, vip: true }]);
+        assert_eq!(booking_id(&rooms_arc, 10, CONFIRMED), rooms_arc.read().unwrap().bookings()[0].read().unwrap().id);
+
+        // a full room cancelling the booking with the least remaining time first
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 2)));
+        rooms_arc.write().unwrap().victim_policy = VictimPolicy::LeastRemainingTime;
+        start_users_with_sink(vec![1], vec![false], vec![vec![skeleton(&rooms_arc, 10, 30)]], clock.clone(), Arc::new(VecSink::new())).join_all();
+        start_users_with_sink(vec![1], vec![false], vec![vec![skeleton(&rooms_arc, 12, 20)]], clock.clone(), Arc::new(VecSink::new())).join_all();
+
+        // we expect the vip compound to displace the booking ending first, not the one found first
+        start_users_with_sink(vec![2], vec![true], vec![vec![skeleton(&rooms_arc, 15, 20)]], clock, Arc::new(VecSink::new())).join_all();
+        let rooms = rooms_arc.read().unwrap();
+        let stati: Vec<(u32, u32)> = rooms.bookings().iter().map(|b| (b.read().unwrap().start, b.read().unwrap().status)).collect();
+        assert_eq!(stati, vec![(10, CONFIRMED), (12, CANCELLED), (15, CONFIRMED)]);
+    }
+
+    #[test]
+    fn test_compound_protect_window(){
+        let clock = Arc::new(ManualClock::new());
+        let skeleton = |facility: &Arc<RwLock<Facility>>, start: u32, end: u32| BookingSkeleton::new(start, end, facility.clone());
+
+        // a full room protecting the bookings starting within 5 ticks
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        rooms_arc.write().unwrap().protect_window = Some(5);
+        start_users_with_sink(vec![1], vec![false], vec![vec![skeleton(&rooms_arc, 10, 20)]], clock.clone(), Arc::new(VecSink::new())).join_all();
+
+        // we expect a vip compound to be rejected once the booking starts soon
+        clock.tick(6);
+        start_users_with_sink(vec![2], vec![true], vec![vec![skeleton(&rooms_arc, 10, 20)]], clock.clone(), Arc::new(VecSink::new())).join_all();
+        assert_eq!(rooms_arc.read().unwrap().confirmed_count(), 1);
+        assert_eq!(rooms_arc.read().unwrap().bookings()[0].read().unwrap().status, CONFIRMED);
+    }
+
+    #[test]
+    fn test_compound_victim_by_booking_order(){
+        let clock: SharedClock = Arc::new(ManualClock::new());
+        let sink: SharedSink = Arc::new(VecSink::new());
+
+        // a room full of identical non-vip bookings, added in another order than they were created in
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 3)));
+        let (tx, _rx) = mpsc::channel();
+        let nonvip = Arc::new(User { id: 1, vip: false, adress: tx.clone(), allowed_facility_types: None });
+        let bookings: Vec<Arc<RwLock<Booking>>> = (0..3).map(|_| Arc::new(RwLock::new(Booking::new(10, 20, rooms_arc.clone(), nonvip.clone())))).collect();
+        for b in [&bookings[2], &bookings[0], &bookings[1]] {
+            b.write().unwrap().status = CONFIRMED;
+            rooms_arc.write().unwrap().add_booking(b.clone(), 10, 20);
+        }
+
+        // we expect a vip compound to displace the booking created first
+        let vip = Arc::new(User { id: 2, vip: true, adress: tx, allowed_facility_types: None });
+        let parts = compound_of(&vip, vec![BookingSkeleton::new(10, 20, rooms_arc.clone())]);
+        CompoundTransaction::begin(parts, clock).commit(&sink);
+        let stati: Vec<u32> = bookings.iter().map(|b| b.read().unwrap().status).collect();
+        assert_eq!(stati, vec![CANCELLED, CONFIRMED, CONFIRMED]);
+    }
+
+    #[test]
+    fn test_compound_not_permitted(){
+        let clock: SharedClock = Arc::new(ManualClock::new());
+        let rooms_arc = Arc::new(RwLock::new(Facility::new(ROOM, 1)));
+        let projectors_arc = Arc::new(RwLock::new(Facility::new(PROJECTOR, 1)));
+
+        // we expect the room part of a user who may only book projectors to be rejected
+        let (tx, _rx) = mpsc::channel();
+        let user = Arc::new(User { id: 1, vip: false, adress: tx, allowed_facility_types: Some(PROJECTOR) });
+        let parts = compound_of(&user, vec![
+            BookingSkeleton::new(10, 20, projectors_arc.clone()),
+            BookingSkeleton::new(10, 20, rooms_arc.clone()),
+        ]);
+        let transaction = CompoundTransaction::begin(parts, clock);
+        assert_eq!(transaction.results(), &[Ok(()), Err(RejectReason::NotPermitted)]);
+        assert!(!transaction.possible());
+        transaction.abort();
+        assert_eq!(rooms_arc.read().unwrap().confirmed_count(), 0);
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,33 @@
+use crate::BookingStatus;
+use crate::Facility;
+use crate::CONFIRMED;
+use std::sync::{Arc, RwLock};
+
+// This function checks that the confirmed bookings of the facility never exceed its capacity.
+// The units of the confirmed bookings running can only grow where one of them starts, so they are
+// counted at each distinct start and end of the confirmed bookings, against the capacity there.
+// A violation panics with the first time it is found at, the count and the capacity, e.g.
+// "capacity invariant violated for facility 4 at time 10: 3 confirmed bookings, capacity 2",
+// where bookings of several units count as that many.
+pub fn assert_capacity_invariant(facility: &Arc<RwLock<Facility>>) {
+    let facility = facility.read().unwrap();
+    let confirmed: Vec<(u32, u32, u32)> = facility.bookings().iter().map(|b| b.read().unwrap()).filter(|b| b.status == CONFIRMED).map(|b| (b.start, b.end, b.units)).collect();
+
+    let mut boundaries: Vec<u32> = confirmed.iter().flat_map(|(start, end, _)| [*start, *end]).collect();
+    boundaries.sort();
+    boundaries.dedup();
+
+    for t in boundaries {
+        let running: u32 = confirmed.iter().filter(|(start, end, _)| *start <= t && t < *end).map(|(_, _, units)| units).sum();
+        let capacity = facility.capacity_between(t, t + 1);
+        assert!(running == 0 || !facility.is_full(running - 1, capacity), "capacity invariant violated for facility {} at time {}: {} confirmed bookings, capacity {}", facility.id, t, running, capacity);
+    }
+}
+
+// This function returns the id of the booking of the facility starting at start with the given status,
+// e.g. to tell which booking an event was about. It panics if there is none.
+pub fn booking_id(facility: &Arc<RwLock<Facility>>, start: u32, status: BookingStatus) -> u64 {
+    let facility = facility.read().unwrap();
+    let id = facility.bookings().iter().map(|b| b.read().unwrap()).find(|b| b.start == start && b.status == status).map(|b| b.id);
+    id.expect("no booking with this start and status")
+}
@@ -0,0 +1,386 @@
+///////////////////////////////////////////////////////////////////////
+//////////////// Simple Reservations System (Task 2) //////////////////
+///////////////////////////////////////////////////////////////////////
+
+// System:  The base system is the same as in Task 1, but now we have
+//          vip- and non-vip users where bookings of vip users
+//          lead to the cancellation of non-vip bookings if necessary.
+//          VIPs cannot overwrite other VIPs' bookings.
+
+// Implementation:  Different from before users now have an inbox on
+//                  which they receive cancellation messages (a channel).
+//                  Bookings now have a status (unconfirmed, confirmed, cancelled),
+//                  where on cancellation the status of the booking (in the list of bookings
+//                  of the facility) is changed to cancelled and the user notified.
+//                  The facility keeps all bookings but only confirmed bookings are counted
+//                  in the capacity checks.
+
+//                  Which bookings a booking may displace is up to a displacement policy,
+//                  by default vips displace non-vips.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{confirmed_overlaps, conflicts, overlapping_confirmed, select_victim_excluding, wait_for_cancellations, AuditAction, AuditEntry, AuditReason, Booking, BookingEvent, BookingResult, BookingSkeleton, BookingView, Facility, RejectReason, SharedClock, SharedSink, Stats, StatsSink, StdoutSink, User, Users, CANCELLED, CONFIRMED};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+use std::sync::mpsc;
+
+/////////////////////// Displacement policy /////////////////////
+
+// The displacement policies are shared with the compound system, so they live in the library.
+pub use crate::{DisplacementPolicy, SharedPolicy, VipPolicy};
+
+/////////////////////// Victim selection /////////////////////
+
+// This function selects which booking a vip booking displaces. Only confirmed non-vip bookings
+// overlapping the vip booking and not starting within the protect window are candidates; if the facility is fair the users cancelled least
+// so far are preferred, among them the victim policy of the facility decides.
+pub fn select_victim(facility: &Facility, booking: &Booking, now: u32) -> Option<Arc<RwLock<Booking>>> {
+    select_victim_excluding(facility, &overlapping_confirmed(facility, booking), booking, now, &[], &VipPolicy)
+}
+
+// This function returns copies of the confirmed non-vip bookings a vip booking from start to end could
+// displace, e.g. to warn a vip which bookings their booking would bump. If the vip booking fits as is or the
+// facility allows no displacements, nobody is bumped and none are returned. It only takes a read lock on the facility.
+pub fn displaceable(facility: &Arc<RwLock<Facility>>, start: u32, end: u32) -> Vec<BookingView> {
+    let facility = facility.read().unwrap();
+    let mut overlaps = 0;
+    let mut views = Vec::new();
+    for b in facility.conflicting(start, end) {
+        let b = b.read().unwrap();
+        if conflicts(&b, start, end, facility.buffer) && b.status == CONFIRMED {
+            overlaps += facility.weight_for(b.user()) * b.units;
+            if !b.user().vip {
+                views.push(BookingView { id: b.id, facility_id: facility.id, fac_type: facility.fac_type, user_id: b.user().id, start: b.start, end: b.end, status: b.status });
+            }
+        }
+    }
+    if !facility.is_full(overlaps, facility.capacity_between(start, end)) || !facility.allow_displacement {
+        return Vec::new();
+    }
+    views
+}
+
+/////////////////////// User server /////////////////////
+
+// The error when the users can't be started as the user ids, their vip stati and their bookings
+// don't line up, the lengths of the three are given.
+#[derive(Debug, PartialEq)]
+pub enum StartError {
+    MismatchedLengths { user_ids: usize, user_stati: usize, bookings: usize },
+}
+
+// This function starts the users with each living in a separate thread. Each user is given a list of bookings
+// to try to book, a user with an empty list only waits for the shutdown. The booking events are printed to stdout.
+// The stats of the returned users are filled in by the user threads as they go.
+pub fn start_users(user_ids: Vec<u32>, user_stati: Vec<bool>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock) -> Result<Users, StartError> {
+    start_users_with_sink(user_ids, user_stati, bookings, program_time, Arc::new(StdoutSink))
+}
+
+// Same as start_users, but the booking events are handed to the given sink.
+pub fn start_users_with_sink(user_ids: Vec<u32>, user_stati: Vec<bool>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock, sink: SharedSink) -> Result<Users, StartError> {
+    start_users_with_options(user_ids, user_stati, bookings, program_time, sink, None)
+}
+
+// Same as start_users_with_sink, but a booking rejected as the capacity is exceeded is retried,
+// with retry being the number of attempts and the number of ticks to wait before each of them.
+pub fn start_users_with_retry(user_ids: Vec<u32>, user_stati: Vec<bool>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock, sink: SharedSink, retry: (u32, u32)) -> Result<Users, StartError> {
+    start_users_with_options(user_ids, user_stati, bookings, program_time, sink, Some(retry))
+}
+
+fn start_users_with_options(user_ids: Vec<u32>, user_stati: Vec<bool>, bookings: Vec<Vec<BookingSkeleton>>, program_time: SharedClock, sink: SharedSink, retry: Option<(u32, u32)>) -> Result<Users, StartError> {
+    // every user needs a vip status and a list of bookings
+    if user_stati.len() != user_ids.len() || bookings.len() != user_ids.len() {
+        return Err(StartError::MismatchedLengths { user_ids: user_ids.len(), user_stati: user_stati.len(), bookings: bookings.len() });
+    }
+
+    // count all events of the users on the way to the sink
+    let stats = Arc::new(RwLock::new(Stats::default()));
+    let sink: SharedSink = Arc::new(StatsSink { stats: stats.clone(), inner: sink });
+
+    // tells the users to stop waiting for cancellation messages
+    let shutdown = Arc::new(RwLock::new(false));
+
+    // start the user threads
+    let threads: Vec<_> = (1..=user_ids.len()).enumerate().map(|(i, user_id)| {
+
+        // create the channel for receiving / sending cancellation messages
+        let (tx, rx) = mpsc::channel();
+        let user = Arc::new(User { id: user_id as u32, vip: user_stati[i], adress: tx, allowed_facility_types: None });
+
+        // create list of bookings of the user from the booking skeletons
+        let mut user_bookings: Vec<Arc<RwLock<Booking>>> = Vec::new();
+        for booking in &bookings[i] {
+            let user = Arc::clone(&user);
+            let booking = Booking::from_skeleton(booking, user);
+            user_bookings.push(Arc::new(RwLock::new(booking)));
+        }
+
+        // get the user a reference to the program time and the sink
+        let program_time = Arc::clone(&program_time);
+        let sink = Arc::clone(&sink);
+        let shutdown = Arc::clone(&shutdown);
+
+        // reference to the bookings
+        let user_bookings = Arc::new(user_bookings);
+
+        // start the user thread
+        thread::spawn(move || {
+            run_user(user_bookings, program_time, rx, shutdown, sink, retry)
+        })
+    }).collect();
+    // the senders of the notification channels live on in the bookings, so the inboxes never
+    // run dry by themselves; instead the users stop waiting once they are shut down
+    Ok(Users::new(stats, threads, shutdown))
+}
+
+// This function runs a user. It tries to book the facilities in the list of bookings.
+// With retry set to (attempts, wait_ticks), a booking rejected as the capacity is exceeded is tried
+// up to attempts more times: each time the user waits wait_ticks ticks of the program time and books
+// again if the slot has freed up by then.
+// Cancellation messages are received on the inbox until the users are shut down.
+// It returns the result of each booking in the order of the list, after the last retry.
+pub fn run_user(to_book: Arc<Vec<Arc<RwLock<Booking>>>>, program_time: SharedClock, inbox: mpsc::Receiver<Arc<RwLock<Booking>>>, shutdown: Arc<RwLock<bool>>, sink: SharedSink, retry: Option<(u32, u32)>) -> Vec<BookingResult> {
+    let mut results: Vec<BookingResult> = Vec::new();
+    for b in to_book.iter() {
+        let (mut result, _) = book_facility(b.clone(), program_time.clone(), &sink);
+        // now the user might react to the success of the booking
+        if let Some((attempts, wait_ticks)) = retry {
+            let mut attempt = 0;
+            while result == (BookingResult::Rejected { reason: RejectReason::CapacityExceeded }) && attempt < attempts {
+                attempt += 1;
+                wait_for_ticks(&program_time, wait_ticks);
+                if slot_free(b) {
+                    result = book_facility(b.clone(), program_time.clone(), &sink).0;
+                }
+            }
+        }
+        results.push(result);
+    }
+    // wait for cancel messages
+    wait_for_cancellations(inbox, shutdown, &sink);
+    results
+}
+
+// This function waits until the program time has advanced by the given number of ticks.
+fn wait_for_ticks(program_time: &SharedClock, ticks: u32) {
+    let until = program_time.get_current_time().saturating_add(ticks);
+    while program_time.get_current_time() < until {
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+// This function tells whether the facility of the booking has spare capacity for it, only taking read locks.
+fn slot_free(booking: &Arc<RwLock<Booking>>) -> bool {
+    let (facility, start, end, units) = {
+        let b = booking.read().unwrap();
+        (b.facility.clone(), b.start, b.end, b.units)
+    };
+    let facility = facility.read().unwrap();
+    !facility.is_full_for(confirmed_overlaps(&facility, start, end), units, facility.capacity_between(start, end))
+}
+
+/////////////////////// Booking function /////////////////////
+
+// This function books a facility for a user at a given time, if available.
+// It locks the facility and alters the bookings list of the facility,
+// if possible. It returns the result of the booking together with the bookings that were cancelled
+// to make room for it (if any), a vip booking failing on other vip bookings is rejected as of equal priority.
+// It receives the respective RwLocks as arguments and reports what happened to the sink.
+pub fn book_facility(booking: Arc<RwLock<Booking>>, program_time: SharedClock, sink: &SharedSink) -> (BookingResult, Vec<Arc<RwLock<Booking>>>) {
+    book_facility_with_mode(booking, program_time, sink, false)
+}
+
+// Same as book_facility, but a dry run only tells what would happen: the result is the one book_facility
+// would return, with the bookings that would be cancelled, but the bookings, the facility and the sink are left alone.
+pub fn book_facility_with_mode(booking: Arc<RwLock<Booking>>, program_time: SharedClock, sink: &SharedSink, dry_run: bool) -> (BookingResult, Vec<Arc<RwLock<Booking>>>) {
+    book_facility_with_options(booking, program_time, sink, dry_run, &VipPolicy)
+}
+
+// Same as book_facility, but the policy decides which bookings the booking may displace instead of the vip status.
+// A vip booking that can't displace enough bookings is still rejected as of equal priority.
+pub fn book_facility_with_policy(booking: Arc<RwLock<Booking>>, program_time: SharedClock, sink: &SharedSink, policy: SharedPolicy) -> (BookingResult, Vec<Arc<RwLock<Booking>>>) {
+    book_facility_with_options(booking, program_time, sink, false, policy.as_ref())
+}
+
+// This function tells whether the user could book the facility from start to end right now, going through
+// all the checks of book_facility (permissions, time, capacity and whether enough bookings could be displaced)
+// as a dry run, so nothing is booked, cancelled or reported.
+pub fn can_book(user: &Arc<User>, facility: &Arc<RwLock<Facility>>, start: u32, end: u32, program_time: SharedClock) -> bool {
+    let booking = Arc::new(RwLock::new(Booking::dry_run(start, end, facility.clone(), user.clone())));
+    let sink: SharedSink = Arc::new(StdoutSink);
+    matches!(book_facility_with_mode(booking, program_time, &sink, true).0, BookingResult::Confirmed { .. })
+}
+
+fn book_facility_with_options(booking: Arc<RwLock<Booking>>, program_time: SharedClock, sink: &SharedSink, dry_run: bool, policy: &dyn DisplacementPolicy) -> (BookingResult, Vec<Arc<RwLock<Booking>>>) {
+    // in a dry run nothing is reported
+    let emit = |e: &BookingEvent| {
+        if !dry_run {
+            sink.emit(e);
+        }
+    };
+    let mut cancelled: Vec<Arc<RwLock<Booking>>> = Vec::new();
+    let id;
+    {
+        // lock the booking
+        let mut booking_read = booking.write().unwrap();
+
+        // lock the facility
+        let facility_arc = booking_read.facility.clone();
+        let mut facility = facility_arc.write().unwrap();
+
+        // check if the user may book the facility at all
+        if !booking_read.user().may_book(facility.fac_type) {
+            // report User X couldn't book facility Y from time Z to time W - not permitted to book this facility
+            emit(&BookingEvent::Rejected { id: booking_read.id, user_id: booking_read.user().id, vip: booking_read.user().vip, fac_type: facility.fac_type, start: booking_read.start, end: booking_read.end, reason: RejectReason::NotPermitted });
+            return (BookingResult::NotPermitted, Vec::new());
+        }
+
+        // check if the booking is short enough for the facility
+        if let Some(max_duration) = facility.exceeded_max_duration(booking_read.start, booking_read.end) {
+            // report User X couldn't book facility Y from time Z to time W - longer than the facility may be booked
+            emit(&BookingEvent::Rejected { id: booking_read.id, user_id: booking_read.user().id, vip: booking_read.user().vip, fac_type: facility.fac_type, start: booking_read.start, end: booking_read.end, reason: RejectReason::TooLong { max_duration } });
+            return (BookingResult::RejectedTooLong, Vec::new());
+        }
+
+        // check if the booking is in the future
+        if booking_read.start < program_time.get_current_time() {
+            // report User X couldn't book facility Y from time Z to time W - time in the past (current time is T)
+            emit(&BookingEvent::Rejected { id: booking_read.id, user_id: booking_read.user().id, vip: booking_read.user().vip, fac_type: facility.fac_type, start: booking_read.start, end: booking_read.end, reason: RejectReason::TimeInPast { current_time: program_time.get_current_time() } });
+            return (BookingResult::Rejected { reason: RejectReason::TimeInPast { current_time: program_time.get_current_time() } }, Vec::new());
+        }
+
+        // check if the booking is made far enough in advance
+        if facility.too_soon(booking_read.start, program_time.get_current_time()) {
+            // report User X couldn't book facility Y from time Z to time W - booked too short in advance
+            emit(&BookingEvent::Rejected { id: booking_read.id, user_id: booking_read.user().id, vip: booking_read.user().vip, fac_type: facility.fac_type, start: booking_read.start, end: booking_read.end, reason: RejectReason::TooSoon { min_lead: facility.min_lead } });
+            return (BookingResult::RejectedTooSoon, Vec::new());
+        }
+
+        // check if the booking ends within the booking horizon
+        if booking_read.end > program_time.get_horizon_end() {
+            // report User X couldn't book facility Y from time Z to time W - beyond the booking horizon (latest end is T)
+            emit(&BookingEvent::Rejected { id: booking_read.id, user_id: booking_read.user().id, vip: booking_read.user().vip, fac_type: facility.fac_type, start: booking_read.start, end: booking_read.end, reason: RejectReason::BeyondHorizon { horizon_end: program_time.get_horizon_end() } });
+            return (BookingResult::Rejected { reason: RejectReason::BeyondHorizon { horizon_end: program_time.get_horizon_end() } }, Vec::new());
+        }
+
+        // count the overlaps and those the booking may displace, each by its units weighted by its user
+        let now = program_time.get_current_time();
+        let overlapping = overlapping_confirmed(&facility, &booking_read);
+        // (the protected ones the policy would let the booking displace are counted apart, as
+        // it's not their priority that keeps them)
+        let mut overlaps = 0;
+        let mut displaceable = 0;
+        let mut protected = 0;
+        for b in &overlapping {
+            let b = b.read().unwrap();
+            let weight = facility.weight_for(b.user()) * b.units;
+            overlaps += weight;
+            if policy.can_displace(&booking_read, &b) {
+                if facility.protected(&b, now) {
+                    protected += weight;
+                } else {
+                    displaceable += weight;
+                }
+            }
+        }
+        // the weight of the bookings cancelled to make room
+        let mut freed = 0;
+
+        // the capacity may be lower during parts of the time span of the booking, which needs room for its units
+        let capacity = facility.capacity_between(booking_read.start, booking_read.end);
+        let units = booking_read.units;
+
+        // if we are at the capacity limit but there are bookings the booking may displace (by default
+        // those of non-vips for a vip), as many of them are cancelled as needed to get below the capacity limit
+        // (bookings that don't overlap each other can all overlap the vip booking), unless the facility doesn't
+        // allow displacements
+        if facility.allow_displacement && facility.is_full_for(overlaps, units, capacity) && !facility.is_full_for(overlaps - displaceable, units, capacity) {
+            // cancel the displaceable bookings, chosen by the victim policy of the facility
+            while facility.is_full_for(overlaps - freed, units, capacity) {
+                let b = match select_victim_excluding(&facility, &overlapping, &booking_read, now, &cancelled, policy) {
+                    Some(b) => b,
+                    None => break,
+                };
+                freed += {
+                    let victim = b.read().unwrap();
+                    facility.weight_for(victim.user()) * victim.units
+                };
+                // in a dry run the victim is only noted down
+                if dry_run {
+                    cancelled.push(b);
+                    continue;
+                }
+                let mut bmut = b.write().unwrap();
+                emit(&BookingEvent::CancelledByVip { id: bmut.id, user_id: bmut.user().id, vip: bmut.user().vip, fac_type: facility.fac_type, start: bmut.start, end: bmut.end });
+                bmut.status = CANCELLED;
+                facility.cancelled(&bmut);
+                bmut.notify_users(&b);
+                if let Some(fairness) = &facility.fairness {
+                    fairness.record_cancellation(bmut.user().id);
+                }
+                // record the cancellation in the audit log
+                facility.audit_log.push(AuditEntry { time: now, action: AuditAction::Cancelled, user_id: bmut.user().id, start: bmut.start, end: bmut.end, reason: AuditReason::VipBooking });
+                cancelled.push(b.clone());
+            }
+        } 
+        
+        // if the capacity is still exceeded, decline the booking; only a vip kept out by bookings of
+        // equal or higher priority is declined as of equal priority, not one that may displace nobody
+        // in the facility or only finds protected bookings to displace
+        let equal_priority = booking_read.user().vip && facility.allow_displacement && facility.is_full_for(overlaps - displaceable - protected, units, capacity);
+        if facility.is_full_for(overlaps - freed, units, capacity) && !equal_priority {
+            emit(&BookingEvent::Rejected { id: booking_read.id, user_id: booking_read.user().id, vip: booking_read.user().vip, fac_type: facility.fac_type, start: booking_read.start, end: booking_read.end, reason: RejectReason::CapacityExceeded });
+            return (BookingResult::Rejected { reason: RejectReason::CapacityExceeded }, Vec::new());
+        }
+        if facility.is_full_for(overlaps - freed, units, capacity) {
+            // report User X couldn't book facility Y from time Z to time W - cannot displace equal-or-higher priority
+            emit(&BookingEvent::Rejected { id: booking_read.id, user_id: booking_read.user().id, vip: booking_read.user().vip, fac_type: facility.fac_type, start: booking_read.start, end: booking_read.end, reason: RejectReason::EqualPriority });
+            return (BookingResult::RejectedEqualPriority, Vec::new());
+        }
+
+        // here the booking can be done, unless it is only a dry run
+        if dry_run {
+            return (BookingResult::Confirmed { id: booking_read.id }, cancelled);
+        }
+        booking_read.created_at = program_time.get_current_time();
+        facility.add_booking(booking.clone(), booking_read.start, booking_read.end);
+        facility.audit_log.push(AuditEntry { time: program_time.get_current_time(), action: AuditAction::Booked, user_id: booking_read.user().id, start: booking_read.start, end: booking_read.end, reason: AuditReason::UserRequest });
+
+        // change the status of the booking to confirmed while the facility is still locked,
+        // otherwise other users could see it unconfirmed in the facility and overbook it
+        booking_read.status = CONFIRMED;
+        id = booking_read.id;
+        facility.audit_log.push(AuditEntry { time: program_time.get_current_time(), action: AuditAction::Confirmed, user_id: booking_read.user().id, start: booking_read.start, end: booking_read.end, reason: AuditReason::UserRequest });
+
+        // report success
+        emit(&BookingEvent::Confirmed { id: booking_read.id, user_id: booking_read.user().id, vip: booking_read.user().vip, fac_type: facility.fac_type, start: booking_read.start, end: booking_read.end });
+
+        // publish the bookings and tell the operator if the booking took the last of the capacity,
+        // the booking itself is among them so it has to be unlocked first
+        let (start, end) = (booking_read.start, booking_read.end);
+        drop(booking_read);
+        facility.publish();
+        if let Some(on_full) = facility.on_full.clone() {
+            if facility.full_during(start, end) {
+                on_full(&facility);
+            }
+        }
+    }
+
+    (BookingResult::Confirmed { id }, cancelled)
+}
+
+/////////////////////// Example scenario /////////////////////
+
+// The example scenario: 2 non-vip users and 3 vip users competing for the given rooms and projectors.
+// It returns the user ids, their vip stati and their bookings as expected by start_users.
+pub fn example_scenario(rooms_arc: &Arc<RwLock<Facility>>, projectors_arc: &Arc<RwLock<Facility>>) -> (Vec<u32>, Vec<bool>, Vec<Vec<BookingSkeleton>>) {
+    let usr1_bookings = vec![BookingSkeleton::new(1, 2, rooms_arc.clone()), BookingSkeleton::new(1, 2, rooms_arc.clone())];
+    let usr2_bookings = vec![BookingSkeleton::new(1, 2, projectors_arc.clone()), BookingSkeleton::new(1, 2, projectors_arc.clone())];
+    let usr3_bookings = vec![BookingSkeleton::new(1, 2, rooms_arc.clone()), BookingSkeleton::new(1, 2, projectors_arc.clone())];
+    let usr4_bookings = vec![BookingSkeleton::new(1, 2, rooms_arc.clone()), BookingSkeleton::new(1, 2, projectors_arc.clone())];
+    let usr5_bookings = vec![BookingSkeleton::new(1, 2, rooms_arc.clone()), BookingSkeleton::new(1, 2, projectors_arc.clone())];
+    (vec![1, 2, 3, 4, 5], vec![false, false, true, true, true], vec![usr1_bookings, usr2_bookings, usr3_bookings, usr4_bookings, usr5_bookings])
+}